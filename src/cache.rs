@@ -0,0 +1,240 @@
+//! Cache of query results
+//!
+//! A link that sets a TTL caches the results of its read-only queries by
+//! statement, parameters and row format, so identical queries within the TTL
+//! are answered without a connection. A statement the link runs that may change
+//! data drops the results of the queries on the tables it refers to, or all of
+//! them when it names none. Changes made by other links or clients show once
+//! the results expire.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use sqlx::any::AnyKind;
+use wasmcloud_interface_sqldb::{QueryResult, Statement};
+
+use crate::{
+    config::CacheOptions,
+    executor::{tables, Dialect},
+    ext::RowFormat,
+};
+
+/// maximum size of the cached results of a link
+const DEFAULT_MAX_BYTES: u64 = 64 << 20;
+/// estimated size of an entry, on top of its statement, rows and columns
+const ENTRY_OVERHEAD: usize = 256;
+
+/// Cached query results of a link
+pub(crate) struct QueryCache {
+    ttl: Duration,
+    max_bytes: usize,
+    dialect: Dialect,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq)]
+struct Key {
+    database: Option<String>,
+    sql: String,
+    parameters: Option<Vec<Vec<u8>>>,
+    /// row format of the query, if not the link's
+    format: Option<RowFormat>,
+}
+
+struct Entry {
+    result: QueryResult,
+    tables: Vec<String>,
+    expires: Instant,
+    size: usize,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_key: HashMap<Key, Entry>,
+    /// keys by last use, least recent first
+    by_use: BTreeMap<u64, Key>,
+    uses: u64,
+    bytes: usize,
+    /// number of invalidations, so a result read before one isn't cached
+    /// after it
+    generation: u64,
+}
+
+impl QueryCache {
+    /// Cache of a link, or none if it doesn't cache results
+    pub(crate) fn new(options: &CacheOptions, db: AnyKind) -> Option<Self> {
+        let ttl = options.ttl_millis.filter(|ttl| *ttl > 0)?;
+        let max_bytes = options.max_bytes.unwrap_or(DEFAULT_MAX_BYTES);
+        Some(QueryCache {
+            ttl: Duration::from_millis(ttl.into()),
+            max_bytes: usize::try_from(max_bytes).unwrap_or(usize::MAX),
+            dialect: db.into(),
+            entries: Mutex::default(),
+        })
+    }
+
+    /// The cached result of a query, unless it expired
+    pub(crate) fn get(&self, stmt: &Statement, format: Option<RowFormat>) -> Option<QueryResult> {
+        let key = key(stmt, format);
+        let mut entries = self.lock();
+        let entries = &mut *entries;
+        let entry = entries.by_key.get_mut(&key)?;
+        if entry.expires <= Instant::now() {
+            entries.remove(&key);
+            return None;
+        }
+        entries.uses += 1;
+        entries.by_use.remove(&entry.last_used);
+        entry.last_used = entries.uses;
+        let result = entry.result.clone();
+        entries.by_use.insert(entries.uses, key);
+        Some(result)
+    }
+
+    /// Number of invalidations so far, taken before running a query
+    pub(crate) fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    /// Record a query that ran: cache its result if it only reads and nothing
+    /// was invalidated since `generation`, or drop the results it may have
+    /// changed otherwise
+    pub(crate) fn record(
+        &self,
+        stmt: &Statement,
+        format: Option<RowFormat>,
+        generation: u64,
+        result: &QueryResult,
+    ) {
+        if !tables::is_read_only(&stmt.sql, self.dialect) {
+            self.invalidate(&stmt.sql);
+            return;
+        }
+        if result.error.is_some() {
+            return;
+        }
+        let key = key(stmt, format);
+        let tables = tables::table_names(&stmt.sql, self.dialect);
+        let size = ENTRY_OVERHEAD
+            + key.sql.len()
+            + key.database.as_ref().map_or(0, String::len)
+            + key.parameters.iter().flatten().map(Vec::len).sum::<usize>()
+            + result.rows.len()
+            + result
+                .columns
+                .iter()
+                .map(|c| c.name.len() + c.db_type.len() + 32)
+                .sum::<usize>()
+            + tables.iter().map(String::len).sum::<usize>();
+        if size > self.max_bytes {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.lock();
+        if entries.generation != generation {
+            return;
+        }
+        entries.remove(&key);
+        entries.make_room(self.max_bytes - size, now);
+        entries.uses += 1;
+        entries.bytes += size;
+        let last_used = entries.uses;
+        entries.by_use.insert(last_used, key.clone());
+        entries.by_key.insert(
+            key,
+            Entry {
+                result: result.clone(),
+                tables,
+                expires: now + self.ttl,
+                size,
+                last_used,
+            },
+        );
+    }
+
+    /// Drop the results of the queries on the tables a statement may change.
+    /// Read-only statements change nothing.
+    pub(crate) fn invalidate(&self, sql: &str) {
+        if !tables::is_read_only(sql, self.dialect) {
+            self.invalidate_tables(&tables::table_names(sql, self.dialect));
+        }
+    }
+
+    /// Drop the results of the queries on `tables`, as `tables::table_name`
+    /// normalizes them, or all results if there are none. Queries whose
+    /// tables weren't found are always dropped.
+    pub(crate) fn invalidate_tables(&self, tables: &[String]) {
+        let mut entries = self.lock();
+        entries.generation += 1;
+        let stale: Vec<Key> = entries
+            .by_key
+            .iter()
+            .filter(|(_, entry)| {
+                tables.is_empty()
+                    || entry.tables.is_empty()
+                    || entry.tables.iter().any(|table| tables.contains(table))
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in stale {
+            entries.remove(&key);
+        }
+    }
+
+    /// Drop all results
+    pub(crate) fn clear(&self) {
+        self.invalidate_tables(&[]);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        // entries are left consistent between statements, so a panic while
+        // holding the lock doesn't spoil them
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Entries {
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.by_key.remove(key) {
+            self.by_use.remove(&entry.last_used);
+            self.bytes -= entry.size;
+        }
+    }
+
+    /// Evict the expired results, then the least recently used ones, until
+    /// the results take no more than `max_bytes`
+    fn make_room(&mut self, max_bytes: usize, now: Instant) {
+        if self.bytes <= max_bytes {
+            return;
+        }
+        let expired: Vec<Key> = self
+            .by_key
+            .iter()
+            .filter(|(_, entry)| entry.expires <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.remove(&key);
+        }
+        while self.bytes > max_bytes {
+            match self.by_use.pop_first() {
+                Some((_, key)) => self.remove(&key),
+                None => break,
+            }
+        }
+    }
+}
+
+fn key(stmt: &Statement, format: Option<RowFormat>) -> Key {
+    Key {
+        database: stmt.database.clone(),
+        sql: stmt.sql.clone(),
+        parameters: stmt.parameters.clone(),
+        format,
+    }
+}
@@ -1,12 +1,14 @@
 //! Configuration for sqldb-postgres capability provider
 //!
-use std::time::Duration;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use base64::Engine;
 use serde::Deserialize;
 use sqlx::{any::AnyPoolOptions, AnyPool};
 use wasmbus_rpc::{core::LinkDefinition, error::RpcError};
 
+use crate::{executor::ident, ext::RowFormat, session};
+
 /// Configuration for this provider (from link definitions)
 #[derive(Debug, Default, Deserialize)]
 pub(crate) struct Config {
@@ -18,6 +20,25 @@ pub(crate) struct Config {
     /// Optional connection pool information
     #[serde(default)]
     pool: PoolOptions,
+    /// Optional result encoding settings
+    #[serde(default)]
+    pub(crate) encoding: EncodingOptions,
+    /// Optional Postgres channels to listen to, forwarding their notifications
+    /// to the actor
+    #[serde(default)]
+    pub(crate) listen: Vec<String>,
+    /// Optional schema migrations, applied when the link is put
+    #[serde(default)]
+    pub(crate) migrations: MigrationsConfig,
+    /// Optional cache of query results, off unless a TTL is set
+    #[serde(default)]
+    pub(crate) cache: CacheOptions,
+    /// Optional limits on the statements of the link
+    #[serde(default)]
+    pub(crate) limits: LimitOptions,
+    /// Optional session variables and SQL applied to each new connection
+    #[serde(default)]
+    pub(crate) session: SessionOptions,
 }
 
 /// max size of connection pool
@@ -64,11 +85,131 @@ pub(crate) struct PoolOptions {
     connection_timeout_millis: Option<u32>,
 }
 
+/// Cache of query results
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct CacheOptions {
+    /// how long a result is served from the cache
+    /// Default: 0 (None), results aren't cached
+    pub(crate) ttl_millis: Option<u32>,
+
+    /// maximum size of the cached results of the link, least recently used
+    /// results are evicted beyond it
+    /// Default: 64 MiB
+    pub(crate) max_bytes: Option<u64>,
+}
+
+/// Limits on the statements of a link
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct LimitOptions {
+    /// statements started per second, with bursts of up to a second's worth
+    /// Default: None (unlimited)
+    pub(crate) queries_per_second: Option<u32>,
+
+    /// statements running at once, exports included
+    /// Default: None (unlimited)
+    pub(crate) max_concurrent: Option<u32>,
+}
+
+/// Session setup of each new connection. `{actor_id}` in the SQL and the
+/// values is replaced with the id of the linked actor.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct SessionOptions {
+    /// SQL run on each new connection, e.g. `SET TIME ZONE 'UTC'`
+    pub(crate) init_sql: Option<String>,
+
+    /// session variables set on each new connection, by name, e.g.
+    /// `application_name`
+    #[serde(default)]
+    pub(crate) variables: BTreeMap<String, String>,
+}
+
+impl SessionOptions {
+    fn is_empty(&self) -> bool {
+        self.init_sql.is_none() && self.variables.is_empty()
+    }
+}
+
+/// Schema migrations: versioned SQL scripts, read from a directory or listed
+/// in the config
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct MigrationsConfig {
+    /// directory of `<version>_<description>.sql` files
+    pub(crate) dir: Option<String>,
+
+    /// migrations listed in the config
+    #[serde(default)]
+    pub(crate) scripts: Vec<MigrationScript>,
+
+    /// name of the table recording the applied migrations
+    /// Default: _sqldb_migrations
+    pub(crate) table: Option<String>,
+}
+
+impl MigrationsConfig {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.dir.is_none() && self.scripts.is_empty()
+    }
+}
+
+/// A migration listed in the config
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct MigrationScript {
+    /// version, migrations are applied in increasing version order
+    pub(crate) version: i64,
+    /// description, recorded in the history table
+    #[serde(default)]
+    pub(crate) description: String,
+    /// statements of the migration
+    pub(crate) sql: String,
+}
+
+/// Options for encoding query results
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct EncodingOptions {
+    /// how JSON and JSONB values are encoded
+    /// Default: string
+    #[serde(default)]
+    pub(crate) json: JsonEncoding,
+
+    /// how MySQL geometry values are encoded
+    /// Default: wkb
+    #[serde(default)]
+    pub(crate) geometry: GeometryEncoding,
+
+    /// format of the rows in query results
+    /// Default: cbor
+    #[serde(default)]
+    pub(crate) rows: RowFormat,
+}
+
+/// Encoding of JSON and JSONB values in query results
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum JsonEncoding {
+    /// serialized to a JSON string
+    #[default]
+    String,
+    /// transcoded to nested CBOR maps and arrays
+    Cbor,
+}
+
+/// Encoding of geometry values in query results
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum GeometryEncoding {
+    /// WKB (well-known binary) bytes
+    #[default]
+    Wkb,
+    /// a GeoJSON geometry object
+    GeoJson,
+}
+
 /// Load configuration from 'values' field of LinkDefinition.
 /// Support a variety of configuration possibilities:
 ///  'uri' (only) - sets the uri, and uses a default connection pool
 ///  'config_json' - json with 'uri' and 'pool' settings
 ///  'config_b64' - base64-encoded json wih 'uri' and 'pool' settings
+///  'listen' - comma-separated Postgres channels to listen to
 pub(crate) fn load_config(ld: &LinkDefinition) -> Result<Config, RpcError> {
     let mut config = Config::default();
     if let Some(cj) = ld.values.get("config_json") {
@@ -84,19 +225,45 @@ pub(crate) fn load_config(ld: &LinkDefinition) -> Result<Config, RpcError> {
     if let Some(uri) = ld.values.get("uri") {
         config.uri = uri.to_string();
     }
+    if let Some(channels) = ld.values.get("listen") {
+        config.listen = channels
+            .split(',')
+            .map(|channel| channel.trim().to_string())
+            .filter(|channel| !channel.is_empty())
+            .collect();
+    }
+    if let Some(sql) = &mut config.session.init_sql {
+        *sql = sql.replace("{actor_id}", &ld.actor_id);
+    }
+    for (name, value) in config.session.variables.iter_mut() {
+        ident::identifier(name).map_err(|e| RpcError::ProviderInit(format!("session: {}", e)))?;
+        *value = value.replace("{actor_id}", &ld.actor_id);
+    }
+    let is_postgres = config.uri.starts_with("postgres:") || config.uri.starts_with("postgresql:");
     if config.uri.is_empty() {
         Err(RpcError::ProviderInit(
             "link params values are missing 'uri'".into(),
         ))
+    } else if !config.listen.is_empty() && !is_postgres {
+        Err(RpcError::ProviderInit(
+            "'listen' is only supported for Postgres".into(),
+        ))
     } else {
         Ok(config)
     }
 }
 
+impl Config {
+    /// Database connection uri
+    pub(crate) fn uri(&self) -> &str {
+        &self.uri
+    }
+}
+
 /// Create the connection pool based on config settings. This function will not return
 /// until the required number of idle connections has been established.
-pub(crate) async fn create_pool(config: Config) -> Result<AnyPool, RpcError> {
-    let pool = AnyPoolOptions::new()
+pub(crate) async fn create_pool(config: &Config) -> Result<AnyPool, RpcError> {
+    let mut options = AnyPoolOptions::new()
         .max_connections(
             config
                 .pool
@@ -121,8 +288,56 @@ pub(crate) async fn create_pool(config: Config) -> Result<AnyPool, RpcError> {
                 .pool
                 .connection_timeout_millis
                 .unwrap_or(DEFAULT_CONNECTION_TIMEOUT_MILLIS) as u64,
-        ))
+        ));
+    if !config.session.is_empty() {
+        let session = Arc::new(config.session.clone());
+        options = options.after_connect(move |conn, _| {
+            let session = session.clone();
+            Box::pin(async move { session::init(conn, &session).await })
+        });
+    }
+    let pool = options
         .connect_lazy(&config.uri)
         .map_err(|e| RpcError::ProviderInit(format!("initializing db connection pool: {}", e)))?;
     Ok(pool)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(values: &[(&str, &str)]) -> LinkDefinition {
+        let mut ld = LinkDefinition::default();
+        ld.actor_id = "actor".into();
+        ld.values = values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ld
+    }
+
+    #[test]
+    fn listen_channels() {
+        let ld = link(&[
+            ("uri", "postgres://localhost/db"),
+            ("listen", " orders, ,shipments,"),
+        ]);
+        let config = load_config(&ld).unwrap();
+        assert_eq!(config.listen, vec!["orders", "shipments"]);
+
+        let ld = link(&[(
+            "config_json",
+            r#"{"uri": "postgresql://localhost/db", "listen": ["orders"]}"#,
+        )]);
+        assert_eq!(load_config(&ld).unwrap().listen, vec!["orders"]);
+    }
+
+    #[test]
+    fn listen_needs_postgres() {
+        let ld = link(&[("uri", "mysql://localhost/db"), ("listen", "orders")]);
+        assert!(load_config(&ld).is_err());
+        // a value without channels doesn't listen
+        let ld = link(&[("uri", "mysql://localhost/db"), ("listen", " , ")]);
+        assert!(load_config(&ld).unwrap().listen.is_empty());
+    }
+}
@@ -0,0 +1,385 @@
+//! Arrow IPC stream encoding of result rows
+//!
+//! The schema is derived from the result columns, mapping each column's type
+//! name to the kind of value the row encoder of its database writes, and the
+//! CBOR rows are then decoded into one record batch. Types without an Arrow
+//! counterpart among the ones written here (numerics, UUIDs, JSON, enums and
+//! the like) become `Utf8` columns, with non-string values rendered as JSON
+//! text.
+
+mod flatbuf;
+
+use minicbor::{data::Type, Decoder};
+use sqlx::any::AnyKind;
+use time::{
+    format_description::well_known::Rfc3339, Date, OffsetDateTime, PrimitiveDateTime, Time,
+};
+use wasmcloud_interface_sqldb::Column;
+
+use crate::{
+    config::{EncodingOptions, GeometryEncoding},
+    result::{Error, Result},
+};
+
+use self::flatbuf::{Field, Object};
+use super::{
+    datetime::{DATE_FORMAT, TIMESTAMP_FORMAT, TIME_FORMAT},
+    json,
+    kind::ValueKind,
+    mssql, mysql, postgres,
+};
+
+/// `MetadataVersion.V5`
+const METADATA_VERSION: i16 = 4;
+/// `MessageHeader` union members
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+const CONTINUATION: [u8; 4] = [0xff; 4];
+
+/// Arrow type of a column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ArrowType {
+    Null,
+    Bool,
+    Int {
+        bits: u8,
+        signed: bool,
+    },
+    Float32,
+    Float64,
+    Utf8,
+    Binary,
+    /// days since the epoch
+    Date32,
+    /// microseconds since midnight
+    Time64,
+    /// microseconds since the epoch, in UTC when `utc`
+    Timestamp {
+        utc: bool,
+    },
+}
+
+impl ArrowType {
+    const fn int(bits: u8, signed: bool) -> Self {
+        ArrowType::Int { bits, signed }
+    }
+
+    /// Arrow type of the values the row encoder of `db` writes for a type
+    fn of(db: AnyKind, type_name: &str, encoding: &EncodingOptions) -> Self {
+        let kind = match db {
+            AnyKind::Postgres => postgres::value_kind(type_name),
+            AnyKind::MySql => mysql::value_kind(type_name),
+            AnyKind::Mssql => mssql::value_kind(type_name),
+        };
+        match kind {
+            ValueKind::Null => ArrowType::Null,
+            ValueKind::Bool => ArrowType::Bool,
+            ValueKind::Int8 => ArrowType::int(8, true),
+            ValueKind::Int16 => ArrowType::int(16, true),
+            ValueKind::Int32 => ArrowType::int(32, true),
+            ValueKind::Int64 => ArrowType::int(64, true),
+            ValueKind::UInt8 => ArrowType::int(8, false),
+            ValueKind::UInt16 => ArrowType::int(16, false),
+            ValueKind::UInt32 => ArrowType::int(32, false),
+            ValueKind::UInt64 => ArrowType::int(64, false),
+            ValueKind::Float32 => ArrowType::Float32,
+            ValueKind::Float64 => ArrowType::Float64,
+            ValueKind::Text => ArrowType::Utf8,
+            ValueKind::Bytes => ArrowType::Binary,
+            ValueKind::Date => ArrowType::Date32,
+            ValueKind::Time => ArrowType::Time64,
+            ValueKind::Timestamp => ArrowType::Timestamp { utc: false },
+            ValueKind::TimestampTz => ArrowType::Timestamp { utc: true },
+            // MySQL geometries are WKB bytes unless they are GeoJSON objects
+            ValueKind::Other
+                if db == AnyKind::MySql
+                    && type_name == "GEOMETRY"
+                    && encoding.geometry == GeometryEncoding::Wkb =>
+            {
+                ArrowType::Binary
+            }
+            ValueKind::Other => ArrowType::Utf8,
+        }
+    }
+
+    /// `Type` union member and table of the type
+    fn to_flatbuf(self) -> (u8, Object) {
+        match self {
+            ArrowType::Null => (1, Object::Table(vec![])),
+            ArrowType::Int { bits, signed } => (
+                2,
+                Object::Table(vec![Field::I32(bits.into()), Field::Bool(signed)]),
+            ),
+            // precision SINGLE or DOUBLE
+            ArrowType::Float32 => (3, Object::Table(vec![Field::I16(1)])),
+            ArrowType::Float64 => (3, Object::Table(vec![Field::I16(2)])),
+            ArrowType::Binary => (4, Object::Table(vec![])),
+            ArrowType::Utf8 => (5, Object::Table(vec![])),
+            ArrowType::Bool => (6, Object::Table(vec![])),
+            // unit DAY
+            ArrowType::Date32 => (8, Object::Table(vec![Field::I16(0)])),
+            // unit MICROSECOND
+            ArrowType::Time64 => (9, Object::Table(vec![Field::I16(2), Field::I32(64)])),
+            ArrowType::Timestamp { utc } => (
+                10,
+                Object::Table(vec![
+                    Field::I16(2),
+                    match utc {
+                        true => Field::Object(Object::String("UTC".into())),
+                        false => Field::Absent,
+                    },
+                ]),
+            ),
+        }
+    }
+}
+
+/// Values of a column, with their validity bitmap
+struct ColumnData {
+    arrow_type: ArrowType,
+    len: usize,
+    null_count: usize,
+    validity: Vec<u8>,
+    /// value offsets of `Utf8` and `Binary` columns
+    offsets: Vec<u8>,
+    values: Vec<u8>,
+}
+
+impl ColumnData {
+    fn new(arrow_type: ArrowType) -> Self {
+        let offsets = match arrow_type {
+            ArrowType::Utf8 | ArrowType::Binary => 0i32.to_le_bytes().to_vec(),
+            _ => Vec::new(),
+        };
+        ColumnData {
+            arrow_type,
+            len: 0,
+            null_count: 0,
+            validity: Vec::new(),
+            offsets,
+            values: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, decoder: &mut Decoder) -> Result<()> {
+        let index = self.len;
+        self.len += 1;
+        if index / 8 == self.validity.len() {
+            self.validity.push(0);
+            if self.arrow_type == ArrowType::Bool {
+                self.values.push(0);
+            }
+        }
+        let datatype = decoder.datatype()?;
+        if matches!(datatype, Type::Null | Type::Undefined) {
+            decoder.skip()?;
+            self.null_count += 1;
+            self.push_empty();
+            return Ok(());
+        }
+        self.validity[index / 8] |= 1 << (index % 8);
+
+        match self.arrow_type {
+            ArrowType::Null => return Err(Error::CborDeValue("expected null".into())),
+            ArrowType::Bool => {
+                if decoder.bool()? {
+                    self.values[index / 8] |= 1 << (index % 8);
+                }
+            }
+            ArrowType::Int { bits, signed } => {
+                let value = i128::from(decoder.int()?);
+                let fits = match signed {
+                    true => value >> (bits - 1) == 0 || value >> (bits - 1) == -1,
+                    false => value >= 0 && value >> bits == 0,
+                };
+                if !fits {
+                    return Err(Error::CborDeIntOutOfRange(value));
+                }
+                let width = usize::from(bits / 8);
+                self.values.extend_from_slice(&value.to_le_bytes()[..width]);
+            }
+            ArrowType::Float32 => {
+                let value = float(decoder, datatype)? as f32;
+                self.values.extend_from_slice(&value.to_le_bytes());
+            }
+            ArrowType::Float64 => {
+                let value = float(decoder, datatype)?;
+                self.values.extend_from_slice(&value.to_le_bytes());
+            }
+            ArrowType::Utf8 => match datatype {
+                Type::String | Type::StringIndef => {
+                    for chunk in decoder.str_iter()? {
+                        self.values.extend_from_slice(chunk?.as_bytes());
+                    }
+                }
+                _ => {
+                    let value = json::decode_json(decoder)?;
+                    self.values
+                        .extend_from_slice(serde_json::to_string(&value)?.as_bytes());
+                }
+            },
+            ArrowType::Binary => match datatype {
+                Type::String | Type::StringIndef => {
+                    for chunk in decoder.str_iter()? {
+                        self.values.extend_from_slice(chunk?.as_bytes());
+                    }
+                }
+                _ => {
+                    for chunk in decoder.bytes_iter()? {
+                        self.values.extend_from_slice(chunk?);
+                    }
+                }
+            },
+            ArrowType::Date32 => {
+                let date = Date::parse(decoder.str()?, DATE_FORMAT)?;
+                let days = (date - OffsetDateTime::UNIX_EPOCH.date()).whole_days();
+                self.values.extend_from_slice(&(days as i32).to_le_bytes());
+            }
+            ArrowType::Time64 => {
+                let time = Time::parse(decoder.str()?, TIME_FORMAT)?;
+                let micros = (time - Time::MIDNIGHT).whole_microseconds() as i64;
+                self.values.extend_from_slice(&micros.to_le_bytes());
+            }
+            ArrowType::Timestamp { utc } => {
+                let value = decoder.str()?;
+                let timestamp = match utc {
+                    true => OffsetDateTime::parse(value, &Rfc3339)?,
+                    false => PrimitiveDateTime::parse(value, TIMESTAMP_FORMAT)?.assume_utc(),
+                };
+                let micros = (timestamp.unix_timestamp_nanos() / 1000) as i64;
+                self.values.extend_from_slice(&micros.to_le_bytes());
+            }
+        }
+        self.push_offset();
+        Ok(())
+    }
+
+    /// Fill the slot of a null value
+    fn push_empty(&mut self) {
+        let width = match self.arrow_type {
+            ArrowType::Null | ArrowType::Bool | ArrowType::Utf8 | ArrowType::Binary => 0,
+            ArrowType::Int { bits, .. } => usize::from(bits / 8),
+            ArrowType::Float32 | ArrowType::Date32 => 4,
+            ArrowType::Float64 | ArrowType::Time64 | ArrowType::Timestamp { .. } => 8,
+        };
+        self.values.resize(self.values.len() + width, 0);
+        self.push_offset();
+    }
+
+    fn push_offset(&mut self) {
+        if let ArrowType::Utf8 | ArrowType::Binary = self.arrow_type {
+            self.offsets
+                .extend_from_slice(&(self.values.len() as i32).to_le_bytes());
+        }
+    }
+
+    /// Buffers of the column, in layout order
+    fn buffers(&self) -> Vec<&[u8]> {
+        match self.arrow_type {
+            ArrowType::Null => vec![],
+            ArrowType::Utf8 | ArrowType::Binary => {
+                vec![&self.validity, &self.offsets, &self.values]
+            }
+            _ => vec![&self.validity, &self.values],
+        }
+    }
+}
+
+fn float(decoder: &mut Decoder, datatype: Type) -> Result<f64> {
+    match datatype {
+        Type::F16 | Type::F32 | Type::F64 => Ok(decoder.f64()?),
+        _ => Ok(i128::from(decoder.int()?) as f64),
+    }
+}
+
+/// Encode CBOR rows of the columns as an Arrow IPC stream of the schema and a
+/// single record batch
+pub(crate) fn encode(
+    rows: &[u8],
+    columns: &[Column],
+    db: AnyKind,
+    encoding: &EncodingOptions,
+) -> Result<Vec<u8>> {
+    let types: Vec<ArrowType> = columns
+        .iter()
+        .map(|column| ArrowType::of(db, &column.db_type, encoding))
+        .collect();
+    let mut data: Vec<ColumnData> = types.iter().map(|t| ColumnData::new(*t)).collect();
+
+    let mut decoder = Decoder::new(rows);
+    let num_rows = decoder
+        .array()?
+        .ok_or_else(|| Error::CborDeValue("rows must be a definite-length array".into()))?;
+    for _ in 0..num_rows {
+        if decoder.array()? != Some(columns.len() as u64) {
+            return Err(Error::CborDeValue(
+                "rows must have a value for each column".into(),
+            ));
+        }
+        for column in data.iter_mut() {
+            column.push(&mut decoder)?;
+        }
+    }
+
+    let mut out = Vec::new();
+    write_message(&mut out, HEADER_SCHEMA, schema(columns, &types), &[]);
+
+    let mut nodes = Vec::with_capacity(data.len());
+    let mut buffers = Vec::new();
+    let mut body = Vec::new();
+    for column in &data {
+        nodes.push(vec![column.len as i64, column.null_count as i64]);
+        for buffer in column.buffers() {
+            buffers.push(vec![body.len() as i64, buffer.len() as i64]);
+            body.extend_from_slice(buffer);
+            body.resize(body.len().next_multiple_of(8), 0);
+        }
+    }
+    let record_batch = Object::Table(vec![
+        Field::I64(num_rows as i64),
+        Field::Object(Object::Structs(nodes)),
+        Field::Object(Object::Structs(buffers)),
+    ]);
+    write_message(&mut out, HEADER_RECORD_BATCH, record_batch, &body);
+
+    // end of stream
+    out.extend_from_slice(&CONTINUATION);
+    out.extend_from_slice(&0u32.to_le_bytes());
+    Ok(out)
+}
+
+fn schema(columns: &[Column], types: &[ArrowType]) -> Object {
+    let fields = columns
+        .iter()
+        .zip(types)
+        .map(|(column, arrow_type)| {
+            let (type_type, type_table) = arrow_type.to_flatbuf();
+            Object::Table(vec![
+                Field::Object(Object::String(column.name.clone())),
+                Field::Bool(true),
+                Field::U8(type_type),
+                Field::Object(type_table),
+                Field::Absent,
+                Field::Object(Object::Tables(vec![])),
+            ])
+        })
+        .collect();
+    // little endian
+    Object::Table(vec![Field::I16(0), Field::Object(Object::Tables(fields))])
+}
+
+/// Write an encapsulated message: the continuation marker, the length of the
+/// metadata, the metadata padded to 8 bytes, and the body
+fn write_message(out: &mut Vec<u8>, header_type: u8, header: Object, body: &[u8]) {
+    let mut metadata = flatbuf::finish(Object::Table(vec![
+        Field::I16(METADATA_VERSION),
+        Field::U8(header_type),
+        Field::Object(header),
+        Field::I64(body.len() as i64),
+    ]));
+    metadata.resize(metadata.len().next_multiple_of(8), 0);
+    out.extend_from_slice(&CONTINUATION);
+    out.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    out.extend_from_slice(&metadata);
+    out.extend_from_slice(body);
+}
@@ -0,0 +1,135 @@
+//! Minimal FlatBuffers writer for Arrow IPC message metadata
+//!
+//! Objects are built as a tree and laid out front to back, each table
+//! preceded by its vtable and followed by the objects it refers to, so every
+//! offset points forward as the format requires. Scalars are aligned to their
+//! size from the start of the buffer, which is 8-aligned in the message.
+
+/// Field of a table, in vtable order. Absent fields are left out of the
+/// vtable, and readers use the schema's default.
+pub(super) enum Field {
+    Absent,
+    U8(u8),
+    Bool(bool),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Object(Object),
+}
+
+pub(super) enum Object {
+    Table(Vec<Field>),
+    String(String),
+    /// vector of tables
+    Tables(Vec<Object>),
+    /// vector of structs made of `i64` fields, such as `FieldNode` and
+    /// `Buffer`
+    Structs(Vec<Vec<i64>>),
+}
+
+/// Serialize the tree under a root table
+pub(super) fn finish(root: Object) -> Vec<u8> {
+    let mut buf = vec![0; 4];
+    let pos = write(&mut buf, &root);
+    patch(&mut buf, 0, pos);
+    buf
+}
+
+fn align(buf: &mut Vec<u8>, alignment: usize) {
+    buf.resize(buf.len().next_multiple_of(alignment), 0);
+}
+
+/// Point the offset at `at` to the object at `target`
+fn patch(buf: &mut [u8], at: usize, target: usize) {
+    buf[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+}
+
+/// Write an object, returning its position
+fn write(buf: &mut Vec<u8>, object: &Object) -> usize {
+    match object {
+        Object::Table(fields) => write_table(buf, fields),
+        Object::String(value) => {
+            align(buf, 4);
+            let pos = buf.len();
+            buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            buf.extend_from_slice(value.as_bytes());
+            buf.push(0);
+            pos
+        }
+        Object::Tables(tables) => {
+            align(buf, 4);
+            let pos = buf.len();
+            buf.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+            buf.resize(buf.len() + 4 * tables.len(), 0);
+            for (i, table) in tables.iter().enumerate() {
+                let target = write(buf, table);
+                patch(buf, pos + 4 + 4 * i, target);
+            }
+            pos
+        }
+        Object::Structs(structs) => {
+            // the elements follow the length and are 8-aligned
+            align(buf, 8);
+            buf.resize(buf.len() + 4, 0);
+            let pos = buf.len();
+            buf.extend_from_slice(&(structs.len() as u32).to_le_bytes());
+            for value in structs.iter().flatten() {
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            pos
+        }
+    }
+}
+
+fn write_table(buf: &mut Vec<u8>, fields: &[Field]) -> usize {
+    // lay out the fields after the vtable offset, each aligned to its size
+    let mut layout = Vec::with_capacity(fields.len());
+    let mut size: usize = 4;
+    for field in fields {
+        let width = match field {
+            Field::Absent => {
+                layout.push(0);
+                continue;
+            }
+            Field::U8(_) | Field::Bool(_) => 1,
+            Field::I16(_) => 2,
+            Field::I32(_) | Field::Object(_) => 4,
+            Field::I64(_) => 8,
+        };
+        size = size.next_multiple_of(width);
+        layout.push(size);
+        size += width;
+    }
+
+    // the vtable, then the table on an 8-byte boundary
+    align(buf, 2);
+    let vtable = buf.len();
+    buf.extend_from_slice(&(4 + 2 * fields.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&(size as u16).to_le_bytes());
+    for offset in &layout {
+        buf.extend_from_slice(&(*offset as u16).to_le_bytes());
+    }
+    align(buf, 8);
+    let table = buf.len();
+    buf.extend_from_slice(&((table - vtable) as i32).to_le_bytes());
+    buf.resize(table + size, 0);
+
+    let mut children = Vec::new();
+    for (field, offset) in fields.iter().zip(layout) {
+        let at = table + offset;
+        match field {
+            Field::Absent => {}
+            Field::U8(value) => buf[at] = *value,
+            Field::Bool(value) => buf[at] = u8::from(*value),
+            Field::I16(value) => buf[at..at + 2].copy_from_slice(&value.to_le_bytes()),
+            Field::I32(value) => buf[at..at + 4].copy_from_slice(&value.to_le_bytes()),
+            Field::I64(value) => buf[at..at + 8].copy_from_slice(&value.to_le_bytes()),
+            Field::Object(object) => children.push((at, object)),
+        }
+    }
+    for (at, object) in children {
+        let target = write(buf, object);
+        patch(buf, at, target);
+    }
+    table
+}
@@ -0,0 +1,130 @@
+//! Bulk inserts
+//!
+//! Rows are sent as a CBOR array of arrays, like query results. Postgres
+//! streams them with `COPY`; MySQL and SQL Server insert them in batches of
+//! multi-row `INSERT` statements, in a transaction.
+
+use minicbor::{data::Type, Decoder};
+use wasmcloud_interface_sqldb::Statement;
+
+use crate::{
+    ext::BulkInsert,
+    result::{Error, Result},
+};
+
+use super::{ident, placeholder::Dialect};
+
+/// most rows a single `INSERT ... VALUES` may have on SQL Server
+const MAX_BATCH_ROWS: usize = 1000;
+/// most parameters a MySQL statement may have
+const MYSQL_MAX_PARAMS: usize = 65535;
+/// most parameters a SQL Server statement may have, less a few the driver
+/// needs
+const MSSQL_MAX_PARAMS: usize = 2000;
+
+/// The table and column names of a bulk insert, checked
+pub(crate) fn target(insert: &BulkInsert) -> Result<(&str, String)> {
+    if insert.columns.is_empty() {
+        return Err(Error::InvalidRequest("a bulk insert needs columns".into()));
+    }
+    let columns = insert
+        .columns
+        .iter()
+        .map(|column| ident::identifier(column))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((ident::identifier(&insert.table)?, columns.join(", ")))
+}
+
+/// Split the CBOR rows into the encoded values of each row, checking that each
+/// row has a value for each column
+pub(crate) fn decode_rows(rows: &[u8], columns: usize) -> Result<Vec<Vec<&[u8]>>> {
+    let mut decoder = Decoder::new(rows);
+    let mut decoded = Vec::new();
+    for_each_item(&mut decoder, |decoder| {
+        let mut row = Vec::with_capacity(columns);
+        for_each_item(decoder, |decoder| {
+            let start = decoder.position();
+            decoder.skip()?;
+            row.push(&rows[start..decoder.position()]);
+            Ok(())
+        })?;
+        if row.len() != columns {
+            return Err(Error::CborDeValue(format!(
+                "row {} has {} values for {} columns",
+                decoded.len() + 1,
+                row.len(),
+                columns
+            )));
+        }
+        decoded.push(row);
+        Ok(())
+    })?;
+    Ok(decoded)
+}
+
+/// Call `f` for each item of a definite or indefinite length array
+fn for_each_item<'b>(
+    decoder: &mut Decoder<'b>,
+    mut f: impl FnMut(&mut Decoder<'b>) -> Result<()>,
+) -> Result<()> {
+    match decoder.array()? {
+        Some(len) => {
+            for _ in 0..len {
+                f(decoder)?;
+            }
+        }
+        None => {
+            while decoder.datatype()? != Type::Break {
+                f(decoder)?;
+            }
+            decoder.skip()?;
+        }
+    }
+    Ok(())
+}
+
+/// The multi-row `INSERT` statements that insert the rows, each with as many
+/// rows as the database allows parameters for
+pub(crate) fn insert_statements(insert: &BulkInsert, dialect: Dialect) -> Result<Vec<Statement>> {
+    let (table, columns) = target(insert)?;
+    let rows = decode_rows(&insert.rows, insert.columns.len())?;
+    let max_params = match dialect {
+        Dialect::Mssql => MSSQL_MAX_PARAMS,
+        _ => MYSQL_MAX_PARAMS,
+    };
+    let batch_rows = (max_params / insert.columns.len()).clamp(1, MAX_BATCH_ROWS);
+
+    let statements = rows
+        .chunks(batch_rows)
+        .map(|batch| {
+            let mut parameters = Vec::with_capacity(batch.len() * insert.columns.len());
+            let values = batch
+                .iter()
+                .map(|row| {
+                    let placeholders = row
+                        .iter()
+                        .map(|value| {
+                            parameters.push(value.to_vec());
+                            match dialect {
+                                Dialect::Mssql => format!("@p{}", parameters.len()),
+                                _ => "?".to_string(),
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    format!("({})", placeholders.join(", "))
+                })
+                .collect::<Vec<_>>();
+            Statement {
+                sql: format!(
+                    "insert into {} ({}) values {}",
+                    table,
+                    columns,
+                    values.join(", ")
+                ),
+                parameters: Some(parameters),
+                ..Default::default()
+            }
+        })
+        .collect();
+    Ok(statements)
+}
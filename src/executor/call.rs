@@ -0,0 +1,58 @@
+//! Stored procedure and function calls
+//!
+//! A call is turned into a statement in the dialect of each database, taking
+//! the values of the in and inout parameters as its parameters.
+
+use minicbor::Decoder;
+
+use crate::{
+    ext::{CallResult, MultiQueryResult, ProcedureParam, ResultSet},
+    result::Result,
+};
+
+use super::ident::identifier;
+
+/// CBOR encoding of null, the value of parameters without one
+const CBOR_NULL: u8 = 0xf6;
+
+/// Name of the output column of the parameter at `index`
+pub(crate) fn output_name(index: usize, param: &ProcedureParam) -> Result<String> {
+    match &param.name {
+        Some(name) => Ok(identifier(name.trim_start_matches('@'))?.to_string()),
+        None => Ok(format!("p{}", index + 1)),
+    }
+}
+
+/// Encoded value of an in or inout parameter
+pub(crate) fn value(param: &ProcedureParam) -> Vec<u8> {
+    param.value.clone().unwrap_or_else(|| vec![CBOR_NULL])
+}
+
+/// The value of a result set with a single row of a single column, encoded in
+/// CBOR
+pub(crate) fn single_value(result_set: &ResultSet) -> Result<Option<Vec<u8>>> {
+    if result_set.num_rows != 1 || result_set.columns.len() != 1 {
+        return Ok(None);
+    }
+    let mut decoder = Decoder::new(&result_set.rows);
+    decoder.array()?;
+    decoder.array()?;
+    let start = decoder.position();
+    decoder.skip()?;
+    Ok(Some(result_set.rows[start..decoder.position()].to_vec()))
+}
+
+/// Result of a function call: the rows it returns, and their value as the
+/// return value when it returns a single value
+pub(crate) fn function_result(result: MultiQueryResult) -> Result<CallResult> {
+    let return_value = match result.result_sets.as_slice() {
+        [result_set] => single_value(result_set)?,
+        _ => None,
+    };
+    Ok(CallResult {
+        return_value,
+        result_sets: result.result_sets,
+        rows_affected: result.rows_affected,
+        ..Default::default()
+    })
+}
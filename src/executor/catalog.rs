@@ -0,0 +1,110 @@
+//! Schema introspection
+//!
+//! Each backend reads its catalog into flat rows, with one row per column of
+//! an index or foreign key in key order. The rows are grouped here into the
+//! shape shared by all backends.
+
+use crate::ext::{ForeignKeyInfo, IndexInfo, TableColumn, TableDescription};
+
+/// Name, ordinal, type, nullability, default, maximum length, precision and
+/// scale of a column
+pub(crate) type ColumnRow = (
+    String,
+    i64,
+    String,
+    bool,
+    Option<String>,
+    Option<i64>,
+    Option<i64>,
+    Option<i64>,
+);
+
+/// Name, uniqueness, whether it's the primary key, and a column of an index
+pub(crate) type IndexRow = (String, bool, bool, String);
+
+/// Name, a column, and the referenced schema, table and column of a foreign key
+pub(crate) type ForeignKeyRow = (String, String, String, String, String);
+
+/// Kind of a table from its `information_schema.tables.table_type`
+pub(crate) fn table_kind(table_type: &str) -> String {
+    match table_type {
+        "BASE TABLE" => "table".into(),
+        "VIEW" => "view".into(),
+        other => other.to_lowercase(),
+    }
+}
+
+pub(crate) fn describe_table(
+    (schema, name, table_type): (String, String, String),
+    columns: Vec<ColumnRow>,
+    indexes: Vec<IndexRow>,
+    foreign_keys: Vec<ForeignKeyRow>,
+) -> TableDescription {
+    let indexes = group_indexes(indexes);
+    let primary_key = indexes
+        .iter()
+        .find(|index| index.primary)
+        .map(|index| index.columns.clone())
+        .unwrap_or_default();
+    TableDescription {
+        schema,
+        name,
+        kind: table_kind(&table_type),
+        columns: columns.into_iter().map(to_column).collect(),
+        primary_key,
+        indexes,
+        foreign_keys: group_foreign_keys(foreign_keys),
+    }
+}
+
+fn to_column(
+    (name, ordinal, data_type, nullable, default, max_length, precision, scale): ColumnRow,
+) -> TableColumn {
+    TableColumn {
+        ordinal: ordinal as u32,
+        name,
+        data_type,
+        nullable,
+        default,
+        // SQL Server reports a length of -1 for `max` columns
+        max_length: max_length.and_then(|len| u32::try_from(len).ok()),
+        precision: precision.and_then(|p| u32::try_from(p).ok()),
+        scale: scale.and_then(|s| u32::try_from(s).ok()),
+    }
+}
+
+fn group_indexes(rows: Vec<IndexRow>) -> Vec<IndexInfo> {
+    let mut indexes: Vec<IndexInfo> = Vec::new();
+    for (name, unique, primary, column) in rows {
+        match indexes.last_mut() {
+            Some(index) if index.name == name => index.columns.push(column),
+            _ => indexes.push(IndexInfo {
+                name,
+                columns: vec![column],
+                unique,
+                primary,
+            }),
+        }
+    }
+    indexes
+}
+
+fn group_foreign_keys(rows: Vec<ForeignKeyRow>) -> Vec<ForeignKeyInfo> {
+    let mut keys: Vec<ForeignKeyInfo> = Vec::new();
+    for (name, column, referenced_schema, referenced_table, referenced_column) in rows {
+        match keys.last_mut() {
+            Some(key) if key.name == name => {
+                key.columns.push(column);
+                key.referenced_columns.push(referenced_column);
+            }
+            _ => keys.push(ForeignKeyInfo {
+                name,
+                columns: vec![column],
+                referenced_schema,
+                referenced_table,
+                referenced_columns: vec![referenced_column],
+            }),
+        }
+    }
+    keys
+}
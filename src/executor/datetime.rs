@@ -0,0 +1,11 @@
+//! Date and time string formats
+//!
+//! Result values of date and time types are encoded as strings in these
+//! formats, and parameters are parsed from them.
+
+use time::{format_description::FormatItem, macros::format_description};
+
+pub(crate) const DATE_FORMAT: &[FormatItem<'_>] = format_description!("[year]-[month]-[day]");
+pub(crate) const TIME_FORMAT: &[FormatItem<'_>] = format_description!("[hour]:[minute]:[second]");
+pub(crate) const TIMESTAMP_FORMAT: &[FormatItem<'_>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
@@ -0,0 +1,190 @@
+//! Transcoding of result rows from CBOR to the row format of the link or
+//! statement
+//!
+//! Rows are always encoded in CBOR first and transcoded value by value, so
+//! every backend and operation supports every format. Bytes are MessagePack
+//! `bin` values, and base64 strings in JSON. Floats that JSON can't represent
+//! become the strings `NaN`, `Infinity` and `-Infinity`. Arrow rows are
+//! encoded by the `arrow` module, using the column types.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use minicbor::{data::Type, Decoder};
+use serde::{ser::SerializeMap, ser::SerializeSeq, Serialize, Serializer};
+
+use sqlx::any::AnyKind;
+use wasmcloud_interface_sqldb::Column;
+
+use crate::{
+    config::EncodingOptions,
+    ext::{ResultSet, RowFormat},
+    result::{Error, Result},
+};
+
+use super::arrow;
+
+/// Transcode the CBOR rows of `columns` to the row format of `encoding`
+pub(crate) fn transcode_rows(
+    rows: &mut Vec<u8>,
+    columns: &[Column],
+    db: AnyKind,
+    encoding: &EncodingOptions,
+) -> Result<()> {
+    match encoding.rows {
+        RowFormat::Arrow => {
+            *rows = arrow::encode(rows, columns, db, encoding)?;
+            Ok(())
+        }
+        format => transcode(rows, format),
+    }
+}
+
+/// Transcode a single CBOR value, or rows, to `format`. Empty data is left
+/// empty, and a value stays CBOR when the format is Arrow.
+pub(crate) fn transcode(cbor: &mut Vec<u8>, format: RowFormat) -> Result<()> {
+    if cbor.is_empty() {
+        return Ok(());
+    }
+    *cbor = match format {
+        RowFormat::Cbor | RowFormat::Arrow => return Ok(()),
+        RowFormat::Msgpack => rmp_serde::to_vec(&decode(&mut Decoder::new(cbor), format)?)?,
+        RowFormat::Json => serde_json::to_vec(&decode(&mut Decoder::new(cbor), format)?)?,
+    };
+    Ok(())
+}
+
+/// Transcode the rows of each result set to the row format of `encoding`
+pub(crate) fn transcode_result_sets(
+    result_sets: &mut [ResultSet],
+    db: AnyKind,
+    encoding: &EncodingOptions,
+) -> Result<()> {
+    for result_set in result_sets {
+        transcode_rows(&mut result_set.rows, &result_set.columns, db, encoding)?;
+    }
+    Ok(())
+}
+
+enum Value {
+    Null,
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+}
+
+fn decode(decoder: &mut Decoder, format: RowFormat) -> Result<Value> {
+    let datatype = decoder.datatype()?;
+    let value = match datatype {
+        Type::Null | Type::Undefined => {
+            decoder.skip()?;
+            Value::Null
+        }
+        Type::Bool => Value::Bool(decoder.bool()?),
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 => Value::Unsigned(decoder.u64()?),
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::Int => {
+            let int = decoder.int()?;
+            match i64::try_from(int) {
+                Ok(value) => Value::Signed(value),
+                Err(_) => Value::Unsigned(
+                    u64::try_from(int).map_err(|_| Error::CborDeIntOutOfRange(i128::from(int)))?,
+                ),
+            }
+        }
+        Type::F16 | Type::F32 | Type::F64 => match decoder.f64()? {
+            value if format == RowFormat::Json && value.is_nan() => Value::String("NaN".into()),
+            value if format == RowFormat::Json && value.is_infinite() => {
+                Value::String(if value > 0.0 { "Infinity" } else { "-Infinity" }.into())
+            }
+            value => Value::Float(value),
+        },
+        Type::String | Type::StringIndef => {
+            let mut value = String::new();
+            for chunk in decoder.str_iter()? {
+                value.push_str(chunk?);
+            }
+            Value::String(value)
+        }
+        Type::Bytes | Type::BytesIndef => {
+            let mut value = Vec::new();
+            for chunk in decoder.bytes_iter()? {
+                value.extend_from_slice(chunk?);
+            }
+            match format {
+                RowFormat::Json => Value::String(STANDARD.encode(value)),
+                _ => Value::Bytes(value),
+            }
+        }
+        Type::Array | Type::ArrayIndef => {
+            let mut values = Vec::new();
+            let mut remaining = decoder.array()?;
+            while next_item(decoder, &mut remaining)? {
+                values.push(decode(decoder, format)?);
+            }
+            Value::Array(values)
+        }
+        Type::Map | Type::MapIndef => {
+            let mut entries = Vec::new();
+            let mut remaining = decoder.map()?;
+            while next_item(decoder, &mut remaining)? {
+                entries.push((decode(decoder, format)?, decode(decoder, format)?));
+            }
+            Value::Map(entries)
+        }
+        // neither format has tags, so the tagged value is kept as is
+        Type::Tag => {
+            decoder.tag()?;
+            decode(decoder, format)?
+        }
+        _ => return Err(Error::CborDeType(datatype)),
+    };
+    Ok(value)
+}
+
+/// Whether an array or map has another item, counting down `remaining` or
+/// consuming the break of an indefinite-length one
+fn next_item(decoder: &mut Decoder, remaining: &mut Option<u64>) -> Result<bool> {
+    match remaining {
+        Some(0) => Ok(false),
+        Some(n) => {
+            *n -= 1;
+            Ok(true)
+        }
+        None if decoder.datatype()? == Type::Break => {
+            decoder.set_position(decoder.position() + 1);
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::Unsigned(value) => serializer.serialize_u64(*value),
+            Value::Signed(value) => serializer.serialize_i64(*value),
+            Value::Float(value) => serializer.serialize_f64(*value),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::Bytes(value) => serializer.serialize_bytes(value),
+            Value::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
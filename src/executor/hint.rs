@@ -0,0 +1,341 @@
+//! Parameter type hints
+//!
+//! A parameter can be sent as CBOR tag 27 (an object with a type name) holding
+//! `[type, value]` to choose the SQL type it is bound as, instead of the type
+//! inferred from the value. Postgres casts the placeholder to the hinted type,
+//! MySQL and SQL Server bind the value converted to the hinted type.
+
+use std::str::FromStr;
+
+use sqlx::{types::BigDecimal, Database, Encode, Type};
+use time::{
+    format_description::well_known::Rfc3339, Date, OffsetDateTime, PrimitiveDateTime, Time,
+    UtcOffset,
+};
+use uuid::Uuid;
+
+use crate::result::{Error, Result};
+
+use super::{
+    datetime::{DATE_FORMAT, TIMESTAMP_FORMAT, TIME_FORMAT},
+    json,
+    param::Param,
+    tag::{self, TaggedValue},
+};
+
+/// SQL type a parameter is bound as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TypeHint {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    Numeric,
+    Text,
+    Bytea,
+    Date,
+    Time,
+    Timestamp,
+    Timestamptz,
+    Uuid,
+    Json,
+    Jsonb,
+}
+
+impl FromStr for TypeHint {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let hint = match name.to_ascii_lowercase().as_str() {
+            "bool" | "boolean" => TypeHint::Bool,
+            "int2" | "smallint" => TypeHint::Int2,
+            "int4" | "int" | "integer" => TypeHint::Int4,
+            "int8" | "bigint" => TypeHint::Int8,
+            "float4" | "real" => TypeHint::Float4,
+            "float8" | "double" | "double precision" => TypeHint::Float8,
+            "numeric" | "decimal" => TypeHint::Numeric,
+            "text" | "varchar" => TypeHint::Text,
+            "bytea" | "binary" | "varbinary" => TypeHint::Bytea,
+            "date" => TypeHint::Date,
+            "time" => TypeHint::Time,
+            "timestamp" | "datetime" => TypeHint::Timestamp,
+            "timestamptz" => TypeHint::Timestamptz,
+            "uuid" => TypeHint::Uuid,
+            "json" => TypeHint::Json,
+            "jsonb" => TypeHint::Jsonb,
+            _ => return Err(Error::TypeHint(name.to_string())),
+        };
+        Ok(hint)
+    }
+}
+
+impl TypeHint {
+    /// Postgres name of the type
+    pub(crate) fn pg_name(self) -> &'static str {
+        match self {
+            TypeHint::Bool => "bool",
+            TypeHint::Int2 => "int2",
+            TypeHint::Int4 => "int4",
+            TypeHint::Int8 => "int8",
+            TypeHint::Float4 => "float4",
+            TypeHint::Float8 => "float8",
+            TypeHint::Numeric => "numeric",
+            TypeHint::Text => "text",
+            TypeHint::Bytea => "bytea",
+            TypeHint::Date => "date",
+            TypeHint::Time => "time",
+            TypeHint::Timestamp => "timestamp",
+            TypeHint::Timestamptz => "timestamptz",
+            TypeHint::Uuid => "uuid",
+            TypeHint::Json => "json",
+            TypeHint::Jsonb => "jsonb",
+        }
+    }
+
+    /// Convert a parameter to the hinted type
+    pub(crate) fn convert(self, param: Param<'_>) -> Result<TypedValue> {
+        let value = match (self, param) {
+            (_, Param::Null) => TypedValue::Null,
+            (_, Param::Hinted(..) | Param::UserTyped(..)) => {
+                return Err(Error::CborDeValue("type hints can't be nested".into()))
+            }
+
+            (TypeHint::Bool, Param::Bool(value)) => TypedValue::Bool(value),
+            (TypeHint::Bool, Param::Int(value)) => TypedValue::Bool(value != 0),
+
+            (TypeHint::Int2, param) => TypedValue::Int2(self.convert_int(param)?),
+            (TypeHint::Int4, param) => TypedValue::Int4(self.convert_int(param)?),
+            (TypeHint::Int8, param) => TypedValue::Int8(self.convert_int(param)?),
+
+            (TypeHint::Float4, Param::Int(value)) => TypedValue::Float4(value as f32),
+            (TypeHint::Float4, Param::F32(value)) => TypedValue::Float4(value),
+            (TypeHint::Float4, Param::F64(value)) => TypedValue::Float4(value as f32),
+            (TypeHint::Float8, Param::Int(value)) => TypedValue::Float8(value as f64),
+            (TypeHint::Float8, Param::F32(value)) => TypedValue::Float8(value as f64),
+            (TypeHint::Float8, Param::F64(value)) => TypedValue::Float8(value),
+            (TypeHint::Float4 | TypeHint::Float8, Param::Text(value)) => {
+                let value = value.parse::<f64>().map_err(|e| self.invalid(e))?;
+                match self {
+                    TypeHint::Float4 => TypedValue::Float4(value as f32),
+                    _ => TypedValue::Float8(value),
+                }
+            }
+
+            (TypeHint::Numeric, Param::Int(value)) => TypedValue::Numeric(
+                BigDecimal::from_str(&value.to_string()).map_err(|e| self.invalid(e))?,
+            ),
+            (TypeHint::Numeric, Param::F32(value)) => TypedValue::Numeric(
+                BigDecimal::try_from(value as f64).map_err(|e| self.invalid(e))?,
+            ),
+            (TypeHint::Numeric, Param::F64(value)) => {
+                TypedValue::Numeric(BigDecimal::try_from(value).map_err(|e| self.invalid(e))?)
+            }
+            (TypeHint::Numeric, Param::Text(value)) => {
+                TypedValue::Numeric(BigDecimal::from_str(&value).map_err(|e| self.invalid(e))?)
+            }
+            (TypeHint::Numeric, Param::Tagged(TaggedValue::Number(value))) => {
+                TypedValue::Numeric(value)
+            }
+
+            (TypeHint::Text, Param::Text(value)) => TypedValue::Text(value),
+            (TypeHint::Text, Param::Bool(value)) => TypedValue::Text(value.to_string()),
+            (TypeHint::Text, Param::Int(value)) => TypedValue::Text(value.to_string()),
+            (TypeHint::Text, Param::F32(value)) => TypedValue::Text(value.to_string()),
+            (TypeHint::Text, Param::F64(value)) => TypedValue::Text(value.to_string()),
+            (TypeHint::Text, Param::Tagged(tagged)) => TypedValue::Text(match tagged {
+                TaggedValue::DateTime(value) => value.format(&Rfc3339)?,
+                TaggedValue::Uuid(value) => value.as_hyphenated().to_string(),
+                TaggedValue::Number(value) => value.to_string(),
+            }),
+
+            (TypeHint::Bytea, Param::Bytes(value)) => TypedValue::Bytes(value),
+            (TypeHint::Bytea, Param::Text(value)) => TypedValue::Bytes(value.into_bytes()),
+
+            (TypeHint::Date, Param::Text(value)) => {
+                TypedValue::Date(Date::parse(&value, DATE_FORMAT)?)
+            }
+            (TypeHint::Date, Param::Tagged(TaggedValue::DateTime(value))) => {
+                TypedValue::Date(value.to_offset(UtcOffset::UTC).date())
+            }
+            (TypeHint::Time, Param::Text(value)) => {
+                TypedValue::Time(Time::parse(&value, TIME_FORMAT)?)
+            }
+            (TypeHint::Timestamp, Param::Text(value)) => {
+                TypedValue::Timestamp(PrimitiveDateTime::parse(&value, TIMESTAMP_FORMAT)?)
+            }
+            (TypeHint::Timestamp, Param::Tagged(TaggedValue::DateTime(value))) => {
+                let value = value.to_offset(UtcOffset::UTC);
+                TypedValue::Timestamp(PrimitiveDateTime::new(value.date(), value.time()))
+            }
+            (TypeHint::Timestamptz, Param::Text(value)) => {
+                TypedValue::Timestamptz(OffsetDateTime::parse(&value, &Rfc3339)?)
+            }
+            (TypeHint::Timestamptz, Param::Tagged(TaggedValue::DateTime(value))) => {
+                TypedValue::Timestamptz(value)
+            }
+
+            (TypeHint::Uuid, Param::Text(value)) => {
+                TypedValue::Uuid(Uuid::parse_str(&value).map_err(|e| self.invalid(e))?)
+            }
+            (TypeHint::Uuid, Param::Bytes(value)) => {
+                TypedValue::Uuid(Uuid::from_slice(&value).map_err(|e| self.invalid(e))?)
+            }
+            (TypeHint::Uuid, Param::Tagged(TaggedValue::Uuid(value))) => TypedValue::Uuid(value),
+
+            (TypeHint::Json | TypeHint::Jsonb, Param::Array(value) | Param::Map(value)) => {
+                TypedValue::Json(json::decode_json(&mut minicbor::Decoder::new(value))?)
+            }
+            (TypeHint::Json | TypeHint::Jsonb, Param::Text(value)) => {
+                TypedValue::Json(serde_json::from_str(&value)?)
+            }
+            (TypeHint::Json | TypeHint::Jsonb, Param::Bool(value)) => {
+                TypedValue::Json(value.into())
+            }
+            (TypeHint::Json | TypeHint::Jsonb, Param::Int(value)) => TypedValue::Json(
+                serde_json::Number::from_str(&value.to_string())
+                    .map_err(|e| self.invalid(e))?
+                    .into(),
+            ),
+            (TypeHint::Json | TypeHint::Jsonb, Param::F64(value)) => TypedValue::Json(value.into()),
+            (TypeHint::Json | TypeHint::Jsonb, Param::F32(value)) => {
+                TypedValue::Json((value as f64).into())
+            }
+
+            (_, param) => {
+                return Err(Error::CborDeValue(format!(
+                    "a {} can't be bound as {}",
+                    param.kind(),
+                    self.pg_name()
+                )))
+            }
+        };
+        Ok(value)
+    }
+
+    fn convert_int<T: TryFrom<i128>>(self, param: Param<'_>) -> Result<T> {
+        let value = match param {
+            Param::Int(value) => value,
+            Param::Bool(value) => value as i128,
+            Param::F32(value) if value.fract() == 0.0 => value as i128,
+            Param::F64(value) if value.fract() == 0.0 => value as i128,
+            Param::Text(value) => value.parse().map_err(|e| self.invalid(e))?,
+            Param::Tagged(TaggedValue::Number(value)) => match tag::as_i64(&value) {
+                Some(value) => value as i128,
+                None => return Err(self.out_of_range(value)),
+            },
+            param => {
+                return Err(Error::CborDeValue(format!(
+                    "a {} can't be bound as {}",
+                    param.kind(),
+                    self.pg_name()
+                )))
+            }
+        };
+        T::try_from(value).map_err(|_| self.out_of_range(value))
+    }
+
+    fn invalid<E: std::fmt::Display>(self, e: E) -> Error {
+        Error::CborDeValue(format!("invalid {}: {}", self.pg_name(), e))
+    }
+
+    fn out_of_range<V: std::fmt::Display>(self, value: V) -> Error {
+        Error::CborDeValue(format!("{} is out of range for {}", value, self.pg_name()))
+    }
+}
+
+/// A parameter converted to its hinted type
+#[derive(Debug)]
+pub(crate) enum TypedValue {
+    Null,
+    Bool(bool),
+    Int2(i16),
+    Int4(i32),
+    Int8(i64),
+    Float4(f32),
+    Float8(f64),
+    Numeric(BigDecimal),
+    Text(String),
+    Bytes(Vec<u8>),
+    Date(Date),
+    Time(Time),
+    Timestamp(PrimitiveDateTime),
+    Timestamptz(OffsetDateTime),
+    Uuid(Uuid),
+    Json(serde_json::Value),
+}
+
+/// Something the values of hinted parameters can be bound to. Booleans,
+/// integers, floats and strings bind natively; values of the other types bind
+/// as strings in the formats of the types table, unless the database binds
+/// them natively.
+pub(crate) trait BindTyped<'q>: Sized
+where
+    Option<bool>: Encode<'q, Self::Database> + Type<Self::Database>,
+    bool: Encode<'q, Self::Database> + Type<Self::Database>,
+    i16: Encode<'q, Self::Database> + Type<Self::Database>,
+    i32: Encode<'q, Self::Database> + Type<Self::Database>,
+    i64: Encode<'q, Self::Database> + Type<Self::Database>,
+    f32: Encode<'q, Self::Database> + Type<Self::Database>,
+    f64: Encode<'q, Self::Database> + Type<Self::Database>,
+    String: Encode<'q, Self::Database> + Type<Self::Database>,
+{
+    type Database: Database;
+
+    fn bind_value<T>(self, value: T) -> Self
+    where
+        T: 'q + Send + Encode<'q, Self::Database> + Type<Self::Database>;
+
+    fn bind_bytes(self, value: Vec<u8>) -> Result<Self>;
+
+    fn bind_numeric(self, value: BigDecimal) -> Result<Self> {
+        Ok(self.bind_value(value.to_string()))
+    }
+
+    fn bind_date(self, value: Date) -> Result<Self> {
+        Ok(self.bind_value(value.format(DATE_FORMAT)?))
+    }
+
+    fn bind_time(self, value: Time) -> Result<Self> {
+        Ok(self.bind_value(value.format(TIME_FORMAT)?))
+    }
+
+    fn bind_timestamp(self, value: PrimitiveDateTime) -> Result<Self> {
+        Ok(self.bind_value(value.format(TIMESTAMP_FORMAT)?))
+    }
+
+    fn bind_timestamptz(self, value: OffsetDateTime) -> Result<Self> {
+        Ok(self.bind_value(value.format(&Rfc3339)?))
+    }
+
+    fn bind_uuid(self, value: Uuid) -> Result<Self> {
+        Ok(self.bind_value(value.as_hyphenated().to_string()))
+    }
+
+    fn bind_json(self, value: serde_json::Value) -> Result<Self> {
+        Ok(self.bind_value(value.to_string()))
+    }
+
+    /// Bind a value converted to its hinted type
+    fn bind_typed_value(self, value: TypedValue) -> Result<Self> {
+        Ok(match value {
+            TypedValue::Null => self.bind_value(None::<bool>),
+            TypedValue::Bool(value) => self.bind_value(value),
+            TypedValue::Int2(value) => self.bind_value(value),
+            TypedValue::Int4(value) => self.bind_value(value),
+            TypedValue::Int8(value) => self.bind_value(value),
+            TypedValue::Float4(value) => self.bind_value(value),
+            TypedValue::Float8(value) => self.bind_value(value),
+            TypedValue::Text(value) => self.bind_value(value),
+            TypedValue::Bytes(value) => self.bind_bytes(value)?,
+            TypedValue::Numeric(value) => self.bind_numeric(value)?,
+            TypedValue::Date(value) => self.bind_date(value)?,
+            TypedValue::Time(value) => self.bind_time(value)?,
+            TypedValue::Timestamp(value) => self.bind_timestamp(value)?,
+            TypedValue::Timestamptz(value) => self.bind_timestamptz(value)?,
+            TypedValue::Uuid(value) => self.bind_uuid(value)?,
+            TypedValue::Json(value) => self.bind_json(value)?,
+        })
+    }
+}
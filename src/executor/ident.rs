@@ -0,0 +1,37 @@
+//! Names written into generated statements
+//!
+//! Procedure, table, column and type names given in requests are checked
+//! rather than quoted, so they keep the case folding of names in plain SQL.
+
+use crate::result::{Error, Result};
+
+/// Check that a name is a plain, optionally qualified identifier, since it is
+/// written into the statement as is
+pub(crate) fn identifier(name: &str) -> Result<&str> {
+    let valid = !name.is_empty()
+        && name.split('.').all(|part| {
+            part.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && part
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        });
+    if valid {
+        Ok(name)
+    } else {
+        Err(Error::InvalidIdentifier(name.to_string()))
+    }
+}
+
+/// Check that a type name (e.g. `numeric(10, 2)`, `nvarchar(max)`) has no
+/// characters that could end it
+pub(crate) fn type_name(name: &str) -> Result<&str> {
+    let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || " _.,()[]".contains(c));
+    if valid {
+        Ok(name)
+    } else {
+        Err(Error::InvalidIdentifier(name.to_string()))
+    }
+}
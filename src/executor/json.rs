@@ -0,0 +1,136 @@
+//! Transcoding between JSON values and CBOR
+//!
+//! JSON objects map to CBOR maps with string keys, arrays to arrays, and
+//! numbers to the smallest CBOR integer or a 64-bit float.
+
+use minicbor::{data::Type, Decoder, Encoder};
+use serde_json::{Map, Number, Value};
+
+use crate::config::JsonEncoding;
+use crate::result::{Error, Result};
+
+/// Encode a JSON value, either as a JSON string or as nested CBOR
+pub(crate) fn encode_json(
+    out: &mut Encoder<&mut Vec<u8>>,
+    value: &Value,
+    encoding: JsonEncoding,
+) -> Result<()> {
+    match encoding {
+        JsonEncoding::String => {
+            out.encode(serde_json::to_string(value)?)?;
+        }
+        JsonEncoding::Cbor => encode_cbor(out, value)?,
+    }
+    Ok(())
+}
+
+fn encode_cbor(out: &mut Encoder<&mut Vec<u8>>, value: &Value) -> Result<()> {
+    match value {
+        Value::Null => {
+            out.null()?;
+        }
+        Value::Bool(value) => {
+            out.bool(*value)?;
+        }
+        Value::Number(number) => {
+            if let Some(value) = number.as_u64() {
+                out.u64(value)?;
+            } else if let Some(value) = number.as_i64() {
+                out.i64(value)?;
+            } else if let Some(value) = number.as_f64() {
+                out.f64(value)?;
+            }
+        }
+        Value::String(value) => {
+            out.str(value)?;
+        }
+        Value::Array(values) => {
+            out.array(values.len() as u64)?;
+            for value in values {
+                encode_cbor(out, value)?;
+            }
+        }
+        Value::Object(map) => {
+            out.map(map.len() as u64)?;
+            for (key, value) in map {
+                out.str(key)?;
+                encode_cbor(out, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode a CBOR value as JSON. Map keys must be strings.
+pub(crate) fn decode_json(decoder: &mut Decoder) -> Result<Value> {
+    let datatype = decoder.datatype()?;
+    let value = match datatype {
+        Type::Null | Type::Undefined => {
+            decoder.skip()?;
+            Value::Null
+        }
+        Type::Bool => Value::Bool(decoder.bool()?),
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 => Value::from(decoder.u64()?),
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::Int => {
+            let int = decoder.int()?;
+            match i64::try_from(int) {
+                Ok(value) => Value::from(value),
+                Err(_) => Value::from(
+                    u64::try_from(int).map_err(|_| Error::CborDeIntOutOfRange(i128::from(int)))?,
+                ),
+            }
+        }
+        Type::F16 | Type::F32 | Type::F64 => {
+            let value = decoder.f64()?;
+            Value::Number(Number::from_f64(value).ok_or_else(|| {
+                Error::CborDeValue(format!("{} is not a valid JSON number", value))
+            })?)
+        }
+        Type::String => Value::String(decoder.str()?.to_string()),
+        Type::Array | Type::ArrayIndef => {
+            let mut values = Vec::new();
+            let mut remaining = decoder.array()?;
+            loop {
+                match remaining {
+                    Some(0) => break,
+                    Some(n) => remaining = Some(n - 1),
+                    None if decoder.datatype()? == Type::Break => {
+                        decoder.set_position(decoder.position() + 1);
+                        break;
+                    }
+                    None => {}
+                }
+                values.push(decode_json(decoder)?);
+            }
+            Value::Array(values)
+        }
+        Type::Map | Type::MapIndef => {
+            let mut map = Map::new();
+            let mut remaining = decoder.map()?;
+            loop {
+                match remaining {
+                    Some(0) => break,
+                    Some(n) => remaining = Some(n - 1),
+                    None if decoder.datatype()? == Type::Break => {
+                        decoder.set_position(decoder.position() + 1);
+                        break;
+                    }
+                    None => {}
+                }
+                let key = match decoder.datatype()? {
+                    Type::String => decoder.str()?.to_string(),
+                    datatype => {
+                        return Err(Error::CborDeValue(format!(
+                            "JSON object keys must be strings, found `{}`",
+                            datatype
+                        )))
+                    }
+                };
+                map.insert(key, decode_json(decoder)?);
+            }
+            Value::Object(map)
+        }
+        _ => return Err(Error::CborDeType(datatype)),
+    };
+    Ok(value)
+}
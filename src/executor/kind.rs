@@ -0,0 +1,34 @@
+//! Kinds of result values
+//!
+//! Each backend maps the type names of its columns to the kind of value its row
+//! encoder writes for them, so the row encoders and the Arrow schema of a result
+//! agree on the type of each column.
+
+/// Kind of the CBOR value a row encoder writes for a column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ValueKind {
+    Null,
+    Bool,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float32,
+    Float64,
+    Text,
+    Bytes,
+    /// string in `DATE_FORMAT`
+    Date,
+    /// string in `TIME_FORMAT`
+    Time,
+    /// string in `TIMESTAMP_FORMAT`
+    Timestamp,
+    /// RFC 3339 string
+    TimestampTz,
+    /// a value the encoder writes by its type name
+    Other,
+}
@@ -1,42 +1,242 @@
+mod arrow;
+mod bulk;
+mod call;
+mod catalog;
+mod datetime;
+pub(crate) mod format;
+mod hint;
+pub(crate) mod ident;
+mod json;
+mod kind;
 mod mssql;
 mod mysql;
+mod param;
+mod placeholder;
 mod postgres;
+pub(crate) mod tables;
+mod tag;
+pub(crate) mod tenant;
 
 use async_trait::async_trait;
+use futures::{stream::BoxStream, TryStreamExt};
 use sqlx::{
-    any::AnyConnectionKind, database::HasArguments, query::Query, AnyConnection, Column as _,
-    Database, Row, TypeInfo,
+    any::AnyConnectionKind, database::HasArguments, query::Query, Database, Either, TypeInfo,
 };
+use tokio::sync::mpsc;
 use wasmcloud_interface_sqldb::{Column, ExecuteResult, QueryResult, Statement};
 
-use crate::result::Result;
+use crate::{
+    config::EncodingOptions,
+    ext::{
+        BulkInsert, CallResult, ColumnMetadata, Export, MultiQueryResult, ProcedureCall,
+        ReturningResult, TableDescription, TableInfo,
+    },
+    result::Result,
+};
+
+pub(crate) use self::{placeholder::Dialect, tenant::Checkout};
 
-pub use self::mssql::*;
-pub use self::mysql::*;
-pub use self::postgres::*;
+use self::placeholder::bind_named;
 
 #[async_trait]
 pub trait SqlDbExecutor {
     async fn execute(&mut self, stmt: &Statement) -> Result<ExecuteResult>;
 
-    async fn fetch_all(&mut self, stmt: &Statement) -> Result<QueryResult>;
+    async fn fetch_all(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<QueryResult>;
+
+    async fn describe(&mut self, stmt: &Statement) -> Result<Vec<ColumnMetadata>>;
+
+    async fn execute_returning(
+        &mut self,
+        stmt: &Statement,
+        key_columns: &[String],
+        encoding: &EncodingOptions,
+    ) -> Result<ReturningResult>;
+
+    async fn fetch_multi(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<MultiQueryResult>;
+
+    async fn call(
+        &mut self,
+        call: &ProcedureCall,
+        encoding: &EncodingOptions,
+    ) -> Result<CallResult>;
+
+    /// Insert the rows, returning the number of rows inserted
+    async fn bulk_insert(&mut self, insert: &BulkInsert) -> Result<u64>;
+
+    /// Export the result of a query, sending the data in chunks of about
+    /// `chunk_size` bytes until it is done or the receiver is dropped
+    async fn export(
+        &mut self,
+        export: &Export,
+        chunk_size: usize,
+        chunks: &mpsc::Sender<Result<Vec<u8>>>,
+    ) -> Result<()>;
+
+    /// List the schemas, leaving out the system ones
+    async fn list_schemas(&mut self) -> Result<Vec<String>>;
+
+    /// List the tables and views of a schema, or of all but the system schemas
+    async fn list_tables(&mut self, schema: Option<&str>) -> Result<Vec<TableInfo>>;
+
+    /// Describe a table of a schema, or of the current schema, returning none
+    /// if there is no such table
+    async fn describe_table(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+    ) -> Result<Option<TableDescription>>;
 }
 
 #[async_trait]
-impl SqlDbExecutor for AnyConnection {
+impl SqlDbExecutor for Checkout {
     async fn execute(&mut self, stmt: &Statement) -> Result<ExecuteResult> {
-        match self.private_get_mut() {
+        let stmt = bind_named(stmt, self.kind().into())?;
+        let mut conn = tenant::enter(self, stmt.database.as_deref()).await?;
+        let result = match conn.private_get_mut() {
             AnyConnectionKind::Postgres(conn) => conn.execute(&stmt).await,
             AnyConnectionKind::MySql(conn) => conn.execute(&stmt).await,
             AnyConnectionKind::Mssql(conn) => conn.execute(&stmt).await,
+        };
+        tenant::leave(conn, result).await
+    }
+
+    async fn fetch_all(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<QueryResult> {
+        let stmt = bind_named(stmt, self.kind().into())?;
+        let mut conn = tenant::enter(self, stmt.database.as_deref()).await?;
+        let result = match conn.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.fetch_all(&stmt, encoding).await,
+            AnyConnectionKind::MySql(conn) => conn.fetch_all(&stmt, encoding).await,
+            AnyConnectionKind::Mssql(conn) => conn.fetch_all(&stmt, encoding).await,
+        };
+        tenant::leave(conn, result).await
+    }
+
+    async fn describe(&mut self, stmt: &Statement) -> Result<Vec<ColumnMetadata>> {
+        let stmt = bind_named(stmt, self.kind().into())?;
+        let mut conn = tenant::enter(self, stmt.database.as_deref()).await?;
+        let result = match conn.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.describe(&stmt).await,
+            AnyConnectionKind::MySql(conn) => conn.describe(&stmt).await,
+            AnyConnectionKind::Mssql(conn) => conn.describe(&stmt).await,
+        };
+        tenant::leave(conn, result).await
+    }
+
+    async fn execute_returning(
+        &mut self,
+        stmt: &Statement,
+        key_columns: &[String],
+        encoding: &EncodingOptions,
+    ) -> Result<ReturningResult> {
+        let stmt = bind_named(stmt, self.kind().into())?;
+        let mut conn = tenant::enter(self, stmt.database.as_deref()).await?;
+        let result = match conn.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => {
+                conn.execute_returning(&stmt, key_columns, encoding).await
+            }
+            AnyConnectionKind::MySql(conn) => {
+                conn.execute_returning(&stmt, key_columns, encoding).await
+            }
+            AnyConnectionKind::Mssql(conn) => {
+                conn.execute_returning(&stmt, key_columns, encoding).await
+            }
+        };
+        tenant::leave(conn, result).await
+    }
+
+    async fn fetch_multi(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<MultiQueryResult> {
+        let stmt = bind_named(stmt, self.kind().into())?;
+        let mut conn = tenant::enter(self, stmt.database.as_deref()).await?;
+        let result = match conn.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.fetch_multi(&stmt, encoding).await,
+            AnyConnectionKind::MySql(conn) => conn.fetch_multi(&stmt, encoding).await,
+            AnyConnectionKind::Mssql(conn) => conn.fetch_multi(&stmt, encoding).await,
+        };
+        tenant::leave(conn, result).await
+    }
+
+    async fn call(
+        &mut self,
+        call: &ProcedureCall,
+        encoding: &EncodingOptions,
+    ) -> Result<CallResult> {
+        match self.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.call(call, encoding).await,
+            AnyConnectionKind::MySql(conn) => conn.call(call, encoding).await,
+            AnyConnectionKind::Mssql(conn) => conn.call(call, encoding).await,
+        }
+    }
+
+    async fn bulk_insert(&mut self, insert: &BulkInsert) -> Result<u64> {
+        match self.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.bulk_insert(insert).await,
+            AnyConnectionKind::MySql(conn) => conn.bulk_insert(insert).await,
+            AnyConnectionKind::Mssql(conn) => conn.bulk_insert(insert).await,
+        }
+    }
+
+    async fn export(
+        &mut self,
+        export: &Export,
+        chunk_size: usize,
+        chunks: &mpsc::Sender<Result<Vec<u8>>>,
+    ) -> Result<()> {
+        let mut conn = tenant::enter(self, export.statement.database.as_deref()).await?;
+        let result = match conn.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.export(export, chunk_size, chunks).await,
+            AnyConnectionKind::MySql(conn) => conn.export(export, chunk_size, chunks).await,
+            AnyConnectionKind::Mssql(conn) => conn.export(export, chunk_size, chunks).await,
+        };
+        // an export stopped midway leaves the connection in the middle of a
+        // response, and it's closed rather than switched back
+        if chunks.is_closed() {
+            return result;
+        }
+        tenant::leave(conn, result).await
+    }
+
+    async fn list_schemas(&mut self) -> Result<Vec<String>> {
+        match self.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.list_schemas().await,
+            AnyConnectionKind::MySql(conn) => conn.list_schemas().await,
+            AnyConnectionKind::Mssql(conn) => conn.list_schemas().await,
+        }
+    }
+
+    async fn list_tables(&mut self, schema: Option<&str>) -> Result<Vec<TableInfo>> {
+        match self.private_get_mut() {
+            AnyConnectionKind::Postgres(conn) => conn.list_tables(schema).await,
+            AnyConnectionKind::MySql(conn) => conn.list_tables(schema).await,
+            AnyConnectionKind::Mssql(conn) => conn.list_tables(schema).await,
         }
     }
 
-    async fn fetch_all(&mut self, stmt: &Statement) -> Result<QueryResult> {
+    async fn describe_table(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+    ) -> Result<Option<TableDescription>> {
         match self.private_get_mut() {
-            AnyConnectionKind::Postgres(conn) => conn.fetch_all(&stmt).await,
-            AnyConnectionKind::MySql(conn) => conn.fetch_all(&stmt).await,
-            AnyConnectionKind::Mssql(conn) => conn.fetch_all(&stmt).await,
+            AnyConnectionKind::Postgres(conn) => conn.describe_table(schema, table).await,
+            AnyConnectionKind::MySql(conn) => conn.describe_table(schema, table).await,
+            AnyConnectionKind::Mssql(conn) => conn.describe_table(schema, table).await,
         }
     }
 }
@@ -58,19 +258,55 @@ where
     let mut query = sqlx::query::<DB>(&stmt.sql);
     if let Some(params) = &stmt.parameters {
         for value in params {
-            query = query.bind_cbor(&value)?;
+            query = query.bind_cbor(value)?;
         }
     }
     Ok(query)
 }
 
-pub(crate) fn to_columns<R>(rows: &[R]) -> Vec<Column>
+/// Split the results of `fetch_many` into the rows of each result set and the
+/// rows affected by each statement that returned no rows. A statement's results
+/// end with its rows affected, so a result set can't be told from an empty one.
+pub(crate) async fn split_results<Q, R>(
+    mut results: BoxStream<'_, std::result::Result<Either<Q, R>, sqlx::Error>>,
+    rows_affected: impl Fn(&Q) -> u64 + Send,
+) -> Result<(Vec<Vec<R>>, Vec<u64>)>
+where
+    Q: Send,
+    R: Send,
+{
+    let mut result_sets = Vec::new();
+    let mut counts = Vec::new();
+    let mut rows = Vec::new();
+    while let Some(result) = results.try_next().await? {
+        match result {
+            Either::Left(_) if !rows.is_empty() => result_sets.push(std::mem::take(&mut rows)),
+            Either::Left(result) => counts.push(rows_affected(&result)),
+            Either::Right(row) => rows.push(row),
+        }
+    }
+    if !rows.is_empty() {
+        result_sets.push(rows);
+    }
+    Ok((result_sets, counts))
+}
+
+/// Result of describing a statement that returned no rows, for its columns. A
+/// statement the server refuses to describe has no columns; other errors, such
+/// as a lost connection, are returned.
+pub(crate) fn described<T>(result: std::result::Result<T, sqlx::Error>) -> Result<Option<T>> {
+    match result {
+        Ok(describe) => Ok(Some(describe)),
+        Err(sqlx::Error::Database(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub(crate) fn to_columns<C>(columns: &[C]) -> Vec<Column>
 where
-    R: Row,
+    C: sqlx::Column,
 {
-    rows.first()
-        .unwrap()
-        .columns()
+    columns
         .iter()
         .map(|column| Column {
             ordinal: column.ordinal() as u32,
@@ -1,18 +1,48 @@
+mod catalog;
+mod convert;
+mod metadata;
+mod types;
+
 use async_trait::async_trait;
+use futures::TryStreamExt;
 use sqlx::{
-    database::HasArguments, mssql::MssqlRow, query::Query, Column, Decode, Mssql, MssqlConnection,
-    Row, TypeInfo, ValueRef,
+    database::HasArguments,
+    mssql::{MssqlColumn, MssqlRow},
+    query::Query,
+    Column as _, Decode, Either, Encode, Mssql, MssqlConnection, Row, Type, ValueRef,
 };
-use wasmcloud_interface_sqldb::{ExecuteResult, QueryResult, Statement};
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::mpsc;
+use wasmcloud_interface_sqldb::{Column, ExecuteResult, QueryResult, Statement};
 
-use crate::result::{Error, Result};
+use crate::{
+    config::EncodingOptions,
+    ext::{
+        BulkInsert, CallResult, ColumnMetadata, Export, MultiQueryResult, ParamMode, ProcedureCall,
+        ResultSet, ReturningResult, TableDescription, TableInfo,
+    },
+    result::{Error, Result},
+};
 
-use super::{bind_query, to_columns, BindCbor, SqlDbExecutor};
+use self::{convert::Capture, types::ColumnType};
+use super::{
+    bulk, call,
+    datetime::{TIMESTAMP_FORMAT, TIME_FORMAT},
+    described,
+    hint::{BindTyped, TypeHint},
+    ident,
+    kind::ValueKind,
+    param::Param,
+    placeholder::{find_keyword, first_keyword, Dialect},
+    split_results,
+    tag::{self, TaggedValue},
+    BindCbor, SqlDbExecutor,
+};
 
 #[async_trait]
 impl SqlDbExecutor for MssqlConnection {
     async fn execute(&mut self, stmt: &Statement) -> Result<ExecuteResult> {
-        let query = bind_query(stmt)?;
+        let query = bind_mssql_query(&stmt.sql, stmt)?;
         let result = sqlx::Executor::execute(self, query).await?;
         Ok(ExecuteResult {
             rows_affected: result.rows_affected(),
@@ -20,102 +50,355 @@ impl SqlDbExecutor for MssqlConnection {
         })
     }
 
-    async fn fetch_all(&mut self, stmt: &Statement) -> Result<QueryResult> {
-        let query = bind_query(stmt)?;
-        let rows = sqlx::Executor::fetch_all(self, query).await?;
+    async fn fetch_all(
+        &mut self,
+        stmt: &Statement,
+        // SQL Server has no JSON column type
+        _encoding: &EncodingOptions,
+    ) -> Result<QueryResult> {
+        let capture = convert::capture(self, &stmt.sql).await?;
+        let sql = capture
+            .as_ref()
+            .map_or(stmt.sql.as_str(), |capture| &capture.sql);
+        let query = bind_mssql_query(sql, stmt)?;
+        let rows = sqlx::Executor::fetch_all(&mut *self, query).await?;
         if rows.is_empty() {
-            Ok(QueryResult::default())
+            let columns = match capture {
+                Some(capture) => captured_columns(&capture),
+                None => described_columns(self, &stmt.sql).await?,
+            };
+            Ok(QueryResult {
+                columns,
+                ..Default::default()
+            })
         } else {
+            let types = column_types(rows[0].columns(), capture.as_ref())?;
             Ok(QueryResult {
                 num_rows: rows.len() as u64,
-                columns: to_columns(&rows),
-                rows: mssql_to_cbor(&rows)?,
+                columns: mssql_columns(rows[0].columns(), &types),
+                rows: mssql_to_cbor(&rows, &types)?,
                 error: None,
             })
         }
     }
-}
 
-impl<'q> BindCbor for Query<'q, Mssql, <Mssql as HasArguments<'q>>::Arguments> {
-    fn bind_cbor(self, value: &[u8]) -> Result<Self> {
-        use minicbor::data::Type;
-
-        let mut decoder = minicbor::Decoder::new(value);
-        let datatype = decoder.datatype()?;
-        let query = match datatype {
-            Type::Bool => self.bind(decoder.bool()?),
-            Type::Null | Type::Undefined => self.bind(None::<bool>),
-            Type::U8 => {
-                let value = decoder.u8()?;
-                if let Ok(value) = i8::try_from(value) {
-                    self.bind(value)
-                } else {
-                    self.bind(value as i16)
-                }
+    async fn describe(&mut self, stmt: &Statement) -> Result<Vec<ColumnMetadata>> {
+        metadata::describe_columns(self, &stmt.sql).await
+    }
+
+    async fn execute_returning(
+        &mut self,
+        stmt: &Statement,
+        key_columns: &[String],
+        // SQL Server has no JSON column type
+        _encoding: &EncodingOptions,
+    ) -> Result<ReturningResult> {
+        let stmt = Statement {
+            sql: with_output(&stmt.sql, key_columns),
+            ..stmt.clone()
+        };
+        let query = bind_mssql_query(&stmt.sql, &stmt)?;
+
+        let mut rows_affected = 0;
+        let mut rows = Vec::new();
+        let mut results = sqlx::Executor::fetch_many(&mut *self, query);
+        while let Some(result) = results.try_next().await? {
+            match result {
+                Either::Left(result) => rows_affected += result.rows_affected(),
+                Either::Right(row) => rows.push(row),
             }
-            Type::U16 => {
-                let value = decoder.u16()?;
-                if let Ok(value) = i16::try_from(value) {
-                    self.bind(value)
-                } else {
-                    self.bind(value as i32)
-                }
+        }
+        drop(results);
+        let num_rows = rows.len() as u64;
+        let (columns, rows) = match rows.first() {
+            Some(row) => {
+                let types = column_types(row.columns(), None)?;
+                (
+                    mssql_columns(row.columns(), &types),
+                    mssql_to_cbor(&rows, &types)?,
+                )
             }
-            Type::U32 => {
-                let value = decoder.u32()?;
-                if let Ok(value) = i32::try_from(value) {
-                    self.bind(value)
-                } else {
-                    self.bind(value as i64)
-                }
+            None => (Vec::new(), mssql_to_cbor(&rows, &[])?),
+        };
+        Ok(ReturningResult {
+            rows_affected,
+            num_rows,
+            columns,
+            rows,
+            error: None,
+        })
+    }
+
+    async fn fetch_multi(
+        &mut self,
+        stmt: &Statement,
+        // SQL Server has no JSON column type
+        _encoding: &EncodingOptions,
+    ) -> Result<MultiQueryResult> {
+        // a single query is captured with its columns converted, as it is in
+        // `fetch_all`
+        let capture = convert::capture(self, &stmt.sql).await?;
+        let sql = capture
+            .as_ref()
+            .map_or(stmt.sql.as_str(), |capture| &capture.sql);
+        let query = bind_mssql_query(sql, stmt)?;
+        let results = sqlx::Executor::fetch_many(&mut *self, query);
+        let (result_sets, mut rows_affected) =
+            split_results(results, |result| result.rows_affected()).await?;
+        if capture.is_some() {
+            // the counts are those of the query, not of capturing its rows
+            rows_affected = if result_sets.is_empty() {
+                vec![0]
+            } else {
+                Vec::new()
+            };
+        }
+        if result_sets.is_empty() && rows_affected == [0] {
+            // a query that returns no rows still has its columns
+            let columns = match &capture {
+                Some(capture) => captured_columns(capture),
+                None => described_columns(self, &stmt.sql).await?,
+            };
+            if !columns.is_empty() {
+                return Ok(MultiQueryResult {
+                    result_sets: vec![ResultSet {
+                        columns,
+                        rows: mssql_to_cbor(&[], &[])?,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                });
             }
-            Type::U64 => {
-                let value = decoder.u64()?;
-                if let Ok(value) = i64::try_from(value) {
-                    self.bind(value)
-                } else {
-                    return Err(Error::CborDeU64OutOfRange(value));
+        }
+        Ok(MultiQueryResult {
+            result_sets: result_sets
+                .iter()
+                .enumerate()
+                .map(|(i, rows)| result_set(rows, capture.as_ref().filter(|_| i == 0)))
+                .collect::<Result<_>>()?,
+            rows_affected,
+            error: None,
+        })
+    }
+
+    async fn call(
+        &mut self,
+        call: &ProcedureCall,
+        encoding: &EncodingOptions,
+    ) -> Result<CallResult> {
+        let mut declarations = Vec::new();
+        let mut args = Vec::new();
+        let mut parameters = Vec::new();
+        let mut outputs = Vec::new();
+        for (i, param) in call.parameters.iter().enumerate() {
+            let arg = match param.mode {
+                ParamMode::In => {
+                    parameters.push(call::value(param));
+                    format!("@p{}", parameters.len())
                 }
-            }
-            Type::I8 => self.bind(decoder.i8()?),
-            Type::I16 => self.bind(decoder.i16()?),
-            Type::I32 => self.bind(decoder.i32()?),
-            Type::I64 => self.bind(decoder.i64()?),
-            Type::Int => {
-                let int = decoder.int()?;
-                if let Ok(value) = i16::try_from(int) {
+                _ if call.function => {
+                    return Err(Error::InvalidCall(
+                        "SQL Server functions only take in parameters".into(),
+                    ))
+                }
+                mode => {
+                    // out and inout parameters are passed in variables, which
+                    // have to be declared with their type
+                    let db_type = param.db_type.as_deref().ok_or_else(|| {
+                        Error::InvalidCall(format!("parameter {} has no dbType", i + 1))
+                    })?;
+                    let variable = format!("@_call_{}", i + 1);
+                    declarations.push(format!(
+                        "declare {} {};",
+                        variable,
+                        ident::type_name(db_type)?
+                    ));
+                    if mode == ParamMode::InOut {
+                        parameters.push(call::value(param));
+                        declarations.push(format!("set {} = @p{};", variable, parameters.len()));
+                    }
+                    let arg = format!("{} output", variable);
+                    outputs.push((variable, call::output_name(i, param)?, db_type.to_string()));
+                    arg
+                }
+            };
+            args.push(match &param.name {
+                Some(name) => format!(
+                    "@{} = {}",
+                    ident::identifier(name.trim_start_matches('@'))?,
+                    arg
+                ),
+                None => arg,
+            });
+        }
+        let procedure = ident::identifier(&call.procedure)?;
+        if call.function {
+            let stmt = Statement {
+                sql: format!(
+                    "select {}({}) as [return_value]",
+                    procedure,
+                    args.join(", ")
+                ),
+                parameters: Some(parameters),
+                ..Default::default()
+            };
+            return call::function_result(self.fetch_multi(&stmt, encoding).await?);
+        }
+
+        let mut sql = format!(
+            "declare @_call_return int;\n{}\nexec @_call_return = {} {};\n",
+            declarations.join("\n"),
+            procedure,
+            args.join(", ")
+        );
+        // the outputs are selected with the types the driver can't read
+        // converted
+        let outputs = (!outputs.is_empty()).then(|| convert::outputs(&outputs));
+        if let Some(outputs) = &outputs {
+            sql.push_str(&outputs.sql);
+        }
+        sql.push_str("select @_call_return as [return_value];");
+        let stmt = Statement {
+            sql,
+            parameters: Some(parameters),
+            ..Default::default()
+        };
+        let query = bind_mssql_query(&stmt.sql, &stmt)?;
+        let results = sqlx::Executor::fetch_many(&mut *self, query);
+        let (row_sets, rows_affected) =
+            split_results(results, |result| result.rows_affected()).await?;
+
+        // the batch ends with the outputs and then the return status
+        let outputs_at = row_sets.len().checked_sub(2).filter(|_| outputs.is_some());
+        let mut result_sets = row_sets
+            .iter()
+            .enumerate()
+            .map(|(i, rows)| result_set(rows, outputs.as_ref().filter(|_| Some(i) == outputs_at)))
+            .collect::<Result<Vec<_>>>()?;
+        let return_value = match result_sets.pop() {
+            Some(result_set) => call::single_value(&result_set)?,
+            None => None,
+        };
+        let outputs = match outputs {
+            Some(_) => result_sets.pop().unwrap_or_default(),
+            None => ResultSet::default(),
+        };
+        Ok(CallResult {
+            outputs,
+            return_value,
+            result_sets,
+            rows_affected,
+            ..Default::default()
+        })
+    }
+
+    async fn bulk_insert(&mut self, insert: &BulkInsert) -> Result<u64> {
+        let statements = bulk::insert_statements(insert, Dialect::Mssql)?;
+        let mut tx = sqlx::Connection::begin(self).await?;
+        let mut rows_affected = 0;
+        for stmt in &statements {
+            rows_affected += tx.execute(stmt).await?.rows_affected;
+        }
+        tx.commit().await?;
+        Ok(rows_affected)
+    }
+
+    async fn export(
+        &mut self,
+        _export: &Export,
+        _chunk_size: usize,
+        _chunks: &mpsc::Sender<Result<Vec<u8>>>,
+    ) -> Result<()> {
+        Err(Error::Unsupported("exporting from SQL Server"))
+    }
+
+    async fn list_schemas(&mut self) -> Result<Vec<String>> {
+        catalog::list_schemas(self).await
+    }
+
+    async fn list_tables(&mut self, schema: Option<&str>) -> Result<Vec<TableInfo>> {
+        catalog::list_tables(self, schema).await
+    }
+
+    async fn describe_table(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+    ) -> Result<Option<TableDescription>> {
+        catalog::describe_table(self, schema, table).await
+    }
+}
+
+/// The SQL Server driver can only declare integer, float and string
+/// parameters, so hinted values of other types bind as strings for SQL Server
+/// to convert
+impl<'q> BindTyped<'q> for Query<'q, Mssql, <Mssql as HasArguments<'q>>::Arguments> {
+    type Database = Mssql;
+
+    fn bind_value<T>(self, value: T) -> Self
+    where
+        T: 'q + Send + Encode<'q, Mssql> + Type<Mssql>,
+    {
+        self.bind(value)
+    }
+
+    fn bind_bytes(self, value: Vec<u8>) -> Result<Self> {
+        Ok(self.bind(to_hex(&value)))
+    }
+}
+
+impl<'q> BindCbor for Query<'q, Mssql, <Mssql as HasArguments<'q>>::Arguments> {
+    fn bind_cbor(self, value: &[u8]) -> Result<Self> {
+        let query = match Param::decode(value)? {
+            Param::Null => self.bind(None::<bool>),
+            Param::Bool(value) => self.bind(value),
+            Param::Int(int) => {
+                // TINYINT is unsigned
+                if let Ok(value) = u8::try_from(int) {
+                    self.bind(value)
+                } else if let Ok(value) = i16::try_from(int) {
                     self.bind(value)
                 } else if let Ok(value) = i32::try_from(int) {
                     self.bind(value)
                 } else if let Ok(value) = i64::try_from(int) {
                     self.bind(value)
                 } else {
-                    return Err(Error::CborDeIntOutOfRange(int));
+                    return Err(Param::int_out_of_range(int));
                 }
             }
-            Type::F16 => self.bind(decoder.f16()?),
-            Type::F32 => self.bind(decoder.f32()?),
-            Type::F64 => self.bind(decoder.f64()?),
-            // Type::Simple => todo!(),
-            // Type::Bytes => todo!(),
-            // Type::BytesIndef => todo!(),
-            Type::String => self.bind(decoder.str()?.to_string()),
-            // Type::StringIndef => todo!(),
-            // Type::Array => todo!(),
-            // Type::ArrayIndef => todo!(),
-            // Type::Map => todo!(),
-            // Type::MapIndef => todo!(),
-            // Type::Tag => todo!(),
-            // Type::Break => todo!(),
-            // Type::Unknown(_) => todo!(),
-            _ => return Err(Error::CborDeType(datatype)),
+            Param::F32(value) => self.bind(value),
+            Param::F64(value) => self.bind(value),
+            // the driver can't declare binary parameters, so they are bound as hex
+            // strings for the statement to convert
+            Param::Bytes(value) => self.bind(to_hex(&value)),
+            Param::Text(value) => self.bind(value),
+            Param::Array(_) | Param::Map(_) | Param::Set(_) => {
+                return Err(Error::CborDeValue(
+                    "arrays and maps are not supported as SQL Server parameters".into(),
+                ))
+            }
+            // the driver can only declare integer, float and string parameters, so
+            // tagged values are bound as strings for SQL Server to convert
+            Param::Tagged(tagged) => match tagged {
+                TaggedValue::DateTime(value) => self.bind(value.format(&Rfc3339)?),
+                TaggedValue::Uuid(value) => self.bind(value.as_hyphenated().to_string()),
+                TaggedValue::Number(value) => match tag::as_i64(&value) {
+                    Some(value) => self.bind(value),
+                    None => self.bind(value.to_string()),
+                },
+            },
+            Param::UserTyped(name, _) => return Err(Error::TypeHint(name)),
+            Param::Hinted(TypeHint::Jsonb, _) => {
+                return Err(Error::TypeHintNotSupported("jsonb", "SQL Server"))
+            }
+            Param::Hinted(hint, param) => self.bind_typed_value(hint.convert(*param)?)?,
         };
 
         Ok(query)
     }
 }
 
-fn mssql_to_cbor(rows: &[MssqlRow]) -> Result<Vec<u8>> {
+fn mssql_to_cbor(rows: &[MssqlRow], types: &[ColumnType]) -> Result<Vec<u8>> {
     let mut buf = Vec::with_capacity(rows.len() * 2);
     let mut out = minicbor::Encoder::new(&mut buf);
 
@@ -123,49 +406,259 @@ fn mssql_to_cbor(rows: &[MssqlRow]) -> Result<Vec<u8>> {
     for row in rows {
         out.array(row.len() as u64)?;
 
-        for column in row.columns() {
+        for (column, column_type) in row.columns().iter().zip(types) {
             let value_ref = row.try_get_raw(column.ordinal())?;
             if value_ref.is_null() {
                 out.null()?;
                 continue;
             }
 
-            let type_name = column.type_info().name();
-            match type_name {
-                "BOOLEAN" => {
+            let type_name = column_type.name();
+            if column_type.is_converted() {
+                let value = <String as Decode<Mssql>>::decode(value_ref)?;
+                if value_kind(type_name) == ValueKind::Bytes {
+                    out.bytes(&from_hex(&value)?)?;
+                } else {
+                    out.str(&value)?;
+                }
+                continue;
+            }
+            if !column_type.is_readable() {
+                // the server only converts the columns of single read-only
+                // queries by itself
+                let target = match value_kind(type_name) {
+                    ValueKind::Bytes => "a hex string with CONVERT(varchar(8000), value, 2)",
+                    _ => "nvarchar(4000)",
+                };
+                return Err(Error::DbTypeCast(type_name.into(), target));
+            }
+            match value_kind(type_name) {
+                ValueKind::Null => {
+                    out.null()?;
+                }
+
+                ValueKind::Bool => {
                     out.encode(<bool as Decode<Mssql>>::decode(value_ref)?)?;
                 }
 
-                "TINYINT" => {
-                    out.encode(<i8 as Decode<Mssql>>::decode(value_ref)?)?;
+                ValueKind::UInt8 => {
+                    out.encode(<u8 as Decode<Mssql>>::decode(value_ref)?)?;
                 }
-                "SMALLINT" => {
+                ValueKind::Int16 => {
                     out.encode(<i16 as Decode<Mssql>>::decode(value_ref)?)?;
                 }
-                "INT" => {
+                ValueKind::Int32 => {
                     out.encode(<i32 as Decode<Mssql>>::decode(value_ref)?)?;
                 }
-                "BIGINT" => {
+                ValueKind::Int64 => {
                     out.encode(<i64 as Decode<Mssql>>::decode(value_ref)?)?;
                 }
 
-                "REAL" => {
+                ValueKind::Float32 => {
                     out.encode(<f32 as Decode<Mssql>>::decode(value_ref)?)?;
                 }
-                "FLOAT" => {
+                ValueKind::Float64 => {
                     out.encode(<f64 as Decode<Mssql>>::decode(value_ref)?)?;
                 }
 
-                "CHAR" | "BIGCHAR" | "NCHAR" | "VARCHAR" | "NVARCHAR" | "BIGVARCHAR" => {
+                ValueKind::Text => {
                     out.encode(<String as Decode<Mssql>>::decode(value_ref)?)?;
                 }
 
-                _ => {
-                    return Err(Error::DbType(type_name.into()));
-                }
+                _ => match type_name {
+                    // the remaining types are named by their declared types, which
+                    // fix their width on the wire
+                    "MONEY" | "SMALLMONEY" => {
+                        let size = if type_name == "MONEY" { 8 } else { 4 };
+                        let raw = types::read_fixed(value_ref, size)?;
+                        out.encode(types::format_money(raw, size))?;
+                    }
+
+                    "DATETIME" | "SMALLDATETIME" => {
+                        let size = if type_name == "DATETIME" { 8 } else { 4 };
+                        let raw = types::read_fixed(value_ref, size)?;
+                        let timestamp = types::decode_datetime(raw, size)?;
+                        out.encode(timestamp.format(TIMESTAMP_FORMAT)?)?;
+                    }
+
+                    "DATETIME2" => {
+                        let raw = types::read_fixed(value_ref, 8)?;
+                        let timestamp = types::decode_datetime2(raw, column_type.scale())?;
+                        out.encode(timestamp.format(TIMESTAMP_FORMAT)?)?;
+                    }
+
+                    // 8 to 10 bytes wide, with the date and time in the first 8
+                    "DATETIMEOFFSET" => {
+                        let raw = types::read_fixed(value_ref, 8)?;
+                        let timestamp = types::decode_datetimeoffset(raw, column_type.scale())?;
+                        out.encode(timestamp.format(&Rfc3339)?)?;
+                    }
+
+                    "TIME" => {
+                        let raw = types::read_fixed(value_ref, 4)?;
+                        let time = types::time_of_day(raw as u64, column_type.scale())?;
+                        out.encode(time.format(TIME_FORMAT)?)?;
+                    }
+
+                    _ => {
+                        return Err(Error::DbType(type_name.into()));
+                    }
+                },
             }
         }
     }
 
     Ok(buf)
 }
+
+/// Kind of the value `mssql_to_cbor` writes for a column type, whether it's
+/// read as it's sent or converted by the server
+pub(super) fn value_kind(type_name: &str) -> ValueKind {
+    match type_name {
+        "NULL" => ValueKind::Null,
+        "BOOLEAN" => ValueKind::Bool,
+        // TINYINT is unsigned
+        "TINYINT" => ValueKind::UInt8,
+        "SMALLINT" => ValueKind::Int16,
+        "INT" => ValueKind::Int32,
+        "BIGINT" => ValueKind::Int64,
+        "REAL" => ValueKind::Float32,
+        "FLOAT" => ValueKind::Float64,
+        "CHAR" | "BIGCHAR" | "NCHAR" | "VARCHAR" | "NVARCHAR" | "BIGVARCHAR" | "TEXT" | "NTEXT"
+        | "XML" | "UNIQUEIDENTIFIER" | "DECIMAL" | "NUMERIC" => ValueKind::Text,
+        "BINARY" | "VARBINARY" | "IMAGE" | "TIMESTAMP" => ValueKind::Bytes,
+        "DATE" => ValueKind::Date,
+        "TIME" => ValueKind::Time,
+        "DATETIME" | "SMALLDATETIME" | "DATETIME2" => ValueKind::Timestamp,
+        "DATETIMEOFFSET" => ValueKind::TimestampTz,
+        _ => ValueKind::Other,
+    }
+}
+
+fn mssql_columns(columns: &[MssqlColumn], types: &[ColumnType]) -> Vec<Column> {
+    columns
+        .iter()
+        .zip(types)
+        .map(|(column, column_type)| Column {
+            ordinal: column.ordinal() as u32,
+            name: column.name().into(),
+            db_type: column_type.name().into(),
+        })
+        .collect()
+}
+
+/// Types of the result columns, as they're sent or as the server converted
+/// them
+fn column_types(columns: &[MssqlColumn], capture: Option<&Capture>) -> Result<Vec<ColumnType>> {
+    columns
+        .iter()
+        .enumerate()
+        .map(
+            |(i, column)| match capture.and_then(|capture| capture.converted(i)) {
+                Some(column_type) => Ok(column_type.clone()),
+                None => ColumnType::of(column.type_info()),
+            },
+        )
+        .collect()
+}
+
+/// Result set of rows, with the columns of a capture converted
+fn result_set(rows: &[MssqlRow], capture: Option<&Capture>) -> Result<ResultSet> {
+    let types = column_types(rows[0].columns(), capture)?;
+    Ok(ResultSet {
+        num_rows: rows.len() as u64,
+        columns: mssql_columns(rows[0].columns(), &types),
+        rows: mssql_to_cbor(rows, &types)?,
+    })
+}
+
+/// Columns of a statement without rows, which the driver describes
+async fn described_columns(conn: &mut MssqlConnection, sql: &str) -> Result<Vec<Column>> {
+    Ok(
+        match described(sqlx::Executor::describe(&mut *conn, sql).await)? {
+            Some(describe) => {
+                mssql_columns(describe.columns(), &column_types(describe.columns(), None)?)
+            }
+            None => Vec::new(),
+        },
+    )
+}
+
+/// Columns of a captured statement without rows, as the server declares them
+fn captured_columns(capture: &Capture) -> Vec<Column> {
+    capture
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, (name, column_type))| Column {
+            ordinal: i as u32,
+            name: name.clone().unwrap_or_default(),
+            db_type: column_type.name().into(),
+        })
+        .collect()
+}
+
+fn bind_mssql_query<'q>(
+    sql: &'q str,
+    stmt: &'q Statement,
+) -> Result<Query<'q, Mssql, <Mssql as HasArguments<'q>>::Arguments>> {
+    let mut query = sqlx::query::<Mssql>(sql);
+    if let Some(params) = &stmt.parameters {
+        for value in params {
+            query = query.bind_cbor(value)?;
+        }
+    }
+    Ok(query)
+}
+
+/// Add an `OUTPUT INSERTED` clause to an `INSERT` statement that doesn't have
+/// an `OUTPUT` clause, returning the key columns or else all columns
+fn with_output(sql: &str, key_columns: &[String]) -> String {
+    let dialect = Dialect::Mssql;
+    let kind = first_keyword(sql, dialect, &["select", "insert", "update", "delete"]);
+    if kind != Some("insert") || find_keyword(sql, dialect, &["output"]).is_some() {
+        return sql.to_string();
+    }
+    // the clause goes between the column list and the values
+    let at = match find_keyword(
+        sql,
+        dialect,
+        &["values", "select", "default", "exec", "execute"],
+    ) {
+        Some(at) => at,
+        None => return sql.to_string(),
+    };
+    let columns = if key_columns.is_empty() {
+        "INSERTED.*".to_string()
+    } else {
+        key_columns
+            .iter()
+            .map(|column| format!("INSERTED.[{}]", column.replace(']', "]]")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("{}OUTPUT {} {}", &sql[..at], columns, &sql[at..])
+}
+
+/// Bytes of the hex string the server converted a binary value to
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| Error::Sqlx(format!("invalid hex string `{}`", hex).into()))
+        })
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2 + 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
@@ -0,0 +1,123 @@
+//! SQL Server schema introspection
+//!
+//! Tables and columns are read from `INFORMATION_SCHEMA`, and indexes and
+//! foreign keys from the `sys` catalog views.
+
+use sqlx::MssqlConnection;
+
+use crate::{
+    ext::{TableDescription, TableInfo},
+    result::Result,
+};
+
+use super::super::catalog::{self, ColumnRow, ForeignKeyRow, IndexRow};
+
+// schemas of the fixed database roles are left out along with the system ones
+const SCHEMAS_SQL: &str = r#"
+select name from sys.schemas
+where name not in ('sys', 'INFORMATION_SCHEMA', 'guest') and name not like 'db[_]%'
+order by name
+"#;
+
+const TABLES_SQL: &str = r#"
+select TABLE_SCHEMA, TABLE_NAME, TABLE_TYPE
+from INFORMATION_SCHEMA.TABLES
+where (@p1 is null and TABLE_SCHEMA not in ('sys', 'INFORMATION_SCHEMA')) or TABLE_SCHEMA = @p1
+order by 1, 2
+"#;
+
+const TABLE_SQL: &str = r#"
+select TABLE_SCHEMA, TABLE_NAME, TABLE_TYPE
+from INFORMATION_SCHEMA.TABLES
+where TABLE_SCHEMA = coalesce(@p1, schema_name()) and TABLE_NAME = @p2
+"#;
+
+const COLUMNS_SQL: &str = r#"
+select COLUMN_NAME, cast(ORDINAL_POSITION as bigint), DATA_TYPE,
+    cast(case when IS_NULLABLE = 'YES' then 1 else 0 end as bit), COLUMN_DEFAULT,
+    cast(CHARACTER_MAXIMUM_LENGTH as bigint),
+    cast(coalesce(NUMERIC_PRECISION, DATETIME_PRECISION) as bigint),
+    cast(NUMERIC_SCALE as bigint)
+from INFORMATION_SCHEMA.COLUMNS
+where TABLE_SCHEMA = @p1 and TABLE_NAME = @p2
+order by ORDINAL_POSITION
+"#;
+
+const INDEXES_SQL: &str = r#"
+select i.name, i.is_unique, i.is_primary_key, c.name
+from sys.indexes i
+join sys.index_columns ic on ic.object_id = i.object_id and ic.index_id = i.index_id
+join sys.columns c on c.object_id = ic.object_id and c.column_id = ic.column_id
+where i.object_id = object_id(quotename(@p1) + '.' + quotename(@p2))
+    and i.name is not null and ic.is_included_column = 0
+order by i.name, ic.key_ordinal
+"#;
+
+const FOREIGN_KEYS_SQL: &str = r#"
+select fk.name, col_name(fkc.parent_object_id, fkc.parent_column_id),
+    object_schema_name(fkc.referenced_object_id), object_name(fkc.referenced_object_id),
+    col_name(fkc.referenced_object_id, fkc.referenced_column_id)
+from sys.foreign_keys fk
+join sys.foreign_key_columns fkc on fkc.constraint_object_id = fk.object_id
+where fk.parent_object_id = object_id(quotename(@p1) + '.' + quotename(@p2))
+order by fk.name, fkc.constraint_column_id
+"#;
+
+pub(super) async fn list_schemas(conn: &mut MssqlConnection) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar(SCHEMAS_SQL).fetch_all(conn).await?)
+}
+
+pub(super) async fn list_tables(
+    conn: &mut MssqlConnection,
+    schema: Option<&str>,
+) -> Result<Vec<TableInfo>> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(TABLES_SQL)
+        .bind(schema)
+        .fetch_all(conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(schema, name, table_type)| TableInfo {
+            schema,
+            name,
+            kind: catalog::table_kind(&table_type),
+        })
+        .collect())
+}
+
+pub(super) async fn describe_table(
+    conn: &mut MssqlConnection,
+    schema: Option<&str>,
+    table: &str,
+) -> Result<Option<TableDescription>> {
+    let found: Option<(String, String, String)> = sqlx::query_as(TABLE_SQL)
+        .bind(schema)
+        .bind(table)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let found = match found {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let columns: Vec<ColumnRow> = sqlx::query_as(COLUMNS_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    let indexes: Vec<IndexRow> = sqlx::query_as(INDEXES_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    let foreign_keys: Vec<ForeignKeyRow> = sqlx::query_as(FOREIGN_KEYS_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(Some(catalog::describe_table(
+        found,
+        columns,
+        indexes,
+        foreign_keys,
+    )))
+}
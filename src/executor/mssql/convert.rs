@@ -0,0 +1,312 @@
+//! Conversion of the columns the driver can't read
+//!
+//! The driver can't read `DATE`, `UNIQUEIDENTIFIER`, `DECIMAL`, `NUMERIC` and
+//! binary values, nor `TIME` and `DATETIME2` values of most precisions, and it
+//! can't parse `(max)`, `TEXT`, `NTEXT`, `IMAGE` and `XML` values at all. A
+//! read-only statement is described before it runs, and when any of its
+//! columns are of such types, its rows are captured in a table variable, in
+//! order, and selected from it with those columns converted to strings:
+//!
+//! - `DATE`, `TIME` and `DATETIME2` in the formats of other date and time
+//!   values, to the second
+//! - binary types as hex, which is decoded back to bytes
+//! - `UNIQUEIDENTIFIER`, `DECIMAL` and `NUMERIC` as SQL Server writes them
+//! - other types to `nvarchar(4000)`
+//!
+//! The output variables of a procedure call are converted the same way when
+//! they're selected. Binary and `nvarchar` values are converted up to 4000
+//! bytes or characters, and the statement fails on longer values rather than
+//! truncate them.
+
+use sqlx::MssqlConnection;
+
+use crate::result::Result;
+
+use super::{
+    super::{
+        placeholder::{find_keyword, split_statements, Dialect},
+        tables,
+    },
+    metadata,
+    types::ColumnType,
+};
+
+/// Most bytes or characters of a converted binary or `nvarchar` value
+const MAX_CONVERTED: usize = 4000;
+
+/// Batch selecting the rows of a statement, or the outputs of a call, with the
+/// columns the driver can't read converted
+#[derive(Debug)]
+pub(super) struct Capture {
+    pub(super) sql: String,
+    /// names and types of the columns, with the converted ones marked
+    columns: Vec<(Option<String>, ColumnType)>,
+}
+
+impl Capture {
+    /// Type of a converted column, or none for columns read as they're sent
+    pub(super) fn converted(&self, ordinal: usize) -> Option<&ColumnType> {
+        self.columns
+            .get(ordinal)
+            .map(|(_, column_type)| column_type)
+            .filter(|column_type| column_type.is_converted())
+    }
+
+    /// Names and declared types of the columns, for results without rows
+    pub(super) fn columns(&self) -> &[(Option<String>, ColumnType)] {
+        &self.columns
+    }
+}
+
+/// Capture a statement whose columns the driver can't all read. There's none
+/// when it can read them all, or the statement isn't a single query the server
+/// can describe.
+pub(super) async fn capture(conn: &mut MssqlConnection, sql: &str) -> Result<Option<Capture>> {
+    let dialect = Dialect::Mssql;
+    if !tables::is_read_only(sql, dialect) || split_statements(sql, dialect).len() != 1 {
+        return Ok(None);
+    }
+    Ok(metadata::result_columns(conn, sql)
+        .await?
+        .and_then(|columns| capture_sql(sql, &columns)))
+}
+
+/// Batch capturing the rows of a query with columns of these names and
+/// declared types
+fn capture_sql(sql: &str, columns: &[(Option<String>, String)]) -> Option<Capture> {
+    let types: Vec<_> = columns
+        .iter()
+        .map(|(_, declared)| ColumnType::declared(declared))
+        .collect();
+    if types.iter().all(ColumnType::is_readable) {
+        return None;
+    }
+    let sql = sql.trim_end().trim_end_matches(';');
+    // the rows are inserted by the query itself, after its `with` clause
+    let at = find_keyword(sql, Dialect::Mssql, &["select"])?;
+
+    let mut declarations = vec!["[_sqldb_row] int identity primary key".to_string()];
+    let mut captured = Vec::new();
+    let mut checks = String::new();
+    let mut selected = Vec::new();
+    let mut capture_columns = Vec::new();
+    for (i, ((name, declared), column_type)) in columns.iter().zip(types).enumerate() {
+        let column = format!("[c{}]", i + 1);
+        declarations.push(format!("{} {}", column, capture_type(declared)));
+        captured.push(column.clone());
+        let (expr, column_type) = if column_type.is_readable() {
+            (column, column_type)
+        } else {
+            let (expr, too_long) = conversion(&column, &column_type);
+            if let Some(too_long) = too_long {
+                checks.push_str(&format!(
+                    "if exists (select * from @_sqldb where {}) {}\n",
+                    too_long,
+                    throw_too_long(name.as_deref().unwrap_or_default())
+                ));
+            }
+            (expr, column_type.converted())
+        };
+        selected.push(match name {
+            Some(name) => format!("{} as [{}]", expr, name.replace(']', "]]")),
+            None => expr,
+        });
+        capture_columns.push((name.clone(), column_type));
+    }
+
+    Some(Capture {
+        sql: format!(
+            "declare @_sqldb table ({});\n{}insert into @_sqldb ({}) {}\n;\n{}select {} from \
+             @_sqldb order by [_sqldb_row];",
+            declarations.join(", "),
+            &sql[..at],
+            captured.join(", "),
+            &sql[at..],
+            checks,
+            selected.join(", ")
+        ),
+        columns: capture_columns,
+    })
+}
+
+/// Batch selecting the output variables of a procedure call, given with the
+/// names and declared types of the parameters they're passed as
+pub(super) fn outputs(variables: &[(String, String, String)]) -> Capture {
+    let mut checks = String::new();
+    let mut selected = Vec::new();
+    let mut columns = Vec::new();
+    for (variable, name, declared) in variables {
+        let column_type = ColumnType::declared(declared);
+        let (expr, column_type) = if column_type.is_readable() {
+            (variable.clone(), column_type)
+        } else {
+            let (expr, too_long) = conversion(variable, &column_type);
+            if let Some(too_long) = too_long {
+                checks.push_str(&format!("if {} {}\n", too_long, throw_too_long(name)));
+            }
+            (expr, column_type.converted())
+        };
+        selected.push(format!("{} as [{}]", expr, name.replace(']', "]]")));
+        columns.push((Some(name.clone()), column_type));
+    }
+    Capture {
+        sql: format!("{}select {};\n", checks, selected.join(", ")),
+        columns,
+    }
+}
+
+/// Statement failing the batch on a value of a column too long to convert
+fn throw_too_long(name: &str) -> String {
+    let message = format!(
+        "a value of column `{}` is longer than the {} characters or bytes it can be converted \
+         to",
+        name, MAX_CONVERTED
+    );
+    format!("throw 50000, N'{}', 1;", message.replace('\'', "''"))
+}
+
+/// Type of the table column capturing a result column of a declared type,
+/// replacing the types table variables can't have
+fn capture_type(declared: &str) -> &str {
+    match declared.to_ascii_lowercase().as_str() {
+        "text" => "varchar(max)",
+        "ntext" => "nvarchar(max)",
+        "image" => "varbinary(max)",
+        // a table has at most one rowversion column, which the server sets
+        "timestamp" => "binary(8)",
+        _ => declared,
+    }
+}
+
+/// Expression converting a captured column to a string, and the condition of
+/// the values too long to convert. The driver decodes `varchar` values only in
+/// the collations of its locale, so hex strings are given one of them.
+fn conversion(column: &str, column_type: &ColumnType) -> (String, Option<String>) {
+    match column_type.name() {
+        "DATE" => (format!("convert(nchar(10), {}, 23)", column), None),
+        "TIME" => (format!("convert(nchar(8), {}, 108)", column), None),
+        "DATETIME2" => (format!("convert(nchar(19), {}, 126)", column), None),
+        "UNIQUEIDENTIFIER" => (format!("convert(nchar(36), {})", column), None),
+        "DECIMAL" | "NUMERIC" => (format!("convert(nvarchar(41), {})", column), None),
+        "BINARY" | "VARBINARY" | "IMAGE" | "TIMESTAMP" => (
+            format!(
+                "convert(varchar(8000), {}, 2) collate Latin1_General_BIN2",
+                column
+            ),
+            Some(format!("datalength({}) > {}", column, MAX_CONVERTED)),
+        ),
+        _ => (
+            format!("convert(nvarchar(4000), {})", column),
+            Some(format!(
+                "datalength(convert(nvarchar(max), {})) > {}",
+                column,
+                2 * MAX_CONVERTED
+            )),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(columns: &[(&str, &str)]) -> Vec<(Option<String>, String)> {
+        columns
+            .iter()
+            .map(|(name, declared)| (Some(name.to_string()), declared.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn readable_columns_are_not_captured() {
+        let columns = columns(&[
+            ("id", "int"),
+            ("name", "nvarchar(50)"),
+            ("at", "datetime"),
+            ("t", "time(3)"),
+        ]);
+        assert!(capture_sql("select * from t", &columns).is_none());
+    }
+
+    #[test]
+    fn converted_columns() {
+        let columns = columns(&[
+            ("id", "int"),
+            ("day", "date"),
+            ("data", "varbinary(16)"),
+            ("notes", "ntext"),
+        ]);
+        let capture = capture_sql("select id, day, data, notes from t order by id;", &columns)
+            .expect("captured");
+        assert_eq!(
+            capture.sql,
+            "declare @_sqldb table ([_sqldb_row] int identity primary key, [c1] int, [c2] date, \
+             [c3] varbinary(16), [c4] nvarchar(max));\n\
+             insert into @_sqldb ([c1], [c2], [c3], [c4]) select id, day, data, notes from t \
+             order by id\n;\n\
+             if exists (select * from @_sqldb where datalength([c3]) > 4000) throw 50000, N'a \
+             value of column `data` is longer than the 4000 characters or bytes it can be \
+             converted to', 1;\n\
+             if exists (select * from @_sqldb where datalength(convert(nvarchar(max), [c4])) > \
+             8000) throw 50000, N'a value of column `notes` is longer than the 4000 characters \
+             or bytes it can be converted to', 1;\n\
+             select [c1] as [id], convert(nchar(10), [c2], 23) as [day], convert(varchar(8000), \
+             [c3], 2) collate Latin1_General_BIN2 as [data], convert(nvarchar(4000), [c4]) as \
+             [notes] from @_sqldb order by [_sqldb_row];"
+        );
+        assert!(capture.converted(0).is_none());
+        assert_eq!(capture.converted(1).map(ColumnType::name), Some("DATE"));
+        assert_eq!(
+            capture.converted(2).map(ColumnType::name),
+            Some("VARBINARY")
+        );
+        assert_eq!(capture.converted(3).map(ColumnType::name), Some("NTEXT"));
+    }
+
+    #[test]
+    fn common_table_expressions() {
+        let columns = columns(&[("id", "uniqueidentifier")]);
+        let capture = capture_sql(
+            "with ids as (select id from t) select id from ids",
+            &columns,
+        )
+        .expect("captured");
+        assert!(
+            capture.sql.contains(
+                "with ids as (select id from t) insert into @_sqldb ([c1]) select id from ids"
+            ),
+            "{}",
+            capture.sql
+        );
+    }
+
+    #[test]
+    fn call_outputs() {
+        let variables = [
+            ("@_call_1", "total", "money"),
+            ("@_call_2", "at", "datetime"),
+            ("@_call_3", "took", "time"),
+        ]
+        .map(|(variable, name, declared)| {
+            (variable.to_string(), name.to_string(), declared.to_string())
+        });
+        let outputs = outputs(&variables);
+        assert_eq!(
+            outputs.sql,
+            "select @_call_1 as [total], @_call_2 as [at], convert(nchar(8), @_call_3, 108) as \
+             [took];\n"
+        );
+        assert!(outputs.converted(1).is_none());
+        assert_eq!(outputs.converted(2).map(ColumnType::name), Some("TIME"));
+    }
+
+    #[test]
+    fn time_precisions() {
+        let columns = columns(&[("a", "time(7)"), ("b", "datetime2(3)"), ("c", "datetime2")]);
+        let capture = capture_sql("select a, b, c from t", &columns).expect("captured");
+        assert!(capture.converted(0).is_some());
+        assert!(capture.converted(1).is_some());
+        // precision 7 is read as it's sent
+        assert!(capture.converted(2).is_none());
+    }
+}
@@ -0,0 +1,178 @@
+//! SQL Server column metadata
+//!
+//! Columns are described by `sp_describe_first_result_set` in browse mode,
+//! which reports the source of each column along with its type details, and
+//! primary keys are read from `sys.indexes`. The declared types of the result
+//! columns tell which of them the server has to convert for the driver to read
+//! them. Parameters are declared with the types
+//! `sp_describe_undeclared_parameters` deduces for them.
+
+use sqlx::{MssqlConnection, Row};
+
+use crate::{ext::ColumnMetadata, result::Result};
+
+use super::super::{
+    described,
+    placeholder::{replace_placeholders, Dialect},
+};
+
+const DESCRIBE_SQL: &str =
+    "exec sp_describe_first_result_set @tsql = @p1, @params = @p2, @browse_information_mode = 1";
+
+const TYPES_SQL: &str = "exec sp_describe_first_result_set @tsql = @p1, @params = @p2";
+
+const PARAMS_SQL: &str = "exec sp_describe_undeclared_parameters @tsql = @p1";
+
+const PRIMARY_KEY_SQL: &str = r#"
+select c.name from sys.indexes i
+join sys.index_columns ic on ic.object_id = i.object_id and ic.index_id = i.index_id
+join sys.columns c on c.object_id = ic.object_id and c.column_id = ic.column_id
+where i.is_primary_key = 1 and i.object_id = object_id(@p1)
+"#;
+
+pub(super) async fn describe_columns(
+    conn: &mut MssqlConnection,
+    sql: &str,
+) -> Result<Vec<ColumnMetadata>> {
+    let params = declare_params(&mut *conn, sql).await?;
+    let rows = sqlx::query(DESCRIBE_SQL)
+        .bind(sql)
+        .bind(params)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let mut columns = Vec::with_capacity(rows.len());
+    // primary key columns of each source table
+    let mut keys: Vec<(String, Vec<String>)> = Vec::new();
+    for row in rows {
+        // browse mode adds hidden key columns the statement doesn't return
+        if row.try_get::<bool, _>("is_hidden")? {
+            continue;
+        }
+        let system_type: String = row.try_get("system_type_name")?;
+        let base_type = system_type
+            .split('(')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let precision = row.try_get::<u8, _>("precision")? as u32;
+        let max_length = match row.try_get::<i16, _>("max_length")? {
+            // `max` types have no limit
+            -1 => None,
+            n if base_type.starts_with('N') => Some(n as u32 / 2),
+            n => Some(n as u32),
+        };
+
+        let mut column = ColumnMetadata {
+            ordinal: (row.try_get::<i32, _>("column_ordinal")? - 1) as u32,
+            name: row
+                .try_get::<Option<String>, _>("name")?
+                .unwrap_or_default(),
+            nullable: row.try_get("is_nullable")?,
+            source_schema: row.try_get("source_schema")?,
+            source_table: row.try_get("source_table")?,
+            source_column: row.try_get("source_column")?,
+            precision: (precision > 0).then_some(precision),
+            scale: (precision > 0).then_some(row.try_get::<u8, _>("scale")? as u32),
+            max_length: is_string(&base_type).then_some(max_length).flatten(),
+            db_type: base_type,
+            ..Default::default()
+        };
+
+        if let (Some(schema), Some(table), Some(source)) = (
+            &column.source_schema,
+            &column.source_table,
+            &column.source_column,
+        ) {
+            let table = format!("{}.{}", quote_name(schema), quote_name(table));
+            let key = match keys.iter().position(|(name, _)| *name == table) {
+                Some(i) => &keys[i].1,
+                None => {
+                    let key = sqlx::query(PRIMARY_KEY_SQL)
+                        .bind(table.clone())
+                        .fetch_all(&mut *conn)
+                        .await?
+                        .iter()
+                        .map(|row| row.try_get::<String, _>(0))
+                        .collect::<std::result::Result<Vec<_>, _>>()?;
+                    keys.push((table, key));
+                    &keys[keys.len() - 1].1
+                }
+            };
+            column.primary_key = Some(key.contains(source));
+        }
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+/// Names and declared types, e.g. `datetime2(7)`, of the result columns of a
+/// statement, in column order, or none when the server can't describe it
+pub(super) async fn result_columns(
+    conn: &mut MssqlConnection,
+    sql: &str,
+) -> Result<Option<Vec<(Option<String>, String)>>> {
+    let params = declare_params(&mut *conn, sql).await?;
+    let rows = match described(
+        sqlx::query(TYPES_SQL)
+            .bind(sql)
+            .bind(params)
+            .fetch_all(conn)
+            .await,
+    )? {
+        Some(rows) => rows,
+        None => return Ok(None),
+    };
+    rows.iter()
+        .map(|row| Ok((row.try_get("name")?, row.try_get("system_type_name")?)))
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+/// Declarations of the `@pN` parameters of a statement, for the server to
+/// describe it, with the types the server deduces from how they are used.
+/// When it can't deduce them, none are declared and describing the statement
+/// reports the server's error.
+async fn declare_params(conn: &mut MssqlConnection, sql: &str) -> Result<Option<String>> {
+    if !has_params(sql) {
+        return Ok(None);
+    }
+    let rows = match described(sqlx::query(PARAMS_SQL).bind(sql).fetch_all(conn).await)? {
+        Some(rows) => rows,
+        None => return Ok(None),
+    };
+    let params = rows
+        .iter()
+        .map(|row| {
+            Ok(format!(
+                "{} {}",
+                row.try_get::<String, _>("name")?,
+                row.try_get::<String, _>("suggested_system_type_name")?
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((!params.is_empty()).then(|| params.join(", ")))
+}
+
+/// Whether a statement has `@pN` parameters
+fn has_params(sql: &str) -> bool {
+    let mut found = false;
+    replace_placeholders(sql, Dialect::Mssql, '@', |name| {
+        found |= name
+            .strip_prefix('p')
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        None
+    });
+    found
+}
+
+fn quote_name(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+fn is_string(base_type: &str) -> bool {
+    matches!(
+        base_type,
+        "CHAR" | "VARCHAR" | "NCHAR" | "NVARCHAR" | "BINARY" | "VARBINARY"
+    )
+}
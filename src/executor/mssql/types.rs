@@ -0,0 +1,420 @@
+//! SQL Server column types
+//!
+//! The sqlx SQL Server driver only names and decodes integers, floats, `bool`
+//! and strings. Other columns are named by the type they are sent as, which
+//! the driver only tells through its serialized type info, and values whose
+//! first 4 or 8 bytes on the wire hold the whole value are read through the
+//! integer decoders. Values of the remaining types are converted by the
+//! server, see `convert`.
+
+use serde::Deserialize;
+use sqlx::{
+    mssql::{MssqlTypeInfo, MssqlValueRef},
+    Decode, Mssql, ValueRef,
+};
+use time::{macros::date, Date, Duration, OffsetDateTime, PrimitiveDateTime, Time};
+
+use crate::result::{Error, Result};
+
+/// Epoch of `DATETIME` and `SMALLDATETIME` values
+const DATETIME_EPOCH: Date = date!(1900 - 01 - 01);
+/// Epoch of `DATE`, `DATETIME2` and `DATETIMEOFFSET` values
+const DATE_EPOCH: Date = date!(0001 - 01 - 01);
+/// Fractional second digits of time types declared without a precision
+const DEFAULT_SCALE: u32 = 7;
+/// Size on the wire of `(max)` string and binary types
+const MAX_SIZE: u32 = 0xffff;
+
+/// SQL type of a result column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ColumnType {
+    name: String,
+    scale: u32,
+    /// a `(max)` string or binary type
+    max: bool,
+    /// selected as a string the server converted the value to
+    converted: bool,
+}
+
+/// Type of a column on the wire, as the driver serializes its type info
+#[derive(Deserialize)]
+struct WireType {
+    ty: String,
+    size: u32,
+    scale: u32,
+}
+
+impl ColumnType {
+    /// Type of a column from the type it is sent as
+    pub(super) fn of(type_info: &MssqlTypeInfo) -> Result<Self> {
+        let wire = WireType::of(type_info)?;
+        let name = match (wire.ty.as_str(), wire.size) {
+            ("Null", _) => "NULL",
+            ("Bit" | "BitN", _) => "BOOLEAN",
+            ("TinyInt", _) | ("IntN", 1) => "TINYINT",
+            ("SmallInt", _) | ("IntN", 2) => "SMALLINT",
+            ("Int", _) | ("IntN", 4) => "INT",
+            ("BigInt", _) | ("IntN", _) => "BIGINT",
+            ("Real", _) | ("FloatN", 4) => "REAL",
+            ("Float" | "FloatN", _) => "FLOAT",
+            ("SmallMoney", _) | ("MoneyN", 4) => "SMALLMONEY",
+            ("Money" | "MoneyN", _) => "MONEY",
+            ("SmallDateTime", _) | ("DateTimeN", 4) => "SMALLDATETIME",
+            ("DateTime" | "DateTimeN", _) => "DATETIME",
+            ("DateN", _) => "DATE",
+            ("TimeN", _) => "TIME",
+            ("DateTime2N", _) => "DATETIME2",
+            ("DateTimeOffsetN", _) => "DATETIMEOFFSET",
+            ("Guid", _) => "UNIQUEIDENTIFIER",
+            ("Decimal" | "DecimalN", _) => "DECIMAL",
+            ("Numeric" | "NumericN", _) => "NUMERIC",
+            ("Binary" | "BigBinary", _) => "BINARY",
+            ("VarBinary" | "BigVarBinary", _) => "VARBINARY",
+            // strings keep the names the driver gives them
+            (ty, _) => ty,
+        };
+        Ok(ColumnType {
+            name: name.to_ascii_uppercase(),
+            scale: wire.scale,
+            max: wire.size == MAX_SIZE && wire.ty.starts_with(['B', 'N']),
+            converted: false,
+        })
+    }
+
+    /// Type of a column from the type the server declares for it, e.g.
+    /// `datetime2(7)`, named as `of` names the type it is sent as
+    pub(super) fn declared(system_type_name: &str) -> Self {
+        let (name, args) = match system_type_name.split_once('(') {
+            Some((name, args)) => (name, Some(args.trim_end_matches(')').trim())),
+            None => (system_type_name, None),
+        };
+        let name = match name.trim().to_ascii_uppercase().as_str() {
+            "BIT" => "BOOLEAN",
+            "CHAR" => "BIGCHAR",
+            "VARCHAR" => "BIGVARCHAR",
+            name => name,
+        }
+        .to_string();
+        // the precision of a time type is its number of fractional second digits
+        let scale = match name.as_str() {
+            "TIME" | "DATETIME2" | "DATETIMEOFFSET" => args
+                .and_then(|args| args.parse().ok())
+                .unwrap_or(DEFAULT_SCALE),
+            _ => 0,
+        };
+        ColumnType {
+            name,
+            scale,
+            max: args.is_some_and(|args| args.eq_ignore_ascii_case("max")),
+            converted: false,
+        }
+    }
+
+    /// The type of a column whose values the server converts to strings
+    pub(super) fn converted(self) -> Self {
+        ColumnType {
+            converted: true,
+            ..self
+        }
+    }
+
+    /// SQL name of the type
+    pub(super) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Fractional second digits of `TIME`, `DATETIME2` and `DATETIMEOFFSET`
+    pub(super) fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Whether the values are strings the server converted them to
+    pub(super) fn is_converted(&self) -> bool {
+        self.converted
+    }
+
+    /// Whether the values of the type can be read as they are sent, as
+    /// opposed to converted by the server
+    pub(super) fn is_readable(&self) -> bool {
+        match self.name.as_str() {
+            "NULL" | "BOOLEAN" | "TINYINT" | "SMALLINT" | "INT" | "BIGINT" | "REAL" | "FLOAT"
+            | "MONEY" | "SMALLMONEY" | "DATETIME" | "SMALLDATETIME" | "DATETIMEOFFSET" => true,
+            "CHAR" | "VARCHAR" | "BIGCHAR" | "BIGVARCHAR" | "NCHAR" | "NVARCHAR" => !self.max,
+            "DATETIME2" => time_width(self.scale) == 5,
+            "TIME" => time_width(self.scale) == 4,
+            _ => false,
+        }
+    }
+}
+
+impl WireType {
+    fn of(type_info: &MssqlTypeInfo) -> Result<Self> {
+        Ok(serde_json::from_value(serde_json::to_value(type_info)?)?)
+    }
+}
+
+/// Read the first `size` bytes of a value as a little-endian integer. The
+/// value must be at least `size` bytes wide, which its type on the wire tells,
+/// so a value sent as a narrower or variable width type is an error.
+pub(super) fn read_fixed(value_ref: MssqlValueRef<'_>, size: u32) -> Result<i64> {
+    let width = fixed_width(&value_ref.type_info())?;
+    if width < size {
+        return Err(Error::Sqlx(
+            format!("expected a value of {} bytes, not {}", size, width).into(),
+        ));
+    }
+    Ok(match size {
+        4 => <i32 as Decode<Mssql>>::decode(value_ref)? as u32 as i64,
+        8 => <i64 as Decode<Mssql>>::decode(value_ref)?,
+        _ => {
+            return Err(Error::Sqlx(
+                format!("unexpected value width {}", size).into(),
+            ))
+        }
+    })
+}
+
+/// Width of the values of a fixed width type on the wire, or 0 for types
+/// whose values vary in width
+fn fixed_width(type_info: &MssqlTypeInfo) -> Result<u32> {
+    let wire = WireType::of(type_info)?;
+    Ok(match wire.ty.as_str() {
+        "Char" | "VarChar" | "Binary" | "VarBinary" | "BigChar" | "BigVarChar" | "BigBinary"
+        | "BigVarBinary" | "NChar" | "NVarChar" | "Decimal" | "Numeric" | "DecimalN"
+        | "NumericN" => 0,
+        _ => wire.size,
+    })
+}
+
+/// Width on the wire of the time part of `TIME`, `DATETIME2` and
+/// `DATETIMEOFFSET` values
+pub(super) fn time_width(scale: u32) -> u32 {
+    match scale {
+        0..=2 => 3,
+        3..=4 => 4,
+        _ => 5,
+    }
+}
+
+/// Format a `MONEY` or `SMALLMONEY` value, in ten-thousandths of a unit.
+/// `MONEY` is sent as the high 32 bits followed by the low 32 bits.
+pub(super) fn format_money(raw: i64, size: u32) -> String {
+    let value = if size == 8 {
+        (raw as u64).rotate_right(32) as i64
+    } else {
+        raw as u32 as i32 as i64
+    };
+    let sign = if value < 0 { "-" } else { "" };
+    let value = value.unsigned_abs();
+    format!("{}{}.{:04}", sign, value / 10_000, value % 10_000)
+}
+
+/// Decode a `DATETIME` (days and 1/300 second ticks) or `SMALLDATETIME`
+/// (days and minutes) value
+pub(super) fn decode_datetime(raw: i64, size: u32) -> Result<PrimitiveDateTime> {
+    let (days, time) = if size == 8 {
+        let ticks = (raw as u64 >> 32) as i64;
+        (
+            raw as u32 as i32 as i64,
+            Duration::nanoseconds(ticks * 10_000_000 / 3),
+        )
+    } else {
+        let minutes = (raw as u64 >> 16) & 0xffff;
+        (raw & 0xffff, Duration::minutes(minutes as i64))
+    };
+    let date = add_days(DATETIME_EPOCH, days)?;
+    Ok(PrimitiveDateTime::new(date, Time::MIDNIGHT) + time)
+}
+
+/// Decode an 8 byte `DATETIME2` value, of precision 5 to 7: 5 bytes of time
+/// ticks followed by 3 bytes of days
+pub(super) fn decode_datetime2(raw: i64, scale: u32) -> Result<PrimitiveDateTime> {
+    let raw = raw as u64;
+    let time = time_of_day(raw & 0xff_ffff_ffff, scale)?;
+    let date = add_days(DATE_EPOCH, (raw >> 40) as i64)?;
+    Ok(PrimitiveDateTime::new(date, time))
+}
+
+/// Decode the first 8 bytes of a `DATETIMEOFFSET` value: the time ticks and 3
+/// bytes of days, in UTC. The offset that follows them only fits for
+/// precisions 0 to 2, so the value is returned in UTC.
+pub(super) fn decode_datetimeoffset(raw: i64, scale: u32) -> Result<OffsetDateTime> {
+    let raw = raw as u64;
+    let time_bits = time_width(scale) * 8;
+    let time = time_of_day(raw & ((1 << time_bits) - 1), scale)?;
+    let date = add_days(DATE_EPOCH, ((raw >> time_bits) & 0xff_ffff) as i64)?;
+    Ok(PrimitiveDateTime::new(date, time).assume_utc())
+}
+
+/// Time of day from ticks of 10^-scale seconds
+pub(super) fn time_of_day(ticks: u64, scale: u32) -> Result<Time> {
+    let nanos = ticks as i64 * 10_i64.pow(9 - scale.min(9));
+    if nanos >= 86_400_000_000_000 {
+        return Err(Error::Sqlx("time of day out of range".into()));
+    }
+    Ok(Time::MIDNIGHT + Duration::nanoseconds(nanos))
+}
+
+fn add_days(epoch: Date, days: i64) -> Result<Date> {
+    epoch
+        .checked_add(Duration::days(days))
+        .ok_or_else(|| Error::Sqlx("date out of range".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{datetime, time};
+
+    use super::*;
+
+    /// Little-endian integer of the first 8 bytes of a value, as `read_fixed`
+    /// reads them
+    fn raw(bytes: &[u8]) -> i64 {
+        let mut buf = [0; 8];
+        let len = bytes.len().min(8);
+        buf[..len].copy_from_slice(&bytes[..len]);
+        i64::from_le_bytes(buf)
+    }
+
+    fn type_info(json: &str) -> MssqlTypeInfo {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn fixed_widths() {
+        let datetime =
+            type_info(r#"{"ty":"DateTimeN","size":8,"scale":0,"precision":0,"collation":null}"#);
+        assert_eq!(fixed_width(&datetime).unwrap(), 8);
+        let date = type_info(r#"{"ty":"DateN","size":3,"scale":0,"precision":0,"collation":null}"#);
+        assert_eq!(fixed_width(&date).unwrap(), 3);
+        let varbinary = type_info(
+            r#"{"ty":"BigVarBinary","size":16,"scale":0,"precision":0,"collation":null}"#,
+        );
+        assert_eq!(fixed_width(&varbinary).unwrap(), 0);
+    }
+
+    #[test]
+    fn wire_types() {
+        let column_type = |json| ColumnType::of(&type_info(json)).unwrap();
+        let money =
+            column_type(r#"{"ty":"MoneyN","size":4,"scale":0,"precision":0,"collation":null}"#);
+        assert_eq!(money.name(), "SMALLMONEY");
+        assert!(money.is_readable());
+        let time =
+            column_type(r#"{"ty":"TimeN","size":5,"scale":7,"precision":0,"collation":null}"#);
+        assert_eq!((time.name(), time.scale()), ("TIME", 7));
+        assert!(!time.is_readable());
+        let uuid =
+            column_type(r#"{"ty":"Guid","size":16,"scale":0,"precision":0,"collation":null}"#);
+        assert_eq!(uuid.name(), "UNIQUEIDENTIFIER");
+        assert!(!uuid.is_readable());
+        let max = column_type(
+            r#"{"ty":"NVarChar","size":65535,"scale":0,"precision":0,"collation":null}"#,
+        );
+        assert_eq!(max.name(), "NVARCHAR");
+        assert!(!max.is_readable());
+    }
+
+    #[test]
+    fn declared_types() {
+        let time = ColumnType::declared("time(7)");
+        assert_eq!((time.name(), time.scale()), ("TIME", 7));
+        let datetime2 = ColumnType::declared("datetime2(3)");
+        assert_eq!((datetime2.name(), datetime2.scale()), ("DATETIME2", 3));
+        let decimal = ColumnType::declared("decimal(18,2)");
+        assert_eq!((decimal.name(), decimal.scale()), ("DECIMAL", 0));
+        assert_eq!(ColumnType::declared("money").name(), "MONEY");
+        assert_eq!(ColumnType::declared("datetimeoffset").scale(), 7);
+        assert_eq!(ColumnType::declared("bit").name(), "BOOLEAN");
+        assert!(ColumnType::declared("varchar(20)").is_readable());
+        assert!(!ColumnType::declared("varchar(max)").is_readable());
+    }
+
+    #[test]
+    fn money() {
+        // 12.5 and -12.5 as high and low 32 bit halves
+        let value: i64 = 125_000;
+        let wire = [(value >> 32) as u32, value as u32];
+        let bytes = [wire[0].to_le_bytes(), wire[1].to_le_bytes()].concat();
+        assert_eq!(format_money(raw(&bytes), 8), "12.5000");
+        let value: i64 = -125_000;
+        let wire = [(value >> 32) as u32, value as u32];
+        let bytes = [wire[0].to_le_bytes(), wire[1].to_le_bytes()].concat();
+        assert_eq!(format_money(raw(&bytes), 8), "-12.5000");
+        assert_eq!(format_money(raw(&(-1_i32).to_le_bytes()), 4), "-0.0001");
+    }
+
+    #[test]
+    fn datetime() {
+        // 2000-01-01 is 36524 days after 1900-01-01, and 12:00 is 12960000 ticks
+        let bytes = [36524_i32.to_le_bytes(), 12_960_000_i32.to_le_bytes()].concat();
+        assert_eq!(
+            decode_datetime(raw(&bytes), 8).unwrap(),
+            datetime!(2000-01-01 12:00)
+        );
+        let bytes = [36524_u16.to_le_bytes(), 90_u16.to_le_bytes()].concat();
+        assert_eq!(
+            decode_datetime(raw(&bytes), 4).unwrap(),
+            datetime!(2000-01-01 1:30)
+        );
+    }
+
+    #[test]
+    fn datetime2() {
+        // 0.5 seconds in 10^-7 second ticks, then days since 0001-01-01
+        let days = (date!(2020 - 02 - 29) - DATE_EPOCH).whole_days() as u32;
+        let bytes = [&5_000_000_u64.to_le_bytes()[..5], &days.to_le_bytes()[..3]].concat();
+        assert_eq!(
+            decode_datetime2(raw(&bytes), 7).unwrap(),
+            datetime!(2020-02-29 0:00:00.5)
+        );
+    }
+
+    #[test]
+    fn datetimeoffset() {
+        let days = (date!(2020 - 02 - 29) - DATE_EPOCH).whole_days() as u32;
+        // precision 7: 5 bytes of ticks, 3 of days and 2 of offset
+        let bytes = [
+            &36_000_000_000_u64.to_le_bytes()[..5],
+            &days.to_le_bytes()[..3],
+            &120_i16.to_le_bytes()[..],
+        ]
+        .concat();
+        assert_eq!(
+            decode_datetimeoffset(raw(&bytes), 7).unwrap(),
+            datetime!(2020-02-29 1:00 UTC)
+        );
+        // precision 3: 4 bytes of ticks
+        let bytes = [
+            &3_600_500_u32.to_le_bytes()[..],
+            &days.to_le_bytes()[..3],
+            &120_i16.to_le_bytes()[..],
+        ]
+        .concat();
+        assert_eq!(
+            decode_datetimeoffset(raw(&bytes), 3).unwrap(),
+            datetime!(2020-02-29 1:00:00.5 UTC)
+        );
+        // precision 0: 3 bytes of ticks
+        let bytes = [
+            &3_600_u32.to_le_bytes()[..3],
+            &days.to_le_bytes()[..3],
+            &(-60_i16).to_le_bytes()[..],
+        ]
+        .concat();
+        assert_eq!(
+            decode_datetimeoffset(raw(&bytes), 0).unwrap(),
+            datetime!(2020-02-29 1:00 UTC)
+        );
+    }
+
+    #[test]
+    fn time_of_day_scales() {
+        assert_eq!(time_of_day(12_345, 4).unwrap(), time!(0:00:01.2345));
+        assert_eq!(time_of_day(86_399_999, 3).unwrap(), time!(23:59:59.999));
+        assert!(time_of_day(86_400_000, 3).is_err());
+        assert_eq!(time_width(7), 5);
+        assert_eq!(time_width(4), 4);
+        assert_eq!(time_width(0), 3);
+    }
+}
@@ -1,18 +1,41 @@
+mod catalog;
+mod geometry;
+mod metadata;
+
 use async_trait::async_trait;
 use sqlx::{
-    database::HasArguments, mysql::MySqlRow, query::Query, Column, Decode, MySql, MySqlConnection,
-    Row, TypeInfo, ValueRef,
+    database::HasArguments, mysql::MySqlRow, query::Query, types::BigDecimal, Column, Decode,
+    Encode, MySql, MySqlConnection, Row, Type, ValueRef,
 };
 use time::{
-    format_description::well_known::Rfc3339, macros::format_description, Date, OffsetDateTime,
-    PrimitiveDateTime, Time,
+    format_description::well_known::Rfc3339, Date, OffsetDateTime, PrimitiveDateTime, Time,
 };
+use tokio::sync::mpsc;
 use uuid::Uuid;
-use wasmcloud_interface_sqldb::{ExecuteResult, QueryResult, Statement};
+use wasmcloud_interface_sqldb::{Column as ColumnInfo, ExecuteResult, QueryResult, Statement};
 
-use crate::result::{Error, Result};
+use crate::{
+    config::EncodingOptions,
+    ext::{
+        BulkInsert, CallResult, ColumnMetadata, Export, MultiQueryResult, ParamMode, ProcedureCall,
+        ResultSet, ReturningResult, TableDescription, TableInfo,
+    },
+    result::{Error, Result},
+};
 
-use super::{bind_query, to_columns, BindCbor, SqlDbExecutor};
+use super::{
+    bind_query, bulk, call,
+    datetime::{DATE_FORMAT, TIMESTAMP_FORMAT, TIME_FORMAT},
+    described,
+    hint::{BindTyped, TypeHint},
+    ident, json,
+    kind::ValueKind,
+    param::Param,
+    placeholder::{find_keyword, split_statements, Dialect},
+    split_results,
+    tag::{self, TaggedValue},
+    BindCbor, SqlDbExecutor,
+};
 
 #[async_trait]
 impl SqlDbExecutor for MySqlConnection {
@@ -25,42 +48,306 @@ impl SqlDbExecutor for MySqlConnection {
         })
     }
 
-    async fn fetch_all(&mut self, stmt: &Statement) -> Result<QueryResult> {
+    async fn fetch_all(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<QueryResult> {
         let query = bind_query(stmt)?;
-        let rows = sqlx::Executor::fetch_all(self, query).await?;
+        let rows = sqlx::Executor::fetch_all(&mut *self, query).await?;
         if rows.is_empty() {
-            Ok(QueryResult::default())
+            // describe the statement so the columns are known without rows
+            let columns = described(sqlx::Executor::describe(self, stmt.sql.as_str()).await)?
+                .map(|describe| metadata::to_columns(describe.columns()))
+                .unwrap_or_default();
+            Ok(QueryResult {
+                columns,
+                ..Default::default()
+            })
         } else {
             Ok(QueryResult {
                 num_rows: rows.len() as u64,
-                columns: to_columns(&rows),
-                rows: mysql_to_cbor(&rows)?,
+                columns: metadata::to_columns(rows[0].columns()),
+                rows: mysql_to_cbor(&rows, encoding)?,
                 error: None,
             })
         }
     }
+
+    async fn describe(&mut self, stmt: &Statement) -> Result<Vec<ColumnMetadata>> {
+        metadata::describe_columns(self, &stmt.sql).await
+    }
+
+    async fn execute_returning(
+        &mut self,
+        stmt: &Statement,
+        key_columns: &[String],
+        _encoding: &EncodingOptions,
+    ) -> Result<ReturningResult> {
+        let query = bind_query(stmt)?;
+        let mut tx = sqlx::Connection::begin(self).await?;
+        let result = sqlx::Executor::execute(&mut *tx, query).await?;
+
+        // MySQL only reports the id generated for the first inserted row, so
+        // a multi-row insert is rolled back rather than returning one key. An
+        // upsert counts an updated row twice.
+        let id = result.last_insert_id();
+        let upsert = find_keyword(&stmt.sql, Dialect::MySql, &["duplicate"]).is_some();
+        if id > 0 && result.rows_affected() > 1 && !upsert {
+            return Err(Error::InvalidRequest(
+                "MySQL only returns the key of the first row of a multi-row insert".into(),
+            ));
+        }
+        tx.commit().await?;
+        let num_rows = u64::from(id > 0);
+        let mut rows = Vec::new();
+        let mut out = minicbor::Encoder::new(&mut rows);
+        out.array(num_rows)?;
+        if id > 0 {
+            out.array(1)?.u64(id)?;
+        }
+        Ok(ReturningResult {
+            rows_affected: result.rows_affected(),
+            num_rows,
+            columns: vec![ColumnInfo {
+                ordinal: 0,
+                name: key_columns
+                    .first()
+                    .map_or("last_insert_id", String::as_str)
+                    .into(),
+                db_type: "BIGINT UNSIGNED".into(),
+            }],
+            rows,
+            error: None,
+        })
+    }
+
+    async fn fetch_multi(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<MultiQueryResult> {
+        // a prepared statement is a single statement, so a string of
+        // statements without parameters is run statement by statement
+        let statements = if stmt.parameters.as_deref().unwrap_or_default().is_empty() {
+            split_statements(&stmt.sql, Dialect::MySql)
+        } else {
+            vec![stmt.sql.as_str()]
+        };
+        let mut result = MultiQueryResult::default();
+        for sql in statements {
+            let stmt = Statement {
+                sql: sql.to_string(),
+                ..stmt.clone()
+            };
+            let results = sqlx::Executor::fetch_many(&mut *self, bind_query(&stmt)?);
+            let (result_sets, rows_affected) =
+                split_results(results, |result| result.rows_affected()).await?;
+            if result_sets.is_empty() && rows_affected == [0] {
+                // a query that returns no rows still has its columns
+                let describe = described(sqlx::Executor::describe(&mut *self, sql).await)?;
+                if let Some(describe) = describe.filter(|d| !d.columns().is_empty()) {
+                    result.result_sets.push(ResultSet {
+                        columns: metadata::to_columns(describe.columns()),
+                        rows: mysql_to_cbor(&[], encoding)?,
+                        ..Default::default()
+                    });
+                    continue;
+                }
+            }
+            for rows in &result_sets {
+                result.result_sets.push(ResultSet {
+                    num_rows: rows.len() as u64,
+                    columns: metadata::to_columns(rows[0].columns()),
+                    rows: mysql_to_cbor(rows, encoding)?,
+                });
+            }
+            result.rows_affected.extend(rows_affected);
+        }
+        Ok(result)
+    }
+
+    async fn call(
+        &mut self,
+        call: &ProcedureCall,
+        encoding: &EncodingOptions,
+    ) -> Result<CallResult> {
+        let mut args = Vec::new();
+        let mut parameters = Vec::new();
+        let mut variables = Vec::new();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for (i, param) in call.parameters.iter().enumerate() {
+            match param.mode {
+                ParamMode::In => {
+                    parameters.push(call::value(param));
+                    args.push("?".to_string());
+                }
+                _ if call.function => {
+                    return Err(Error::InvalidCall(
+                        "MySQL functions only take in parameters".into(),
+                    ))
+                }
+                mode => {
+                    // out and inout parameters are passed in session variables
+                    let variable = format!("@_call_{}", i + 1);
+                    if mode == ParamMode::InOut {
+                        inputs.push(Statement {
+                            sql: format!("set {} = ?", variable),
+                            parameters: Some(vec![call::value(param)]),
+                            ..Default::default()
+                        });
+                    }
+                    outputs.push(format!(
+                        "{} as `{}`",
+                        variable,
+                        call::output_name(i, param)?
+                    ));
+                    args.push(variable.clone());
+                    variables.push(variable);
+                }
+            }
+        }
+        let procedure = ident::identifier(&call.procedure)?;
+        let sql = if call.function {
+            format!("select {}({}) as return_value", procedure, args.join(", "))
+        } else {
+            format!("call {}({})", procedure, args.join(", "))
+        };
+        let stmt = Statement {
+            sql,
+            parameters: (!parameters.is_empty()).then_some(parameters),
+            ..Default::default()
+        };
+
+        let result = async {
+            for input in &inputs {
+                self.execute(input).await?;
+            }
+            let result = self.fetch_multi(&stmt, encoding).await?;
+            if call.function {
+                return call::function_result(result);
+            }
+
+            let outputs = if outputs.is_empty() {
+                ResultSet::default()
+            } else {
+                let select = Statement {
+                    sql: format!("select {}", outputs.join(", ")),
+                    ..Default::default()
+                };
+                let mut outputs = self.fetch_multi(&select, encoding).await?;
+                outputs.result_sets.pop().unwrap_or_default()
+            };
+            Ok(CallResult {
+                outputs,
+                result_sets: result.result_sets,
+                rows_affected: result.rows_affected,
+                ..Default::default()
+            })
+        }
+        .await;
+
+        // session variables outlive the call, so they are cleared before the
+        // connection goes back to the pool
+        if !variables.is_empty() {
+            let reset = Statement {
+                sql: format!("set {} = null", variables.join(" = null, ")),
+                ..Default::default()
+            };
+            let reset = self.execute(&reset).await;
+            let result = result?;
+            reset?;
+            return Ok(result);
+        }
+        result
+    }
+
+    async fn bulk_insert(&mut self, insert: &BulkInsert) -> Result<u64> {
+        let statements = bulk::insert_statements(insert, Dialect::MySql)?;
+        let mut tx = sqlx::Connection::begin(self).await?;
+        let mut rows_affected = 0;
+        for stmt in &statements {
+            rows_affected += tx.execute(stmt).await?.rows_affected;
+        }
+        tx.commit().await?;
+        Ok(rows_affected)
+    }
+
+    async fn export(
+        &mut self,
+        _export: &Export,
+        _chunk_size: usize,
+        _chunks: &mpsc::Sender<Result<Vec<u8>>>,
+    ) -> Result<()> {
+        Err(Error::Unsupported("exporting from MySQL"))
+    }
+
+    async fn list_schemas(&mut self) -> Result<Vec<String>> {
+        catalog::list_schemas(self).await
+    }
+
+    async fn list_tables(&mut self, schema: Option<&str>) -> Result<Vec<TableInfo>> {
+        catalog::list_tables(self, schema).await
+    }
+
+    async fn describe_table(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+    ) -> Result<Option<TableDescription>> {
+        catalog::describe_table(self, schema, table).await
+    }
+}
+
+/// MySQL binds the hinted types natively, except UUIDs, which bind as strings
+/// like tagged UUIDs
+impl<'q> BindTyped<'q> for Query<'q, MySql, <MySql as HasArguments<'q>>::Arguments> {
+    type Database = MySql;
+
+    fn bind_value<T>(self, value: T) -> Self
+    where
+        T: 'q + Send + Encode<'q, MySql> + Type<MySql>,
+    {
+        self.bind(value)
+    }
+
+    fn bind_bytes(self, value: Vec<u8>) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_numeric(self, value: BigDecimal) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_date(self, value: Date) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_time(self, value: Time) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_timestamp(self, value: PrimitiveDateTime) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_timestamptz(self, value: OffsetDateTime) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_json(self, value: serde_json::Value) -> Result<Self> {
+        Ok(self.bind(value))
+    }
 }
 
 impl<'q> BindCbor for Query<'q, MySql, <MySql as HasArguments<'q>>::Arguments> {
     fn bind_cbor(self, value: &[u8]) -> Result<Self> {
-        use minicbor::data::Type;
-
-        let mut decoder = minicbor::Decoder::new(value);
-        let datatype = decoder.datatype()?;
-        let query = match datatype {
-            Type::Bool => self.bind(decoder.bool()?),
-            Type::Null | Type::Undefined => self.bind(None::<bool>),
-            Type::U8 => self.bind(decoder.u8()?),
-            Type::U16 => self.bind(decoder.u16()?),
-            Type::U32 => self.bind(decoder.u32()?),
-            Type::U64 => self.bind(decoder.u64()?),
-            Type::I8 => self.bind(decoder.i8()?),
-            Type::I16 => self.bind(decoder.i16()?),
-            Type::I32 => self.bind(decoder.i32()?),
-            Type::I64 => self.bind(decoder.i64()?),
-            Type::Int => {
-                let int = decoder.int()?;
-                if int < 0.into() {
+        let query = match Param::decode(value)? {
+            Param::Null => self.bind(None::<bool>),
+            Param::Bool(value) => self.bind(value),
+            Param::Int(int) => {
+                if int < 0 {
                     if let Ok(value) = i8::try_from(int) {
                         self.bind(value)
                     } else if let Ok(value) = i16::try_from(int) {
@@ -72,154 +359,237 @@ impl<'q> BindCbor for Query<'q, MySql, <MySql as HasArguments<'q>>::Arguments> {
                     } else {
                         return Err(Error::CborDeIntOutOfRange(int));
                     }
+                } else if let Ok(value) = u8::try_from(int) {
+                    self.bind(value)
+                } else if let Ok(value) = u16::try_from(int) {
+                    self.bind(value)
+                } else if let Ok(value) = u32::try_from(int) {
+                    self.bind(value)
+                } else if let Ok(value) = u64::try_from(int) {
+                    self.bind(value)
                 } else {
-                    if let Ok(value) = u8::try_from(int) {
-                        self.bind(value)
-                    } else if let Ok(value) = u16::try_from(int) {
-                        self.bind(value)
-                    } else if let Ok(value) = u32::try_from(int) {
-                        self.bind(value)
-                    } else if let Ok(value) = u64::try_from(int) {
-                        self.bind(value)
-                    } else {
-                        return Err(Error::CborDeIntOutOfRange(int));
+                    return Err(Error::CborDeIntOutOfRange(int));
+                }
+            }
+            Param::F32(value) => self.bind(value),
+            Param::F64(value) => self.bind(value),
+            Param::Bytes(value) => self.bind(value),
+            Param::Text(value) => self.bind(value),
+            Param::Array(value) | Param::Map(value) => {
+                self.bind(json::decode_json(&mut minicbor::Decoder::new(value))?)
+            }
+            // a finite set of strings binds as a SET value
+            Param::Set(members) => {
+                if let Some(member) = members.iter().find(|member| member.contains(',')) {
+                    return Err(Error::CborDeValue(format!(
+                        "SET member `{}` contains a comma",
+                        member
+                    )));
+                }
+                self.bind(members.join(","))
+            }
+            Param::Tagged(tagged) => match tagged {
+                TaggedValue::DateTime(value) => self.bind(value),
+                // MySQL has no UUID type; use UUID_TO_BIN(?) for BINARY(16) columns
+                TaggedValue::Uuid(value) => self.bind(value.as_hyphenated().to_string()),
+                TaggedValue::Number(value) => match tag::as_i64(&value) {
+                    Some(value) => self.bind(value),
+                    None => self.bind(value),
+                },
+            },
+            // a GeoJSON geometry object binds as a geometry when it's hinted
+            // as one, so other maps still bind as JSON
+            Param::UserTyped(name, param) if name.eq_ignore_ascii_case("geometry") => {
+                match *param {
+                    Param::Null => self.bind(None::<Vec<u8>>),
+                    Param::Map(value) => self.bind(geometry::decode_geojson(
+                        &mut minicbor::Decoder::new(value),
+                    )?),
+                    param => {
+                        return Err(Error::CborDeValue(format!(
+                            "expected a GeoJSON geometry object, not a {}",
+                            param.kind()
+                        )))
                     }
                 }
             }
-            Type::F16 => self.bind(decoder.f16()?),
-            Type::F32 => self.bind(decoder.f32()?),
-            Type::F64 => self.bind(decoder.f64()?),
-            // Type::Simple => todo!(),
-            Type::Bytes => self.bind(decoder.bytes()?.to_vec()),
-            // Type::BytesIndef => todo!(),
-            Type::String => self.bind(decoder.str()?.to_string()),
-            // Type::StringIndef => todo!(),
-            // Type::Array => todo!(),
-            // Type::ArrayIndef => todo!(),
-            // Type::Map => todo!(),
-            // Type::MapIndef => todo!(),
-            // Type::Tag => todo!(),
-            // Type::Break => todo!(),
-            // Type::Unknown(_) => todo!(),
-            _ => return Err(Error::CborDeType(datatype)),
+            Param::UserTyped(name, _) => return Err(Error::TypeHint(name)),
+            Param::Hinted(TypeHint::Jsonb, _) => {
+                return Err(Error::TypeHintNotSupported("jsonb", "MySQL"))
+            }
+            Param::Hinted(hint, param) => self.bind_typed_value(hint.convert(*param)?)?,
         };
 
         Ok(query)
     }
 }
 
-fn mysql_to_cbor(rows: &[MySqlRow]) -> Result<Vec<u8>> {
+fn mysql_to_cbor(rows: &[MySqlRow], encoding: &EncodingOptions) -> Result<Vec<u8>> {
     let mut buf = Vec::with_capacity(rows.len() * 2);
     let mut out = minicbor::Encoder::new(&mut buf);
 
+    // the type names of the first row's columns, which the other rows share
+    let type_names: Vec<&str> = match rows.first() {
+        Some(row) => row
+            .columns()
+            .iter()
+            .map(|column| metadata::type_name(column.type_info()))
+            .collect(),
+        None => Vec::new(),
+    };
+
     out.array(rows.len() as u64)?;
     for row in rows {
         out.array(row.len() as u64)?;
 
-        for column in row.columns() {
+        for (column, &type_name) in row.columns().iter().zip(&type_names) {
             let value_ref = row.try_get_raw(column.ordinal())?;
             if value_ref.is_null() {
                 out.null()?;
                 continue;
             }
 
-            let type_name = column.type_info().name();
-            match type_name {
-                "BOOLEAN" => {
+            match value_kind(type_name) {
+                ValueKind::Null => {
+                    out.null()?;
+                }
+
+                ValueKind::Bool => {
                     out.encode(<bool as Decode<MySql>>::decode(value_ref)?)?;
                 }
 
-                "TINYINT" => {
+                ValueKind::Int8 => {
                     out.encode(<i8 as Decode<MySql>>::decode(value_ref)?)?;
                 }
-                "SMALLINT" => {
+                ValueKind::Int16 => {
                     out.encode(<i16 as Decode<MySql>>::decode(value_ref)?)?;
                 }
-                "INT" => {
+                ValueKind::Int32 => {
                     out.encode(<i32 as Decode<MySql>>::decode(value_ref)?)?;
                 }
-                "BIGINT" => {
+                ValueKind::Int64 => {
                     out.encode(<i64 as Decode<MySql>>::decode(value_ref)?)?;
                 }
 
-                "TINYINT UNSIGNED" => {
+                ValueKind::UInt8 => {
                     out.encode(<u8 as Decode<MySql>>::decode(value_ref)?)?;
                 }
-                "SMALLINT UNSIGNED" => {
+                ValueKind::UInt16 => {
                     out.encode(<u16 as Decode<MySql>>::decode(value_ref)?)?;
                 }
-                "INT UNSIGNED" => {
+                ValueKind::UInt32 => {
                     out.encode(<u32 as Decode<MySql>>::decode(value_ref)?)?;
                 }
-                "BIGINT UNSIGNED" => {
+                ValueKind::UInt64 => {
                     out.encode(<u64 as Decode<MySql>>::decode(value_ref)?)?;
                 }
 
-                "FLOAT" => {
+                ValueKind::Float32 => {
                     out.encode(<f32 as Decode<MySql>>::decode(value_ref)?)?;
                 }
-                "DOUBLE" => {
+                ValueKind::Float64 => {
                     out.encode(<f64 as Decode<MySql>>::decode(value_ref)?)?;
                 }
 
-                "CHAR" | "VARCHAR" | "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT" => {
+                ValueKind::Text => {
                     out.encode(<&str as Decode<MySql>>::decode(value_ref)?)?;
                 }
 
-                "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
-                    out.encode(<&[u8] as Decode<MySql>>::decode(value_ref)?)?;
+                ValueKind::Bytes => {
+                    out.bytes(<&[u8] as Decode<MySql>>::decode(value_ref)?)?;
                 }
 
-                "DATETIME" => {
+                ValueKind::Timestamp => {
                     let timestamp = <PrimitiveDateTime as Decode<MySql>>::decode(value_ref)?;
-                    let format =
-                        format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
-                    let rfc3339 = timestamp.format(format)?;
+                    let rfc3339 = timestamp.format(TIMESTAMP_FORMAT)?;
                     out.encode(rfc3339)?;
                 }
 
-                "TIMESTAMP" => {
+                ValueKind::TimestampTz => {
                     let timestamp = <OffsetDateTime as Decode<MySql>>::decode(value_ref)?;
                     let rfc3339 = timestamp.format(&Rfc3339)?;
                     out.encode(rfc3339)?;
                 }
 
-                "DATE" => {
+                ValueKind::Date => {
                     let date = <Date as Decode<MySql>>::decode(value_ref)?;
-                    let format = format_description!("[year]-[month]-[day]");
-                    let value = date.format(format)?;
+                    let value = date.format(DATE_FORMAT)?;
                     out.encode(value)?;
                 }
 
-                "TIME" => {
+                ValueKind::Time => {
                     let date = <Time as Decode<MySql>>::decode(value_ref)?;
-                    let format = format_description!("[hour]:[minute]:[second]");
-                    let value = date.format(format)?;
+                    let value = date.format(TIME_FORMAT)?;
                     out.encode(value)?;
                 }
 
-                "UUID" => {
-                    let id = <Uuid as Decode<MySql>>::decode(value_ref)?;
-                    let value = id.as_hyphenated().to_string();
-                    out.encode(value)?;
-                }
+                ValueKind::Other => match type_name {
+                    "GEOMETRY" => {
+                        let value = <&[u8] as Decode<MySql>>::decode(value_ref)?;
+                        geometry::encode_geometry(&mut out, value, encoding.geometry)?;
+                    }
 
-                "JSON" => {
-                    let json = <serde_json::Value as Decode<MySql>>::decode(value_ref)?;
-                    let value = serde_json::to_string(&json)?;
-                    out.encode(value)?;
-                }
+                    "SET" => {
+                        let members = <&str as Decode<MySql>>::decode(value_ref)?;
+                        if members.is_empty() {
+                            out.array(0)?;
+                        } else {
+                            out.array(members.split(',').count() as u64)?;
+                            for member in members.split(',') {
+                                out.str(member)?;
+                            }
+                        }
+                    }
 
-                "NULL" | "VOID" => {
-                    out.null()?;
-                }
+                    "UUID" => {
+                        let id = <Uuid as Decode<MySql>>::decode(value_ref)?;
+                        let value = id.as_hyphenated().to_string();
+                        out.encode(value)?;
+                    }
 
-                _ => {
-                    return Err(Error::DbType(type_name.into()));
-                }
+                    "JSON" => {
+                        let json = <serde_json::Value as Decode<MySql>>::decode(value_ref)?;
+                        json::encode_json(&mut out, &json, encoding.json)?;
+                    }
+
+                    _ => {
+                        return Err(Error::DbType(type_name.into()));
+                    }
+                },
             }
         }
     }
 
     Ok(buf)
 }
+
+/// Kind of the value `mysql_to_cbor` writes for a type, by the name
+/// `metadata::type_name` gives it. Sets are arrays of their members.
+/// `DECIMAL` is sent as text in both protocols, and passed through to keep its
+/// scale.
+pub(super) fn value_kind(type_name: &str) -> ValueKind {
+    match type_name {
+        "NULL" | "VOID" => ValueKind::Null,
+        "BOOLEAN" => ValueKind::Bool,
+        "TINYINT" => ValueKind::Int8,
+        "SMALLINT" => ValueKind::Int16,
+        "INT" | "MEDIUMINT" => ValueKind::Int32,
+        "BIGINT" => ValueKind::Int64,
+        "TINYINT UNSIGNED" => ValueKind::UInt8,
+        "SMALLINT UNSIGNED" | "YEAR" => ValueKind::UInt16,
+        "INT UNSIGNED" | "MEDIUMINT UNSIGNED" => ValueKind::UInt32,
+        "BIGINT UNSIGNED" | "BIT" => ValueKind::UInt64,
+        "FLOAT" => ValueKind::Float32,
+        "DOUBLE" => ValueKind::Float64,
+        "CHAR" | "VARCHAR" | "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT" | "ENUM"
+        | "DECIMAL" => ValueKind::Text,
+        "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
+            ValueKind::Bytes
+        }
+        "DATE" => ValueKind::Date,
+        "TIME" => ValueKind::Time,
+        "DATETIME" => ValueKind::Timestamp,
+        "TIMESTAMP" => ValueKind::TimestampTz,
+        _ => ValueKind::Other,
+    }
+}
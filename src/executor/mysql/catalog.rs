@@ -0,0 +1,122 @@
+//! MySQL schema introspection
+//!
+//! Schemas are databases, and everything is read from `information_schema`.
+//! Its columns are cast to plain types, since the server reports some of them
+//! as unsigned or as binary strings depending on the version.
+
+use sqlx::MySqlConnection;
+
+use crate::{
+    ext::{TableDescription, TableInfo},
+    result::Result,
+};
+
+use super::super::catalog::{self, ColumnRow, ForeignKeyRow, IndexRow};
+
+const SYSTEM_SCHEMAS: &str = "('information_schema', 'mysql', 'performance_schema', 'sys')";
+
+const TABLE_SQL: &str = r#"
+select cast(table_schema as char), cast(table_name as char), cast(table_type as char)
+from information_schema.tables
+where table_schema = coalesce(?, database()) and table_name = ?
+"#;
+
+const COLUMNS_SQL: &str = r#"
+select cast(column_name as char), cast(ordinal_position as signed), cast(column_type as char),
+    is_nullable = 'YES', cast(column_default as char),
+    cast(character_maximum_length as signed),
+    cast(coalesce(numeric_precision, datetime_precision) as signed),
+    cast(numeric_scale as signed)
+from information_schema.columns
+where table_schema = ? and table_name = ?
+order by ordinal_position
+"#;
+
+const INDEXES_SQL: &str = r#"
+select cast(index_name as char), non_unique = 0, index_name = 'PRIMARY',
+    cast(column_name as char)
+from information_schema.statistics
+where table_schema = ? and table_name = ? and column_name is not null
+order by index_name = 'PRIMARY' desc, index_name, seq_in_index
+"#;
+
+const FOREIGN_KEYS_SQL: &str = r#"
+select cast(constraint_name as char), cast(column_name as char),
+    cast(referenced_table_schema as char), cast(referenced_table_name as char),
+    cast(referenced_column_name as char)
+from information_schema.key_column_usage
+where table_schema = ? and table_name = ? and referenced_table_name is not null
+order by constraint_name, ordinal_position
+"#;
+
+pub(super) async fn list_schemas(conn: &mut MySqlConnection) -> Result<Vec<String>> {
+    let sql = format!(
+        "select cast(schema_name as char) from information_schema.schemata \
+        where schema_name not in {} order by 1",
+        SYSTEM_SCHEMAS
+    );
+    Ok(sqlx::query_scalar(&sql).fetch_all(conn).await?)
+}
+
+pub(super) async fn list_tables(
+    conn: &mut MySqlConnection,
+    schema: Option<&str>,
+) -> Result<Vec<TableInfo>> {
+    let sql = format!(
+        "select cast(table_schema as char), cast(table_name as char), cast(table_type as char) \
+        from information_schema.tables \
+        where (? is null and table_schema not in {}) or table_schema = ? \
+        order by 1, 2",
+        SYSTEM_SCHEMAS
+    );
+    let rows: Vec<(String, String, String)> = sqlx::query_as(&sql)
+        .bind(schema)
+        .bind(schema)
+        .fetch_all(conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(schema, name, table_type)| TableInfo {
+            schema,
+            name,
+            kind: catalog::table_kind(&table_type),
+        })
+        .collect())
+}
+
+pub(super) async fn describe_table(
+    conn: &mut MySqlConnection,
+    schema: Option<&str>,
+    table: &str,
+) -> Result<Option<TableDescription>> {
+    let found: Option<(String, String, String)> = sqlx::query_as(TABLE_SQL)
+        .bind(schema)
+        .bind(table)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let found = match found {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let columns: Vec<ColumnRow> = sqlx::query_as(COLUMNS_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    let indexes: Vec<IndexRow> = sqlx::query_as(INDEXES_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    let foreign_keys: Vec<ForeignKeyRow> = sqlx::query_as(FOREIGN_KEYS_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(Some(catalog::describe_table(
+        found,
+        columns,
+        indexes,
+        foreign_keys,
+    )))
+}
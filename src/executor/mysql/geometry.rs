@@ -0,0 +1,486 @@
+//! MySQL spatial types
+//!
+//! MySQL stores geometry values as a 4-byte little-endian SRID followed by the
+//! WKB (well-known binary) representation. Values are encoded either as the
+//! WKB bytes or as a GeoJSON geometry object, and GeoJSON geometry objects
+//! hinted as `geometry` are accepted as parameters.
+
+use minicbor::{Decoder, Encoder};
+use serde_json::Value;
+
+use crate::config::GeometryEncoding;
+use crate::result::{Error, Result};
+
+use super::super::json;
+
+const WKB_POINT: u32 = 1;
+const WKB_LINESTRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTIPOINT: u32 = 4;
+const WKB_MULTILINESTRING: u32 = 5;
+const WKB_MULTIPOLYGON: u32 = 6;
+const WKB_GEOMETRYCOLLECTION: u32 = 7;
+
+/// Encode a geometry value from the MySQL internal format
+pub(super) fn encode_geometry(
+    out: &mut Encoder<&mut Vec<u8>>,
+    buf: &[u8],
+    encoding: GeometryEncoding,
+) -> Result<()> {
+    if buf.len() < 4 {
+        return Err(invalid_wkb());
+    }
+    let wkb = &buf[4..];
+    match encoding {
+        GeometryEncoding::Wkb => {
+            out.bytes(wkb)?;
+        }
+        GeometryEncoding::GeoJson => {
+            let mut wkb = wkb;
+            encode_geojson(out, &mut wkb)?;
+        }
+    }
+    Ok(())
+}
+
+/// Decode a GeoJSON geometry object to the MySQL internal format, with SRID 0
+pub(super) fn decode_geojson(decoder: &mut Decoder) -> Result<Vec<u8>> {
+    let geometry = json::decode_json(decoder)?;
+    let mut buf = vec![0; 4];
+    write_wkb(&mut buf, &geometry)?;
+    Ok(buf)
+}
+
+fn encode_geojson(out: &mut Encoder<&mut Vec<u8>>, buf: &mut &[u8]) -> Result<()> {
+    let little_endian = match read_u8(buf)? {
+        0 => false,
+        1 => true,
+        _ => return Err(invalid_wkb()),
+    };
+    let geometry_type = read_u32(buf, little_endian)?;
+    if geometry_type == WKB_GEOMETRYCOLLECTION {
+        let count = read_u32(buf, little_endian)?;
+        out.map(2)?;
+        out.str("type")?.str("GeometryCollection")?;
+        out.str("geometries")?.array(count as u64)?;
+        for _ in 0..count {
+            encode_geojson(out, buf)?;
+        }
+        return Ok(());
+    }
+
+    let name = match geometry_type {
+        WKB_POINT => "Point",
+        WKB_LINESTRING => "LineString",
+        WKB_POLYGON => "Polygon",
+        WKB_MULTIPOINT => "MultiPoint",
+        WKB_MULTILINESTRING => "MultiLineString",
+        WKB_MULTIPOLYGON => "MultiPolygon",
+        _ => return Err(invalid_wkb()),
+    };
+    out.map(2)?;
+    out.str("type")?.str(name)?;
+    out.str("coordinates")?;
+    match geometry_type {
+        WKB_POINT => encode_point(out, buf, little_endian)?,
+        WKB_LINESTRING => encode_points(out, buf, little_endian)?,
+        WKB_POLYGON => encode_rings(out, buf, little_endian)?,
+        _ => {
+            // each member of a multi-geometry carries its own WKB header
+            let count = read_u32(buf, little_endian)?;
+            out.array(count as u64)?;
+            for _ in 0..count {
+                let little_endian = match read_u8(buf)? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(invalid_wkb()),
+                };
+                match (geometry_type, read_u32(buf, little_endian)?) {
+                    (WKB_MULTIPOINT, WKB_POINT) => encode_point(out, buf, little_endian)?,
+                    (WKB_MULTILINESTRING, WKB_LINESTRING) => {
+                        encode_points(out, buf, little_endian)?
+                    }
+                    (WKB_MULTIPOLYGON, WKB_POLYGON) => encode_rings(out, buf, little_endian)?,
+                    _ => return Err(invalid_wkb()),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn encode_point(
+    out: &mut Encoder<&mut Vec<u8>>,
+    buf: &mut &[u8],
+    little_endian: bool,
+) -> Result<()> {
+    let x = read_f64(buf, little_endian)?;
+    let y = read_f64(buf, little_endian)?;
+    // an empty point has NaN coordinates
+    if x.is_nan() && y.is_nan() {
+        out.array(0)?;
+    } else {
+        out.array(2)?.f64(x)?.f64(y)?;
+    }
+    Ok(())
+}
+
+fn encode_points(
+    out: &mut Encoder<&mut Vec<u8>>,
+    buf: &mut &[u8],
+    little_endian: bool,
+) -> Result<()> {
+    let count = read_u32(buf, little_endian)?;
+    out.array(count as u64)?;
+    for _ in 0..count {
+        encode_point(out, buf, little_endian)?;
+    }
+    Ok(())
+}
+
+fn encode_rings(
+    out: &mut Encoder<&mut Vec<u8>>,
+    buf: &mut &[u8],
+    little_endian: bool,
+) -> Result<()> {
+    let count = read_u32(buf, little_endian)?;
+    out.array(count as u64)?;
+    for _ in 0..count {
+        encode_points(out, buf, little_endian)?;
+    }
+    Ok(())
+}
+
+fn write_wkb(buf: &mut Vec<u8>, geometry: &Value) -> Result<()> {
+    let name = geometry
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid_geojson("missing geometry type"))?;
+    if name == "GeometryCollection" {
+        let geometries = geometry
+            .get("geometries")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid_geojson("missing geometries"))?;
+        write_header(buf, WKB_GEOMETRYCOLLECTION);
+        buf.extend_from_slice(&(geometries.len() as u32).to_le_bytes());
+        for geometry in geometries {
+            write_wkb(buf, geometry)?;
+        }
+        return Ok(());
+    }
+
+    let coordinates = geometry
+        .get("coordinates")
+        .ok_or_else(|| invalid_geojson("missing coordinates"))?;
+    match name {
+        "Point" => {
+            write_header(buf, WKB_POINT);
+            write_point(buf, coordinates)?;
+        }
+        "LineString" => {
+            write_header(buf, WKB_LINESTRING);
+            write_points(buf, coordinates)?;
+        }
+        "Polygon" => {
+            write_header(buf, WKB_POLYGON);
+            write_rings(buf, coordinates)?;
+        }
+        "MultiPoint" | "MultiLineString" | "MultiPolygon" => {
+            let (multi_type, member_type) = match name {
+                "MultiPoint" => (WKB_MULTIPOINT, WKB_POINT),
+                "MultiLineString" => (WKB_MULTILINESTRING, WKB_LINESTRING),
+                _ => (WKB_MULTIPOLYGON, WKB_POLYGON),
+            };
+            let members = as_array(coordinates)?;
+            write_header(buf, multi_type);
+            buf.extend_from_slice(&(members.len() as u32).to_le_bytes());
+            for member in members {
+                write_header(buf, member_type);
+                match member_type {
+                    WKB_POINT => write_point(buf, member)?,
+                    WKB_LINESTRING => write_points(buf, member)?,
+                    _ => write_rings(buf, member)?,
+                }
+            }
+        }
+        _ => {
+            return Err(invalid_geojson(&format!(
+                "unsupported geometry type `{}`",
+                name
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn write_header(buf: &mut Vec<u8>, geometry_type: u32) {
+    buf.push(1);
+    buf.extend_from_slice(&geometry_type.to_le_bytes());
+}
+
+fn write_point(buf: &mut Vec<u8>, point: &Value) -> Result<()> {
+    let point = as_array(point)?;
+    let (x, y) = match point.as_slice() {
+        [] => (f64::NAN, f64::NAN),
+        [x, y, ..] => (as_f64(x)?, as_f64(y)?),
+        _ => return Err(invalid_geojson("a position needs two coordinates")),
+    };
+    buf.extend_from_slice(&x.to_le_bytes());
+    buf.extend_from_slice(&y.to_le_bytes());
+    Ok(())
+}
+
+fn write_points(buf: &mut Vec<u8>, points: &Value) -> Result<()> {
+    let points = as_array(points)?;
+    buf.extend_from_slice(&(points.len() as u32).to_le_bytes());
+    for point in points {
+        write_point(buf, point)?;
+    }
+    Ok(())
+}
+
+fn write_rings(buf: &mut Vec<u8>, rings: &Value) -> Result<()> {
+    let rings = as_array(rings)?;
+    buf.extend_from_slice(&(rings.len() as u32).to_le_bytes());
+    for ring in rings {
+        write_points(buf, ring)?;
+    }
+    Ok(())
+}
+
+fn as_array(value: &Value) -> Result<&Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid_geojson("expected an array of coordinates"))
+}
+
+fn as_f64(value: &Value) -> Result<f64> {
+    value
+        .as_f64()
+        .ok_or_else(|| invalid_geojson("expected a numeric coordinate"))
+}
+
+fn read_u8(buf: &mut &[u8]) -> Result<u8> {
+    let (value, rest) = buf.split_first().ok_or_else(invalid_wkb)?;
+    *buf = rest;
+    Ok(*value)
+}
+
+fn read_u32(buf: &mut &[u8], little_endian: bool) -> Result<u32> {
+    if buf.len() < 4 {
+        return Err(invalid_wkb());
+    }
+    let (value, rest) = buf.split_at(4);
+    *buf = rest;
+    let value = value.try_into().unwrap();
+    Ok(if little_endian {
+        u32::from_le_bytes(value)
+    } else {
+        u32::from_be_bytes(value)
+    })
+}
+
+fn read_f64(buf: &mut &[u8], little_endian: bool) -> Result<f64> {
+    if buf.len() < 8 {
+        return Err(invalid_wkb());
+    }
+    let (value, rest) = buf.split_at(8);
+    *buf = rest;
+    let value = value.try_into().unwrap();
+    Ok(if little_endian {
+        f64::from_le_bytes(value)
+    } else {
+        f64::from_be_bytes(value)
+    })
+}
+
+fn invalid_wkb() -> Error {
+    Error::Sqlx("invalid geometry value".into())
+}
+
+fn invalid_geojson(msg: &str) -> Error {
+    Error::CborDeValue(format!("invalid GeoJSON geometry: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Cbor<'a> = Encoder<&'a mut Vec<u8>>;
+
+    /// CBOR of a GeoJSON geometry, written by `coordinates`
+    fn geojson(name: &str, coordinates: impl FnOnce(&mut Cbor) -> Result<()>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut out = Encoder::new(&mut buf);
+        out.map(2).unwrap();
+        out.str("type").unwrap().str(name).unwrap();
+        out.str("coordinates").unwrap();
+        coordinates(&mut out).unwrap();
+        buf
+    }
+
+    fn point(out: &mut Cbor, x: f64, y: f64) -> Result<()> {
+        out.array(2)?.f64(x)?.f64(y)?;
+        Ok(())
+    }
+
+    fn ring(out: &mut Cbor) -> Result<()> {
+        out.array(4)?;
+        point(out, 0.0, 0.0)?;
+        point(out, 1.0, 0.0)?;
+        point(out, 1.0, 1.0)?;
+        point(out, 0.0, 0.0)
+    }
+
+    /// Decode a GeoJSON parameter and encode it back as a column value
+    fn round_trip(cbor: &[u8]) -> Vec<u8> {
+        let geometry = decode_geojson(&mut Decoder::new(cbor)).unwrap();
+        let mut buf = Vec::new();
+        encode_geometry(
+            &mut Encoder::new(&mut buf),
+            &geometry,
+            GeometryEncoding::GeoJson,
+        )
+        .unwrap();
+        buf
+    }
+
+    #[test]
+    fn point_wkb() {
+        let cbor = geojson("Point", |out| point(out, 1.5, -2.0));
+        let geometry = decode_geojson(&mut Decoder::new(&cbor)).unwrap();
+
+        let mut expected = vec![0, 0, 0, 0, 1, 1, 0, 0, 0];
+        expected.extend_from_slice(&1.5f64.to_le_bytes());
+        expected.extend_from_slice(&(-2.0f64).to_le_bytes());
+        assert_eq!(geometry, expected);
+        assert_eq!(round_trip(&cbor), cbor);
+    }
+
+    #[test]
+    fn line_string() {
+        let cbor = geojson("LineString", |out| {
+            out.array(2)?;
+            point(out, 0.0, 0.0)?;
+            point(out, 3.0, 4.0)
+        });
+        assert_eq!(round_trip(&cbor), cbor);
+    }
+
+    #[test]
+    fn polygon() {
+        let cbor = geojson("Polygon", |out| {
+            out.array(1)?;
+            ring(out)
+        });
+        assert_eq!(round_trip(&cbor), cbor);
+    }
+
+    #[test]
+    fn multi_geometries() {
+        let multi_point = geojson("MultiPoint", |out| {
+            out.array(2)?;
+            point(out, 1.0, 2.0)?;
+            point(out, 3.0, 4.0)
+        });
+        assert_eq!(round_trip(&multi_point), multi_point);
+
+        let multi_line_string = geojson("MultiLineString", |out| {
+            out.array(2)?;
+            out.array(2)?;
+            point(out, 0.0, 0.0)?;
+            point(out, 1.0, 1.0)?;
+            out.array(1)?;
+            point(out, 2.0, 2.0)
+        });
+        assert_eq!(round_trip(&multi_line_string), multi_line_string);
+
+        let multi_polygon = geojson("MultiPolygon", |out| {
+            out.array(2)?;
+            out.array(1)?;
+            ring(out)?;
+            out.array(0)?;
+            Ok(())
+        });
+        assert_eq!(round_trip(&multi_polygon), multi_polygon);
+    }
+
+    #[test]
+    fn geometry_collection() {
+        let mut cbor = Vec::new();
+        let mut out = Encoder::new(&mut cbor);
+        out.map(2).unwrap();
+        out.str("type").unwrap().str("GeometryCollection").unwrap();
+        out.str("geometries").unwrap().array(2).unwrap();
+        let member = geojson("Point", |out| point(out, 1.0, 2.0));
+        let empty = geojson("LineString", |out| {
+            out.array(0)?;
+            Ok(())
+        });
+        cbor.extend_from_slice(&member);
+        cbor.extend_from_slice(&empty);
+        assert_eq!(round_trip(&cbor), cbor);
+    }
+
+    #[test]
+    fn srid_is_dropped() {
+        let mut wkb = vec![1, 1, 0, 0, 0];
+        wkb.extend_from_slice(&1.0f64.to_le_bytes());
+        wkb.extend_from_slice(&2.0f64.to_le_bytes());
+        let mut value = 4326u32.to_le_bytes().to_vec();
+        value.extend_from_slice(&wkb);
+
+        let mut buf = Vec::new();
+        encode_geometry(&mut Encoder::new(&mut buf), &value, GeometryEncoding::Wkb).unwrap();
+        assert_eq!(Decoder::new(&buf).bytes().unwrap(), wkb.as_slice());
+
+        let mut buf = Vec::new();
+        encode_geometry(
+            &mut Encoder::new(&mut buf),
+            &value,
+            GeometryEncoding::GeoJson,
+        )
+        .unwrap();
+        assert_eq!(buf, geojson("Point", |out| point(out, 1.0, 2.0)));
+    }
+
+    #[test]
+    fn big_endian_wkb() {
+        let mut value = vec![0, 0, 0, 0, 0, 0, 0, 0, 1];
+        value.extend_from_slice(&1.0f64.to_be_bytes());
+        value.extend_from_slice(&2.0f64.to_be_bytes());
+
+        let mut buf = Vec::new();
+        encode_geometry(
+            &mut Encoder::new(&mut buf),
+            &value,
+            GeometryEncoding::GeoJson,
+        )
+        .unwrap();
+        assert_eq!(buf, geojson("Point", |out| point(out, 1.0, 2.0)));
+    }
+
+    #[test]
+    fn invalid_wkb_is_an_error() {
+        // a point missing its y coordinate
+        let mut value = vec![0, 0, 0, 0, 1, 1, 0, 0, 0];
+        value.extend_from_slice(&1.0f64.to_le_bytes());
+
+        let mut buf = Vec::new();
+        let result = encode_geometry(
+            &mut Encoder::new(&mut buf),
+            &value,
+            GeometryEncoding::GeoJson,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn other_geojson_objects_are_rejected() {
+        let feature = geojson("Feature", |out| {
+            out.array(0)?;
+            Ok(())
+        });
+        assert!(decode_geojson(&mut Decoder::new(&feature)).is_err());
+    }
+}
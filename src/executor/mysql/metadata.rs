@@ -0,0 +1,88 @@
+//! MySQL column metadata
+//!
+//! sqlx keeps the flags and display width of each column, but doesn't expose
+//! them, nor does it keep the table of the column definition, so only
+//! nullability is reported. The flags can be read from the type info sqlx
+//! serializes for its offline mode, which tells SET columns apart from the
+//! `CHAR` columns sqlx names them.
+
+use sqlx::{
+    mysql::{MySqlColumn, MySqlTypeInfo},
+    Column as _, MySqlConnection, TypeInfo,
+};
+use wasmcloud_interface_sqldb::Column;
+
+use crate::{ext::ColumnMetadata, result::Result};
+
+/// Column definition flag of SET columns
+const SET_FLAG: u64 = 0x800;
+
+/// Name of a column type, `SET` for SET columns
+pub(super) fn type_name(type_info: &MySqlTypeInfo) -> &str {
+    let name = type_info.name();
+    if name != "CHAR" {
+        return name;
+    }
+    let flags = serde_json::to_value(type_info)
+        .ok()
+        .and_then(|info| info["flags"]["bits"].as_u64());
+    match flags {
+        Some(flags) if flags & SET_FLAG != 0 => "SET",
+        _ => name,
+    }
+}
+
+/// The columns of a result, with their type names from `type_name`
+pub(super) fn to_columns(columns: &[MySqlColumn]) -> Vec<Column> {
+    columns
+        .iter()
+        .map(|column| Column {
+            ordinal: column.ordinal() as u32,
+            name: column.name().into(),
+            db_type: type_name(column.type_info()).into(),
+        })
+        .collect()
+}
+
+pub(super) async fn describe_columns(
+    conn: &mut MySqlConnection,
+    sql: &str,
+) -> Result<Vec<ColumnMetadata>> {
+    let describe = sqlx::Executor::describe(&mut *conn, sql).await?;
+    let columns = describe
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| ColumnMetadata {
+            ordinal: column.ordinal() as u32,
+            name: column.name().into(),
+            db_type: type_name(column.type_info()).into(),
+            nullable: describe.nullable(i),
+            ..Default::default()
+        })
+        .collect();
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Type info of a string column with the given flags, as sqlx serializes it
+    fn string_column(flags: u64) -> MySqlTypeInfo {
+        serde_json::from_value(serde_json::json!({
+            "type": "String",
+            "flags": { "bits": flags },
+            "char_set": 255,
+            "max_size": 40,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn set_columns_are_told_apart() {
+        // NOT_NULL | SET
+        assert_eq!(type_name(&string_column(0x801)), "SET");
+        assert_eq!(type_name(&string_column(0x001)), "CHAR");
+    }
+}
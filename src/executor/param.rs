@@ -0,0 +1,137 @@
+//! Statement parameters
+//!
+//! Parameters are decoded from CBOR once, into the values the executors know
+//! how to bind, so each backend only decides how to map them onto its types.
+
+use minicbor::{
+    data::{Tag, Type},
+    Decoder,
+};
+
+use crate::result::{Error, Result};
+
+use super::{hint::TypeHint, tag::TaggedValue};
+
+/// CBOR tag for an object with a type name, `[type, value]`
+const TAG_TYPE_HINT: u64 = 27;
+/// CBOR tag for a finite set
+const TAG_SET: u64 = 258;
+
+/// A statement parameter decoded from CBOR
+#[derive(Debug)]
+pub(crate) enum Param<'a> {
+    Null,
+    Bool(bool),
+    Int(i128),
+    F32(f32),
+    F64(f64),
+    Bytes(Vec<u8>),
+    Text(String),
+    /// an array, left encoded
+    Array(&'a [u8]),
+    /// a map, left encoded
+    Map(&'a [u8]),
+    /// a finite set (CBOR tag 258) of strings
+    Set(Vec<String>),
+    Tagged(TaggedValue),
+    /// a value with a type hint (CBOR tag 27)
+    Hinted(TypeHint, Box<Param<'a>>),
+    /// a value with a type hint naming a type that isn't one of the hints,
+    /// such as a Postgres enum
+    UserTyped(String, Box<Param<'a>>),
+}
+
+impl<'a> Param<'a> {
+    pub(crate) fn decode(value: &'a [u8]) -> Result<Self> {
+        let mut decoder = Decoder::new(value);
+        let datatype = decoder.datatype()?;
+        let param = match datatype {
+            Type::Null | Type::Undefined => Param::Null,
+            Type::Bool => Param::Bool(decoder.bool()?),
+            Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::Int => Param::Int(i128::from(decoder.int()?)),
+            Type::F16 | Type::F32 => Param::F32(decoder.f32()?),
+            Type::F64 => Param::F64(decoder.f64()?),
+            Type::Bytes => Param::Bytes(decoder.bytes()?.to_vec()),
+            Type::BytesIndef => {
+                let mut bytes = Vec::new();
+                for chunk in decoder.bytes_iter()? {
+                    bytes.extend_from_slice(chunk?);
+                }
+                Param::Bytes(bytes)
+            }
+            Type::String => Param::Text(decoder.str()?.to_string()),
+            Type::StringIndef => {
+                let mut text = String::new();
+                for chunk in decoder.str_iter()? {
+                    text.push_str(chunk?);
+                }
+                Param::Text(text)
+            }
+            Type::Array | Type::ArrayIndef => Param::Array(value),
+            Type::Map | Type::MapIndef => Param::Map(value),
+            Type::Tag => match decoder.tag()? {
+                Tag::Unassigned(TAG_SET) => {
+                    let mut members = Vec::new();
+                    for member in decoder.array_iter::<&str>()? {
+                        members.push(member?.to_string());
+                    }
+                    Param::Set(members)
+                }
+                Tag::Unassigned(TAG_TYPE_HINT) => {
+                    if decoder.array()? != Some(2) {
+                        return Err(Error::CborDeValue(
+                            "a type hint must be an array of a type name and a value".into(),
+                        ));
+                    }
+                    let name = decoder.str()?;
+                    let start = decoder.position();
+                    decoder.skip()?;
+                    let value = Box::new(Param::decode(&value[start..decoder.position()])?);
+                    match name.parse() {
+                        Ok(hint) => Param::Hinted(hint, value),
+                        Err(_) => Param::UserTyped(name.to_string(), value),
+                    }
+                }
+                tag => Param::Tagged(TaggedValue::decode(tag, &mut decoder)?),
+            },
+            _ => return Err(Error::CborDeType(datatype)),
+        };
+        Ok(param)
+    }
+
+    /// Error for an integer that doesn't fit the types a database binds,
+    /// reporting values beyond `i64` that fit a `u64` as such
+    pub(crate) fn int_out_of_range(int: i128) -> Error {
+        match u64::try_from(int) {
+            Ok(value) => Error::CborDeU64OutOfRange(value),
+            Err(_) => Error::CborDeIntOutOfRange(int),
+        }
+    }
+
+    /// Name of the kind of value, for error messages
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Param::Null => "null",
+            Param::Bool(_) => "bool",
+            Param::Int(_) => "integer",
+            Param::F32(_) | Param::F64(_) => "float",
+            Param::Bytes(_) => "byte string",
+            Param::Text(_) => "string",
+            Param::Array(_) => "array",
+            Param::Map(_) => "map",
+            Param::Set(_) => "set",
+            Param::Tagged(TaggedValue::DateTime(_)) => "date/time",
+            Param::Tagged(TaggedValue::Uuid(_)) => "uuid",
+            Param::Tagged(TaggedValue::Number(_)) => "number",
+            Param::Hinted(..) | Param::UserTyped(..) => "hinted value",
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Statement placeholders
+//!
+//! Placeholders are found by scanning the SQL text, skipping string literals,
+//! quoted identifiers and comments in the quoting rules of each database.
+//! Statements may use portable `:name` placeholders, with their values in a
+//! map parameter carrying the named parameters tag, which are rewritten to the
+//! native `$1`, `?` or `@p1` placeholders before the parameters are bound.
+//! Keywords are found the same way, for the few statement rewrites the
+//! provider makes.
+
+use std::{borrow::Cow, collections::HashMap};
+
+use minicbor::{
+    data::{Tag, Type},
+    Decoder,
+};
+use sqlx::any::AnyKind;
+use wasmcloud_interface_sqldb::Statement;
+
+use crate::result::{Error, Result};
+
+/// CBOR tag of a map of named parameters (`np` in ASCII)
+const TAG_NAMED_PARAMS: u64 = 0x6e70;
+
+/// SQL dialect, for the quoting rules used when scanning for placeholders
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    Postgres,
+    MySql,
+    Mssql,
+}
+
+impl From<AnyKind> for Dialect {
+    fn from(kind: AnyKind) -> Self {
+        match kind {
+            AnyKind::Postgres => Dialect::Postgres,
+            AnyKind::MySql => Dialect::MySql,
+            AnyKind::Mssql => Dialect::Mssql,
+        }
+    }
+}
+
+/// Replace the placeholders starting with `sigil` outside of literals and
+/// comments. `replace` is called with the name following the sigil (e.g. `1`
+/// for `$1`, `p1` for `@p1`), and the placeholder is kept as is when it
+/// returns `None`.
+pub(crate) fn replace_placeholders(
+    sql: &str,
+    dialect: Dialect,
+    sigil: char,
+    mut replace: impl FnMut(&str) -> Option<String>,
+) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut rest = sql;
+    // array subscripts and slices, e.g. `arr[1:n]`, don't hold named placeholders
+    let mut brackets = 0usize;
+    while let Some(c) = rest.chars().next() {
+        let skip = match c {
+            // a Postgres `::` cast isn't a placeholder
+            ':' if rest.starts_with("::") => 2,
+            _ => skipped_len(rest, dialect),
+        };
+        if skip > 0 {
+            out.push_str(&rest[..skip]);
+            rest = &rest[skip..];
+            continue;
+        }
+
+        let len = c.len_utf8();
+        match c {
+            '[' if sigil == ':' => brackets += 1,
+            ']' => brackets = brackets.saturating_sub(1),
+            _ => {}
+        }
+        if c == sigil && brackets == 0 {
+            let name_len = rest[len..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - len);
+            let name = &rest[len..len + name_len];
+            if let Some(replacement) = replace(name) {
+                out.push_str(&replacement);
+                rest = &rest[len + name_len..];
+                continue;
+            }
+        }
+        out.push(c);
+        rest = &rest[len..];
+    }
+    out
+}
+
+/// Offset of the first occurrence of one of `keywords` outside of literals,
+/// comments and parentheses, ignoring case
+pub(crate) fn find_keyword(sql: &str, dialect: Dialect, keywords: &[&str]) -> Option<usize> {
+    let mut depth = 0;
+    let mut offset = 0;
+    while let Some(c) = sql[offset..].chars().next() {
+        let rest = &sql[offset..];
+        let skip = skipped_len(rest, dialect);
+        if skip > 0 {
+            offset += skip;
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let word_len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            let word = &rest[..word_len];
+            if depth == 0 && keywords.iter().any(|k| k.eq_ignore_ascii_case(word)) {
+                return Some(offset);
+            }
+            offset += word_len;
+            continue;
+        }
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        offset += c.len_utf8();
+    }
+    None
+}
+
+/// Which of `keywords` occurs first, as `find_keyword` finds them
+pub(crate) fn first_keyword<'k>(
+    sql: &str,
+    dialect: Dialect,
+    keywords: &[&'k str],
+) -> Option<&'k str> {
+    let at = find_keyword(sql, dialect, keywords)?;
+    let word = &sql[at..];
+    keywords.iter().copied().find(|k| {
+        word.get(..k.len())
+            .is_some_and(|w| w.eq_ignore_ascii_case(k))
+    })
+}
+
+/// The statements of a string of statements separated by `;` outside of
+/// literals, comments and parentheses, leaving out blank ones
+pub(crate) fn split_statements(sql: &str, dialect: Dialect) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut offset = 0;
+    while let Some(c) = sql[offset..].chars().next() {
+        let skip = skipped_len(&sql[offset..], dialect);
+        if skip > 0 {
+            offset += skip;
+            continue;
+        }
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ';' if depth == 0 => {
+                statements.push(&sql[start..offset]);
+                start = offset + 1;
+            }
+            _ => {}
+        }
+        offset += c.len_utf8();
+    }
+    statements.push(&sql[start..]);
+    statements.retain(|statement| !statement.trim().is_empty());
+    statements
+}
+
+/// Length of the literal, quoted identifier or comment `sql` starts with, or
+/// zero if it doesn't start with one
+pub(super) fn skipped_len(sql: &str, dialect: Dialect) -> usize {
+    match sql.chars().next() {
+        Some('\'') => quoted_len(sql, '\'', dialect == Dialect::MySql),
+        Some('"') => quoted_len(sql, '"', dialect == Dialect::MySql),
+        Some('`') if dialect == Dialect::MySql => quoted_len(sql, '`', false),
+        Some('[') if dialect == Dialect::Mssql => quoted_len(sql, ']', false),
+        Some('-') if sql.starts_with("--") => sql.find('\n').unwrap_or(sql.len()),
+        Some('#') if dialect == Dialect::MySql => sql.find('\n').unwrap_or(sql.len()),
+        Some('/') if sql.starts_with("/*") => sql[2..].find("*/").map_or(sql.len(), |n| n + 4),
+        Some('$') if dialect == Dialect::Postgres => dollar_quoted_len(sql),
+        _ => 0,
+    }
+}
+
+/// Length of a literal or identifier quoted with `quote` (or ending with it,
+/// for bracketed identifiers). A doubled closing quote is an escaped quote.
+fn quoted_len(sql: &str, close: char, backslash_escapes: bool) -> usize {
+    let mut chars = sql.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if backslash_escapes && c == '\\' {
+            chars.next();
+        } else if c == close {
+            let end = i + c.len_utf8();
+            if !sql[end..].starts_with(close) {
+                return end;
+            }
+            chars.next();
+        }
+    }
+    sql.len()
+}
+
+/// Length of a Postgres dollar-quoted string (`$$...$$` or `$tag$...$tag$`), or
+/// zero if `sql` doesn't start with one
+fn dollar_quoted_len(sql: &str) -> usize {
+    let tag_len = match sql[1..].find(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        Some(n) if sql[1 + n..].starts_with('$') => n,
+        _ => return 0,
+    };
+    // `$1` is a placeholder, tags can't start with a digit
+    if sql[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return 0;
+    }
+    let delimiter = &sql[..tag_len + 2];
+    sql[delimiter.len()..]
+        .find(delimiter)
+        .map_or(sql.len(), |n| delimiter.len() * 2 + n)
+}
+
+/// Rewrite portable `:name` placeholders into the native placeholders of the
+/// database, when the statement has a single parameter holding the values by
+/// name, in a CBOR map tagged as named parameters. Other statements are left
+/// as is.
+pub(crate) fn bind_named(stmt: &Statement, dialect: Dialect) -> Result<Cow<'_, Statement>> {
+    let map = match stmt.parameters.as_deref() {
+        Some([param]) => match named_params(param) {
+            Some(map) => map,
+            None => return Ok(Cow::Borrowed(stmt)),
+        },
+        _ => return Ok(Cow::Borrowed(stmt)),
+    };
+    if has_positional(&stmt.sql, dialect) {
+        return Err(Error::InvalidRequest(
+            "named and positional placeholders can't be mixed".into(),
+        ));
+    }
+    let mut names = Vec::new();
+    replace_placeholders(&stmt.sql, dialect, ':', |name| {
+        if is_name(name) {
+            names.push(name.to_string());
+        }
+        None
+    });
+
+    let values = decode_named(map)?;
+    if let Some(name) = names
+        .iter()
+        .find(|name| !values.contains_key(name.as_str()))
+    {
+        return Err(Error::CborDeValue(format!("missing parameter `{}`", name)));
+    }
+
+    // names are numbered in order of first use, MySQL repeats the value for
+    // each `?`
+    let mut order: Vec<&str> = Vec::new();
+    let mut parameters = Vec::new();
+    let sql = replace_placeholders(&stmt.sql, dialect, ':', |name| {
+        let (name, value) = values.get_key_value(name)?;
+        if dialect == Dialect::MySql {
+            parameters.push(value.to_vec());
+            return Some("?".to_string());
+        }
+        let n = match order.iter().position(|n| n == name) {
+            Some(i) => i + 1,
+            None => {
+                order.push(name);
+                parameters.push(value.to_vec());
+                order.len()
+            }
+        };
+        Some(match dialect {
+            Dialect::Postgres => format!("${}", n),
+            _ => format!("@p{}", n),
+        })
+    });
+
+    Ok(Cow::Owned(Statement {
+        database: stmt.database.clone(),
+        parameters: Some(parameters),
+        sql,
+    }))
+}
+
+/// The encoded map of a parameter tagged as named parameters
+fn named_params(param: &[u8]) -> Option<&[u8]> {
+    let mut decoder = Decoder::new(param);
+    if decoder.tag().ok()? != Tag::Unassigned(TAG_NAMED_PARAMS) {
+        return None;
+    }
+    let map = &param[decoder.position()..];
+    matches!(decoder.datatype(), Ok(Type::Map | Type::MapIndef)).then_some(map)
+}
+
+/// Whether a statement has native positional placeholders
+fn has_positional(sql: &str, dialect: Dialect) -> bool {
+    let mut found = false;
+    let (sigil, prefix) = match dialect {
+        Dialect::Postgres => ('$', ""),
+        Dialect::MySql => ('?', ""),
+        Dialect::Mssql => ('@', "p"),
+    };
+    replace_placeholders(sql, dialect, sigil, |name| {
+        let number = &name[name.len().min(prefix.len())..];
+        found |= match dialect {
+            // every `?` is a placeholder
+            Dialect::MySql => true,
+            _ => {
+                name.starts_with(prefix)
+                    && !number.is_empty()
+                    && number.chars().all(|c| c.is_ascii_digit())
+            }
+        };
+        None
+    });
+    found
+}
+
+/// Whether a placeholder name is an identifier, so `[1:2]` slices aren't
+/// taken for placeholders
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+/// Split a CBOR map of parameters into the encoded value of each name
+fn decode_named(map: &[u8]) -> Result<HashMap<&str, &[u8]>> {
+    let mut decoder = Decoder::new(map);
+    let mut values = HashMap::new();
+    let mut remaining = decoder.map()?;
+    loop {
+        match remaining {
+            Some(0) => break,
+            Some(n) => remaining = Some(n - 1),
+            None if decoder.datatype()? == Type::Break => break,
+            None => {}
+        }
+        let name = decoder.str()?;
+        let start = decoder.position();
+        decoder.skip()?;
+        values.insert(name, &map[start..decoder.position()]);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A statement with the entries as its named parameters
+    fn named(sql: &str, entries: &[(&str, i64)], tagged: bool) -> Statement {
+        let mut param = Vec::new();
+        let mut encoder = minicbor::Encoder::new(&mut param);
+        if tagged {
+            encoder.tag(Tag::Unassigned(TAG_NAMED_PARAMS)).unwrap();
+        }
+        encoder.map(entries.len() as u64).unwrap();
+        for (name, value) in entries {
+            encoder.str(name).unwrap().i64(*value).unwrap();
+        }
+        Statement {
+            sql: sql.to_string(),
+            parameters: Some(vec![param]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn named_placeholders() {
+        let stmt = named("select :a, :b, :a", &[("a", 1), ("b", 2)], true);
+        let postgres = bind_named(&stmt, Dialect::Postgres).unwrap();
+        assert_eq!(postgres.sql, "select $1, $2, $1");
+        assert_eq!(postgres.parameters.as_ref().map(Vec::len), Some(2));
+        let mysql = bind_named(&stmt, Dialect::MySql).unwrap();
+        assert_eq!(mysql.sql, "select ?, ?, ?");
+        assert_eq!(mysql.parameters.as_ref().map(Vec::len), Some(3));
+        let mssql = bind_named(&stmt, Dialect::Mssql).unwrap();
+        assert_eq!(mssql.sql, "select @p1, @p2, @p1");
+    }
+
+    #[test]
+    fn untagged_maps_are_left_alone() {
+        let stmt = named("select :a", &[("a", 1)], false);
+        let bound = bind_named(&stmt, Dialect::Postgres).unwrap();
+        assert!(matches!(bound, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn skips_casts_literals_and_slices() {
+        let stmt = named(
+            "select :a::int, ':a', arr[1:n], arr[:a] from t where x = :a",
+            &[("a", 1)],
+            true,
+        );
+        let bound = bind_named(&stmt, Dialect::Postgres).unwrap();
+        assert_eq!(
+            bound.sql,
+            "select $1::int, ':a', arr[1:n], arr[:a] from t where x = $1"
+        );
+    }
+
+    #[test]
+    fn mixed_placeholders_are_rejected() {
+        for (sql, dialect) in [
+            ("select :a, $1", Dialect::Postgres),
+            ("select :a, ?", Dialect::MySql),
+            ("select :a, @p1", Dialect::Mssql),
+        ] {
+            let stmt = named(sql, &[("a", 1)], true);
+            assert!(matches!(
+                bind_named(&stmt, dialect),
+                Err(Error::InvalidRequest(_))
+            ));
+        }
+        // `$$` quotes and `@variables` aren't positional placeholders
+        let stmt = named("select :a, $$?$$", &[("a", 1)], true);
+        assert!(bind_named(&stmt, Dialect::Postgres).is_ok());
+        let stmt = named("select :a, @count", &[("a", 1)], true);
+        assert!(bind_named(&stmt, Dialect::Mssql).is_ok());
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            split_statements(
+                "select 1; select ';' -- ;\n; select $$a;b$$;",
+                Dialect::Postgres
+            ),
+            vec!["select 1", " select ';' -- ;\n", " select $$a;b$$"]
+        );
+        assert_eq!(
+            split_statements("call p(';'); select `a;b`", Dialect::MySql),
+            vec!["call p(';')", " select `a;b`"]
+        );
+    }
+
+    #[test]
+    fn missing_names_are_rejected() {
+        let stmt = named("select :a, :b", &[("a", 1)], true);
+        assert!(bind_named(&stmt, Dialect::Postgres).is_err());
+    }
+}
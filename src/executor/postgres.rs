@@ -1,25 +1,124 @@
+mod catalog;
+mod copy;
+mod metadata;
+mod range;
+
+use std::str::FromStr;
+
 use async_trait::async_trait;
+use futures::TryStreamExt;
 use sqlx::{
     database::HasArguments,
-    postgres::{types::Oid, PgRow},
+    encode::IsNull,
+    postgres::{
+        types::{Oid, PgRecordDecoder},
+        PgArgumentBuffer, PgRow, PgTypeInfo, PgTypeKind, PgValue, PgValueRef,
+    },
     query::Query,
-    Column, Decode, PgConnection, Postgres, Row, TypeInfo, ValueRef,
+    types::BigDecimal,
+    Column, Decode, Either, Encode, PgConnection, Postgres, Row, Type as SqlType, TypeInfo, Value,
+    ValueRef,
 };
 use time::{
-    format_description::well_known::Rfc3339, macros::format_description, Date, OffsetDateTime,
-    PrimitiveDateTime, Time,
+    format_description::well_known::Rfc3339, Date, OffsetDateTime, PrimitiveDateTime, Time,
+    UtcOffset,
 };
+use tokio::sync::mpsc;
 use uuid::Uuid;
 use wasmcloud_interface_sqldb::{ExecuteResult, QueryResult, Statement};
 
-use crate::result::{Error, Result};
+use crate::{
+    config::EncodingOptions,
+    ext::{
+        BulkInsert, CallResult, ColumnMetadata, Export, MultiQueryResult, ParamMode, ProcedureCall,
+        ResultSet, ReturningResult, TableDescription, TableInfo,
+    },
+    result::{Error, Result},
+};
+
+use self::range::{RangeElement, RangeParam};
+use super::{
+    call,
+    datetime::{DATE_FORMAT, TIMESTAMP_FORMAT, TIME_FORMAT},
+    described,
+    hint::BindTyped,
+    ident, json,
+    kind::ValueKind,
+    param::Param,
+    placeholder::{find_keyword, first_keyword, replace_placeholders, split_statements, Dialect},
+    split_results,
+    tag::{self, TaggedValue},
+    to_columns, BindCbor, SqlDbExecutor,
+};
+
+type PgQuery<'q> = Query<'q, Postgres, <Postgres as HasArguments<'q>>::Arguments>;
+
+/// Something Postgres values can be bound to: the parameters of a query, or
+/// the fields of a row sent with `COPY`
+pub(super) trait PgBind<'q>: Sized {
+    fn bind<T>(self, value: T) -> Self
+    where
+        T: 'q + Send + Encode<'q, Postgres> + SqlType<Postgres>;
+}
+
+impl<'q> PgBind<'q> for PgQuery<'q> {
+    fn bind<T>(self, value: T) -> Self
+    where
+        T: 'q + Send + Encode<'q, Postgres> + SqlType<Postgres>,
+    {
+        Query::bind(self, value)
+    }
+}
+
+/// Postgres binds every hinted type natively
+impl<'q, B: PgBind<'q>> BindTyped<'q> for B {
+    type Database = Postgres;
+
+    fn bind_value<T>(self, value: T) -> Self
+    where
+        T: 'q + Send + Encode<'q, Postgres> + SqlType<Postgres>,
+    {
+        self.bind(value)
+    }
+
+    fn bind_bytes(self, value: Vec<u8>) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_numeric(self, value: BigDecimal) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_date(self, value: Date) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_time(self, value: Time) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_timestamp(self, value: PrimitiveDateTime) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_timestamptz(self, value: OffsetDateTime) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+
+    fn bind_uuid(self, value: Uuid) -> Result<Self> {
+        Ok(self.bind(value))
+    }
 
-use super::{bind_query, to_columns, BindCbor, SqlDbExecutor};
+    fn bind_json(self, value: serde_json::Value) -> Result<Self> {
+        Ok(self.bind(value))
+    }
+}
 
 #[async_trait]
 impl SqlDbExecutor for PgConnection {
     async fn execute(&mut self, stmt: &Statement) -> Result<ExecuteResult> {
-        let query = bind_query(stmt)?;
+        let sql = cast_hinted_params(stmt)?;
+        let query = bind_pg_query(&sql, stmt)?;
         let result = sqlx::Executor::execute(self, query).await?;
         Ok(ExecuteResult {
             rows_affected: result.rows_affected(),
@@ -27,95 +126,517 @@ impl SqlDbExecutor for PgConnection {
         })
     }
 
-    async fn fetch_all(&mut self, stmt: &Statement) -> Result<QueryResult> {
-        let query = bind_query(stmt)?;
-        let rows = sqlx::Executor::fetch_all(self, query).await?;
+    async fn fetch_all(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<QueryResult> {
+        let sql = cast_hinted_params(stmt)?;
+        let query = bind_pg_query(&sql, stmt)?;
+        let rows = sqlx::Executor::fetch_all(&mut *self, query).await?;
         if rows.is_empty() {
-            Ok(QueryResult::default())
+            // describe the statement so the columns are known without rows
+            let columns = described(sqlx::Executor::describe(self, sql.as_str()).await)?
+                .map(|describe| to_columns(describe.columns()))
+                .unwrap_or_default();
+            Ok(QueryResult {
+                columns,
+                ..Default::default()
+            })
         } else {
             Ok(QueryResult {
                 num_rows: rows.len() as u64,
-                columns: to_columns(&rows),
-                rows: pgrow_to_cbor(&rows)?,
+                columns: to_columns(rows[0].columns()),
+                rows: pgrow_to_cbor(&rows, encoding)?,
                 error: None,
             })
         }
     }
-}
 
-impl<'q> BindCbor for Query<'q, Postgres, <Postgres as HasArguments<'q>>::Arguments> {
-    fn bind_cbor(self, value: &[u8]) -> Result<Self> {
-        use minicbor::data::Type;
-
-        let mut decoder = minicbor::Decoder::new(value);
-        let datatype = decoder.datatype()?;
-        let query = match datatype {
-            Type::Bool => self.bind(decoder.bool()?),
-            Type::Null | Type::Undefined => self.bind(None::<bool>),
-            Type::U8 => self.bind(decoder.u8()? as i16),
-            Type::U16 => {
-                let value = decoder.u16()?;
-                if let Ok(value) = i16::try_from(value) {
-                    self.bind(value)
-                } else {
-                    self.bind(value as i32)
-                }
+    async fn describe(&mut self, stmt: &Statement) -> Result<Vec<ColumnMetadata>> {
+        let sql = cast_hinted_params(stmt)?;
+        metadata::describe_columns(self, &sql).await
+    }
+
+    async fn execute_returning(
+        &mut self,
+        stmt: &Statement,
+        key_columns: &[String],
+        encoding: &EncodingOptions,
+    ) -> Result<ReturningResult> {
+        let stmt = Statement {
+            sql: with_returning(&stmt.sql, key_columns),
+            ..stmt.clone()
+        };
+        let sql = cast_hinted_params(&stmt)?;
+        let query = bind_pg_query(&sql, &stmt)?;
+
+        let mut rows_affected = 0;
+        let mut rows = Vec::new();
+        let mut results = sqlx::Executor::fetch_many(&mut *self, query);
+        while let Some(result) = results.try_next().await? {
+            match result {
+                Either::Left(result) => rows_affected += result.rows_affected(),
+                Either::Right(row) => rows.push(row),
             }
-            Type::U32 => {
-                let value = decoder.u32()?;
-                if let Ok(value) = i32::try_from(value) {
-                    self.bind(value)
-                } else {
-                    self.bind(value as i64)
+        }
+        Ok(ReturningResult {
+            rows_affected,
+            num_rows: rows.len() as u64,
+            columns: rows
+                .first()
+                .map(|row| to_columns(row.columns()))
+                .unwrap_or_default(),
+            rows: pgrow_to_cbor(&rows, encoding)?,
+            error: None,
+        })
+    }
+
+    async fn fetch_multi(
+        &mut self,
+        stmt: &Statement,
+        encoding: &EncodingOptions,
+    ) -> Result<MultiQueryResult> {
+        // the extended query protocol runs one statement at a time, so a
+        // string of statements without parameters is run statement by statement
+        let sql = cast_hinted_params(stmt)?;
+        let statements = if stmt.parameters.as_deref().unwrap_or_default().is_empty() {
+            split_statements(&sql, Dialect::Postgres)
+        } else {
+            vec![sql.as_str()]
+        };
+        let mut result = MultiQueryResult::default();
+        for sql in statements {
+            let query = bind_pg_query(sql, stmt)?;
+            let results = sqlx::Executor::fetch_many(&mut *self, query);
+            let (result_sets, rows_affected) =
+                split_results(results, |result| result.rows_affected()).await?;
+            if result_sets.is_empty() && rows_affected == [0] {
+                // a query that returns no rows still has its columns
+                let describe = described(sqlx::Executor::describe(&mut *self, sql).await)?;
+                if let Some(describe) = describe.filter(|d| !d.columns().is_empty()) {
+                    result.result_sets.push(ResultSet {
+                        columns: to_columns(describe.columns()),
+                        rows: pgrow_to_cbor(&[], encoding)?,
+                        ..Default::default()
+                    });
+                    continue;
                 }
             }
-            Type::U64 => {
-                let value = decoder.u64()?;
-                if let Ok(value) = i64::try_from(value) {
-                    self.bind(value)
-                } else {
-                    return Err(Error::CborDeU64OutOfRange(value));
-                }
+            for rows in &result_sets {
+                result.result_sets.push(ResultSet {
+                    num_rows: rows.len() as u64,
+                    columns: to_columns(rows[0].columns()),
+                    rows: pgrow_to_cbor(rows, encoding)?,
+                });
             }
-            Type::I8 => self.bind(decoder.i8()? as i16),
-            Type::I16 => self.bind(decoder.i16()?),
-            Type::I32 => self.bind(decoder.i32()?),
-            Type::I64 => self.bind(decoder.i64()?),
-            Type::Int => {
-                let int = decoder.int()?;
-                if let Ok(value) = i16::try_from(int) {
-                    self.bind(value)
-                } else if let Ok(value) = i32::try_from(int) {
-                    self.bind(value)
-                } else if let Ok(value) = i64::try_from(int) {
-                    self.bind(value)
-                } else {
-                    return Err(Error::CborDeIntOutOfRange(int));
+            result.rows_affected.extend(rows_affected);
+        }
+        Ok(result)
+    }
+
+    async fn call(
+        &mut self,
+        call: &ProcedureCall,
+        encoding: &EncodingOptions,
+    ) -> Result<CallResult> {
+        let mut args = Vec::new();
+        let mut parameters = Vec::new();
+        for param in &call.parameters {
+            let arg = match param.mode {
+                // a function's out parameters are columns of its result
+                ParamMode::Out if call.function => continue,
+                ParamMode::Out => match &param.db_type {
+                    Some(db_type) => format!("null::{}", ident::type_name(db_type)?),
+                    None => "null".to_string(),
+                },
+                ParamMode::In | ParamMode::InOut => {
+                    parameters.push(call::value(param));
+                    match &param.db_type {
+                        Some(db_type) => {
+                            format!("${}::{}", parameters.len(), ident::type_name(db_type)?)
+                        }
+                        None => format!("${}", parameters.len()),
+                    }
                 }
-            }
-            Type::F16 => self.bind(decoder.f16()?),
-            Type::F32 => self.bind(decoder.f32()?),
-            Type::F64 => self.bind(decoder.f64()?),
-            // Type::Simple => todo!(),
-            Type::Bytes => self.bind(decoder.bytes()?.to_vec()),
-            // Type::BytesIndef => todo!(),
-            Type::String => self.bind(decoder.str()?.to_string()),
-            // Type::StringIndef => todo!(),
-            // Type::Array => todo!(),
-            // Type::ArrayIndef => todo!(),
-            // Type::Map => todo!(),
-            // Type::MapIndef => todo!(),
-            // Type::Tag => todo!(),
-            // Type::Break => todo!(),
-            // Type::Unknown(_) => todo!(),
-            _ => return Err(Error::CborDeType(datatype)),
+            };
+            args.push(match &param.name {
+                Some(name) => format!("{} => {}", ident::identifier(name)?, arg),
+                None => arg,
+            });
+        }
+        let procedure = ident::identifier(&call.procedure)?;
+        let sql = if call.function {
+            format!("select * from {}({})", procedure, args.join(", "))
+        } else {
+            format!("call {}({})", procedure, args.join(", "))
+        };
+        let stmt = Statement {
+            sql,
+            parameters: (!parameters.is_empty()).then_some(parameters),
+            ..Default::default()
         };
+        let mut result = self.fetch_multi(&stmt, encoding).await?;
+        if call.function {
+            return call::function_result(result);
+        }
+
+        // a procedure with out or inout parameters returns them as a row
+        let has_outputs = call.parameters.iter().any(|p| p.mode != ParamMode::In);
+        let outputs = if has_outputs {
+            result.result_sets.pop().unwrap_or_default()
+        } else {
+            ResultSet::default()
+        };
+        Ok(CallResult {
+            outputs,
+            result_sets: result.result_sets,
+            rows_affected: result.rows_affected,
+            ..Default::default()
+        })
+    }
+
+    async fn bulk_insert(&mut self, insert: &BulkInsert) -> Result<u64> {
+        copy::copy_in(self, insert).await
+    }
+
+    async fn export(
+        &mut self,
+        export: &Export,
+        chunk_size: usize,
+        chunks: &mpsc::Sender<Result<Vec<u8>>>,
+    ) -> Result<()> {
+        copy::copy_out(self, export, chunk_size, chunks).await
+    }
+
+    async fn list_schemas(&mut self) -> Result<Vec<String>> {
+        catalog::list_schemas(self).await
+    }
+
+    async fn list_tables(&mut self, schema: Option<&str>) -> Result<Vec<TableInfo>> {
+        catalog::list_tables(self, schema).await
+    }
+
+    async fn describe_table(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+    ) -> Result<Option<TableDescription>> {
+        catalog::describe_table(self, schema, table).await
+    }
+}
+
+/// Bind the statement parameters as the natural types of their CBOR values,
+/// which Postgres casts where it can. Strings bind to enums and other
+/// user-defined types when the placeholder is cast to the type, in the
+/// statement or by a type hint, and maps bind to ranges when they are hinted
+/// with the range type.
+fn bind_pg_query<'q>(sql: &'q str, stmt: &Statement) -> Result<PgQuery<'q>> {
+    let mut query = sqlx::query::<Postgres>(sql);
+    for value in stmt.parameters.iter().flatten() {
+        query = query.bind_cbor(value)?;
+    }
+    Ok(query)
+}
+
+/// Append a `RETURNING` clause to an `INSERT`, `UPDATE` or `DELETE` statement
+/// that doesn't have one, returning the key columns or else all columns
+fn with_returning(sql: &str, key_columns: &[String]) -> String {
+    let dialect = Dialect::Postgres;
+    // `select ... for update` isn't an update
+    let kind = first_keyword(sql, dialect, &["select", "insert", "update", "delete"]);
+    if !matches!(kind, Some("insert" | "update" | "delete"))
+        || find_keyword(sql, dialect, &["returning"]).is_some()
+    {
+        return sql.to_string();
+    }
+    let columns = if key_columns.is_empty() {
+        "*".to_string()
+    } else {
+        key_columns
+            .iter()
+            .map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    // on a new line, in case the statement ends with a comment
+    format!(
+        "{}\nreturning {}",
+        sql.trim_end().trim_end_matches(';'),
+        columns
+    )
+}
+
+/// Cast the placeholders of parameters with a type hint to the hinted type, so
+/// the server infers the hinted type when the statement is prepared
+fn cast_hinted_params(stmt: &Statement) -> Result<String> {
+    let mut hints = Vec::new();
+    for (i, value) in stmt.parameters.iter().flatten().enumerate() {
+        match Param::decode(value)? {
+            Param::Hinted(hint, _) => hints.push((i + 1, hint.pg_name().to_string())),
+            Param::UserTyped(name, _) => {
+                ident::type_name(&name)?;
+                hints.push((i + 1, name));
+            }
+            _ => {}
+        }
+    }
+    if hints.is_empty() {
+        return Ok(stmt.sql.clone());
+    }
+
+    Ok(replace_placeholders(
+        &stmt.sql,
+        Dialect::Postgres,
+        '$',
+        |name| {
+            let n = name.parse::<usize>().ok()?;
+            let (_, hint) = hints.iter().find(|(i, _)| *i == n)?;
+            Some(format!("(${}::{})", n, hint))
+        },
+    ))
+}
+
+/// Bind a parameter, converting it to the parameter type of the prepared
+/// statement where the CBOR type doesn't map onto it directly
+fn bind_typed<'q, B: PgBind<'q>>(query: B, param: Param<'_>, type_info: &PgTypeInfo) -> Result<B> {
+    let mut type_info = type_info;
+    while let PgTypeKind::Domain(base) = type_info.kind() {
+        type_info = base;
+    }
+    if let (Some(element), Param::Map(value)) = (RangeElement::of_range(type_info), &param) {
+        return RangeParam::decode(&mut minicbor::Decoder::new(value))?.bind_as(query, element);
+    }
+
+    let query = match (type_info.name(), param) {
+        (_, Param::Text(label)) if matches!(type_info.kind(), PgTypeKind::Enum(_)) => {
+            query.bind(EnumLabel(label, type_info.clone()))
+        }
+
+        ("INT2", Param::Int(value)) => query.bind(convert_int::<i16>(value, type_info)?),
+        ("INT4", Param::Int(value)) => query.bind(convert_int::<i32>(value, type_info)?),
+        ("INT8", Param::Int(value)) => query.bind(convert_int::<i64>(value, type_info)?),
+        ("OID", Param::Int(value)) => query.bind(Oid(convert_int::<u32>(value, type_info)?)),
+
+        ("FLOAT4", Param::Int(value)) => query.bind(value as f32),
+        ("FLOAT4", Param::F64(value)) => query.bind(value as f32),
+        ("FLOAT8", Param::Int(value)) => query.bind(value as f64),
+        ("FLOAT8", Param::F32(value)) => query.bind(value as f64),
+
+        ("NUMERIC", Param::Int(value)) => {
+            query.bind(BigDecimal::from_str(&value.to_string()).map_err(invalid_numeric)?)
+        }
+        ("NUMERIC", Param::F32(value)) => {
+            query.bind(BigDecimal::try_from(value as f64).map_err(invalid_numeric)?)
+        }
+        ("NUMERIC", Param::F64(value)) => {
+            query.bind(BigDecimal::try_from(value).map_err(invalid_numeric)?)
+        }
+        ("NUMERIC", Param::Text(value)) => {
+            query.bind(BigDecimal::from_str(&value).map_err(invalid_numeric)?)
+        }
+
+        ("TEXT" | "VARCHAR" | "BPCHAR" | "NAME", Param::Int(value)) => {
+            query.bind(value.to_string())
+        }
+        ("TEXT" | "VARCHAR" | "BPCHAR" | "NAME", Param::F32(value)) => {
+            query.bind(value.to_string())
+        }
+        ("TEXT" | "VARCHAR" | "BPCHAR" | "NAME", Param::F64(value)) => {
+            query.bind(value.to_string())
+        }
+        ("TEXT" | "VARCHAR" | "BPCHAR" | "NAME", Param::Bool(value)) => {
+            query.bind(value.to_string())
+        }
+
+        ("DATE", Param::Text(value)) => query.bind(Date::parse(&value, DATE_FORMAT)?),
+        ("TIME", Param::Text(value)) => query.bind(Time::parse(&value, TIME_FORMAT)?),
+        ("TIMESTAMP", Param::Text(value)) => {
+            query.bind(PrimitiveDateTime::parse(&value, TIMESTAMP_FORMAT)?)
+        }
+        ("TIMESTAMPTZ", Param::Text(value)) => query.bind(OffsetDateTime::parse(&value, &Rfc3339)?),
+        ("UUID", Param::Text(value)) => query.bind(
+            Uuid::parse_str(&value)
+                .map_err(|e| Error::CborDeValue(format!("invalid uuid: {}", e)))?,
+        ),
+        ("JSON" | "JSONB", Param::Text(value)) => {
+            query.bind(serde_json::from_str::<serde_json::Value>(&value)?)
+        }
+        ("JSON" | "JSONB", Param::Array(value) | Param::Map(value)) => {
+            query.bind(json::decode_json(&mut minicbor::Decoder::new(value))?)
+        }
+
+        (_, Param::Tagged(tagged)) => bind_tagged_typed(query, tagged, type_info)?,
+        // the placeholder is cast to the hinted type
+        (_, Param::Hinted(_, param) | Param::UserTyped(_, param)) => {
+            bind_typed(query, *param, type_info)?
+        }
 
-        Ok(query)
+        (_, param) => bind_param(query, param)?,
+    };
+
+    Ok(query)
+}
+
+/// Bind a parameter as its natural Postgres type
+fn bind_param<'q, B: PgBind<'q>>(query: B, param: Param<'_>) -> Result<B> {
+    let query = match param {
+        Param::Null => query.bind(None::<bool>),
+        Param::Bool(value) => query.bind(value),
+        Param::Int(int) => {
+            if let Ok(value) = i16::try_from(int) {
+                query.bind(value)
+            } else if let Ok(value) = i32::try_from(int) {
+                query.bind(value)
+            } else if let Ok(value) = i64::try_from(int) {
+                query.bind(value)
+            } else {
+                return Err(Param::int_out_of_range(int));
+            }
+        }
+        Param::F32(value) => query.bind(value),
+        Param::F64(value) => query.bind(value),
+        Param::Bytes(value) => query.bind(value),
+        Param::Text(value) => query.bind(value),
+        Param::Array(value) | Param::Map(value) => {
+            query.bind(json::decode_json(&mut minicbor::Decoder::new(value))?)
+        }
+        Param::Set(members) => query.bind(members),
+        Param::Tagged(tagged) => bind_tagged(query, tagged),
+        // the placeholder is cast to the user-defined type
+        Param::UserTyped(name, param) => match (RangeElement::of_range_name(&name), *param) {
+            (Some(element), Param::Map(value)) => {
+                RangeParam::decode(&mut minicbor::Decoder::new(value))?.bind_as(query, element)?
+            }
+            (_, param) => bind_param(query, param)?,
+        },
+        Param::Hinted(hint, param) => query.bind_typed_value(hint.convert(*param)?)?,
+    };
+    Ok(query)
+}
+
+/// Bind a tagged value as its natural Postgres type
+fn bind_tagged<'q, B: PgBind<'q>>(query: B, tagged: TaggedValue) -> B {
+    match tagged {
+        TaggedValue::DateTime(value) => query.bind(value),
+        TaggedValue::Uuid(value) => query.bind(value),
+        TaggedValue::Number(value) => match tag::as_i64(&value) {
+            Some(value) => query.bind(value),
+            None => query.bind(value),
+        },
+    }
+}
+
+/// Bind a tagged value, converting it to the parameter type where needed
+fn bind_tagged_typed<'q, B: PgBind<'q>>(
+    query: B,
+    tagged: TaggedValue,
+    type_info: &PgTypeInfo,
+) -> Result<B> {
+    let query = match (type_info.name(), tagged) {
+        ("TIMESTAMP", TaggedValue::DateTime(value)) => {
+            let value = value.to_offset(UtcOffset::UTC);
+            query.bind(PrimitiveDateTime::new(value.date(), value.time()))
+        }
+        ("DATE", TaggedValue::DateTime(value)) => {
+            query.bind(value.to_offset(UtcOffset::UTC).date())
+        }
+        ("INT2" | "INT4" | "INT8" | "OID", TaggedValue::Number(value)) => {
+            let int = tag::as_i64(&value).ok_or_else(|| {
+                Error::CborDeValue(format!(
+                    "{} is out of range for {}",
+                    value,
+                    type_info.name()
+                ))
+            })?;
+            let out_of_range =
+                || Error::CborDeValue(format!("{} is out of range for {}", int, type_info.name()));
+            match type_info.name() {
+                "INT2" => query.bind(i16::try_from(int).map_err(|_| out_of_range())?),
+                "INT4" => query.bind(i32::try_from(int).map_err(|_| out_of_range())?),
+                "OID" => query.bind(Oid(u32::try_from(int).map_err(|_| out_of_range())?)),
+                _ => query.bind(int),
+            }
+        }
+        ("FLOAT4", TaggedValue::Number(value)) => {
+            query.bind(value.to_string().parse::<f32>().map_err(invalid_numeric)?)
+        }
+        ("FLOAT8", TaggedValue::Number(value)) => {
+            query.bind(value.to_string().parse::<f64>().map_err(invalid_numeric)?)
+        }
+        ("NUMERIC", TaggedValue::Number(value)) => query.bind(value),
+        ("TEXT" | "VARCHAR" | "BPCHAR" | "NAME", tagged) => query.bind(match tagged {
+            TaggedValue::DateTime(value) => value.format(&Rfc3339)?,
+            TaggedValue::Uuid(value) => value.as_hyphenated().to_string(),
+            TaggedValue::Number(value) => value.to_string(),
+        }),
+        (_, tagged) => bind_tagged(query, tagged),
+    };
+    Ok(query)
+}
+
+fn convert_int<T: TryFrom<i128>>(value: i128, type_info: &PgTypeInfo) -> Result<T> {
+    T::try_from(value).map_err(|_| {
+        Error::CborDeValue(format!(
+            "{} is out of range for {}",
+            value,
+            type_info.name()
+        ))
+    })
+}
+
+fn invalid_numeric<E: std::fmt::Display>(e: E) -> Error {
+    Error::CborDeValue(format!("invalid numeric: {}", e))
+}
+
+/// An enum label, bound with the enum type of the parameter
+struct EnumLabel(String, PgTypeInfo);
+
+impl Encode<'_, Postgres> for EnumLabel {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+        <&str as Encode<Postgres>>::encode(self.0.as_str(), buf)
+    }
+
+    fn produces(&self) -> Option<PgTypeInfo> {
+        Some(self.1.clone())
     }
 }
 
-fn pgrow_to_cbor(rows: &[PgRow]) -> Result<Vec<u8>> {
+impl SqlType<Postgres> for EnumLabel {
+    /// the type of a label without its enum; bound labels produce the type of
+    /// their enum instead
+    fn type_info() -> PgTypeInfo {
+        <&str as SqlType<Postgres>>::type_info()
+    }
+}
+
+/// A composite field, captured so it can be encoded by its field type
+struct RecordField(PgValue);
+
+impl SqlType<Postgres> for RecordField {
+    /// fields are only decoded, from values of any type
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("record")
+    }
+
+    fn compatible(_ty: &PgTypeInfo) -> bool {
+        true
+    }
+}
+
+impl Decode<'_, Postgres> for RecordField {
+    fn decode(value: PgValueRef<'_>) -> std::result::Result<Self, sqlx::error::BoxDynError> {
+        Ok(RecordField(ValueRef::to_owned(&value)))
+    }
+}
+
+impl<'q> BindCbor for PgQuery<'q> {
+    fn bind_cbor(self, value: &[u8]) -> Result<Self> {
+        bind_param(self, Param::decode(value)?)
+    }
+}
+
+fn pgrow_to_cbor(rows: &[PgRow], encoding: &EncodingOptions) -> Result<Vec<u8>> {
     let mut buf = Vec::with_capacity(rows.len() * 2);
     let mut out = minicbor::Encoder::new(&mut buf);
 
@@ -125,100 +646,230 @@ fn pgrow_to_cbor(rows: &[PgRow]) -> Result<Vec<u8>> {
 
         for column in row.columns() {
             let value_ref = row.try_get_raw(column.ordinal())?;
-            if value_ref.is_null() {
-                out.null()?;
-                continue;
-            }
+            encode_value(&mut out, column.type_info(), value_ref, encoding)?;
+        }
+    }
 
-            let type_name = column.type_info().name();
-            match type_name {
-                "OID" => {
-                    let oid = <Oid as Decode<Postgres>>::decode(value_ref)?;
-                    out.encode(oid.0)?;
-                }
+    Ok(buf)
+}
 
-                "BOOL" => {
-                    out.encode(<bool as Decode<Postgres>>::decode(value_ref)?)?;
-                }
+/// Encode a single value as CBOR. User-defined types are resolved through the
+/// catalog when the statement is prepared, so enums are encoded as strings,
+/// domains as their base type, and composites as a map of their fields.
+fn encode_value(
+    out: &mut minicbor::Encoder<&mut Vec<u8>>,
+    type_info: &PgTypeInfo,
+    value_ref: PgValueRef<'_>,
+    encoding: &EncodingOptions,
+) -> Result<()> {
+    if value_ref.is_null() {
+        out.null()?;
+        return Ok(());
+    }
 
-                "\"CHAR\"" => {
-                    out.encode(<i8 as Decode<Postgres>>::decode(value_ref)?)?;
-                }
-                "SMALLINT" | "SMALLSERIAL" | "INT2" => {
-                    out.encode(<i16 as Decode<Postgres>>::decode(value_ref)?)?;
-                }
-                "INT" | "SERIAL" | "INT4" => {
-                    out.encode(<i32 as Decode<Postgres>>::decode(value_ref)?)?;
-                }
-                "BIGINT" | "BIGSERIAL" | "INT8" => {
-                    out.encode(<i64 as Decode<Postgres>>::decode(value_ref)?)?;
-                }
+    let type_name = type_info.name();
+    match value_kind(type_name) {
+        ValueKind::Null => {
+            out.null()?;
+        }
 
-                "REAL" | "FLOAT4" => {
-                    out.encode(<f32 as Decode<Postgres>>::decode(value_ref)?)?;
-                }
-                "DOUBLE PRECISION" | "FLOAT8" => {
-                    out.encode(<f64 as Decode<Postgres>>::decode(value_ref)?)?;
+        ValueKind::Bool => {
+            out.encode(<bool as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+
+        ValueKind::Int8 => {
+            out.encode(<i8 as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+        ValueKind::Int16 => {
+            out.encode(<i16 as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+        ValueKind::Int32 => {
+            out.encode(<i32 as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+        ValueKind::Int64 => {
+            out.encode(<i64 as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+        ValueKind::UInt32 => {
+            let oid = <Oid as Decode<Postgres>>::decode(value_ref)?;
+            out.encode(oid.0)?;
+        }
+
+        ValueKind::Float32 => {
+            out.encode(<f32 as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+        ValueKind::Float64 => {
+            out.encode(<f64 as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+
+        ValueKind::Text => {
+            out.encode(<&str as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+
+        ValueKind::Bytes => {
+            out.bytes(<&[u8] as Decode<Postgres>>::decode(value_ref)?)?;
+        }
+
+        ValueKind::Timestamp => {
+            let timestamp = <PrimitiveDateTime as Decode<Postgres>>::decode(value_ref)?;
+            let rfc3339 = timestamp.format(TIMESTAMP_FORMAT)?;
+            out.encode(rfc3339)?;
+        }
+
+        ValueKind::TimestampTz => {
+            let timestamp = <OffsetDateTime as Decode<Postgres>>::decode(value_ref)?;
+            let rfc3339 = timestamp.format(&Rfc3339)?;
+            out.encode(rfc3339)?;
+        }
+
+        ValueKind::Date => {
+            let date = <Date as Decode<Postgres>>::decode(value_ref)?;
+            let value = date.format(DATE_FORMAT)?;
+            out.encode(value)?;
+        }
+
+        ValueKind::Time => {
+            let date = <Time as Decode<Postgres>>::decode(value_ref)?;
+            let value = date.format(TIME_FORMAT)?;
+            out.encode(value)?;
+        }
+
+        _ => match type_name {
+            "NUMERIC" => {
+                out.encode(decode_numeric(value_ref.as_bytes()?)?)?;
+            }
+
+            "UUID" => {
+                let id = <Uuid as Decode<Postgres>>::decode(value_ref)?;
+                let value = id.as_hyphenated().to_string();
+                out.encode(value)?;
+            }
+
+            "JSON" | "JSONB" => {
+                // decoded from the raw bytes, as the column may be typed as a domain
+                let mut bytes = value_ref.as_bytes()?;
+                if type_name == "JSONB" {
+                    match bytes.split_first() {
+                        Some((1, rest)) => bytes = rest,
+                        _ => return Err(Error::Sqlx("unsupported JSONB format version".into())),
+                    }
                 }
+                let json: serde_json::Value = serde_json::from_slice(bytes)?;
+                json::encode_json(out, &json, encoding.json)?;
+            }
 
-                "VARCHAR" | "CHAR" | "TEXT" | "NAME" => {
+            _ => match type_info.kind() {
+                PgTypeKind::Enum(_) => {
                     out.encode(<&str as Decode<Postgres>>::decode(value_ref)?)?;
                 }
 
-                "BYTEA" => {
-                    out.encode(<&[u8] as Decode<Postgres>>::decode(value_ref)?)?;
+                PgTypeKind::Domain(base) => {
+                    encode_value(out, base, value_ref, encoding)?;
                 }
 
-                "TIMESTAMP" => {
-                    let timestamp = <PrimitiveDateTime as Decode<Postgres>>::decode(value_ref)?;
-                    let format =
-                        format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
-                    let rfc3339 = timestamp.format(format)?;
-                    out.encode(rfc3339)?;
+                PgTypeKind::Composite(fields) => {
+                    let mut record = PgRecordDecoder::new(value_ref)?;
+                    out.map(fields.len() as u64)?;
+                    for (name, field_type) in fields.iter() {
+                        let field = record.try_decode::<RecordField>()?;
+                        out.str(name)?;
+                        encode_value(out, field_type, field.0.as_ref(), encoding)?;
+                    }
                 }
 
-                "TIMESTAMPTZ" => {
-                    let timestamp = <OffsetDateTime as Decode<Postgres>>::decode(value_ref)?;
-                    let rfc3339 = timestamp.format(&Rfc3339)?;
-                    out.encode(rfc3339)?;
-                }
+                PgTypeKind::Range(_) => match RangeElement::of_range(type_info) {
+                    Some(element) => range::encode_range(out, element, value_ref.as_bytes()?)?,
+                    None => return Err(Error::DbType(type_name.into())),
+                },
 
-                "DATE" => {
-                    let date = <Date as Decode<Postgres>>::decode(value_ref)?;
-                    let format = format_description!("[year]-[month]-[day]");
-                    let value = date.format(format)?;
-                    out.encode(value)?;
-                }
+                _ => match RangeElement::of_multirange(type_name) {
+                    Some(element) => range::encode_multirange(out, element, value_ref.as_bytes()?)?,
+                    None => return Err(Error::DbType(type_name.into())),
+                },
+            },
+        },
+    }
 
-                "TIME" => {
-                    let date = <Time as Decode<Postgres>>::decode(value_ref)?;
-                    let format = format_description!("[hour]:[minute]:[second]");
-                    let value = date.format(format)?;
-                    out.encode(value)?;
-                }
+    Ok(())
+}
 
-                "UUID" => {
-                    let id = <Uuid as Decode<Postgres>>::decode(value_ref)?;
-                    let value = id.as_hyphenated().to_string();
-                    out.encode(value)?;
-                }
+/// Kind of the value `encode_value` writes for a type
+pub(super) fn value_kind(type_name: &str) -> ValueKind {
+    match type_name {
+        "NULL" | "VOID" => ValueKind::Null,
+        "BOOL" => ValueKind::Bool,
+        "\"CHAR\"" => ValueKind::Int8,
+        "SMALLINT" | "SMALLSERIAL" | "INT2" => ValueKind::Int16,
+        "INT" | "SERIAL" | "INT4" => ValueKind::Int32,
+        "BIGINT" | "BIGSERIAL" | "INT8" => ValueKind::Int64,
+        "OID" => ValueKind::UInt32,
+        "REAL" | "FLOAT4" => ValueKind::Float32,
+        "DOUBLE PRECISION" | "FLOAT8" => ValueKind::Float64,
+        "VARCHAR" | "CHAR" | "TEXT" | "NAME" => ValueKind::Text,
+        "BYTEA" => ValueKind::Bytes,
+        "DATE" => ValueKind::Date,
+        "TIME" => ValueKind::Time,
+        "TIMESTAMP" => ValueKind::Timestamp,
+        "TIMESTAMPTZ" => ValueKind::TimestampTz,
+        _ => ValueKind::Other,
+    }
+}
 
-                "JSON" | "JSONB" => {
-                    let json = <serde_json::Value as Decode<Postgres>>::decode(value_ref)?;
-                    let value = serde_json::to_string(&json)?;
-                    out.encode(value)?;
-                }
+/// Format a `NUMERIC` value from its binary wire format, preserving its scale
+fn decode_numeric(buf: &[u8]) -> Result<String> {
+    const NUMERIC_NEG: u16 = 0x4000;
+    const NUMERIC_NAN: u16 = 0xC000;
+    const NUMERIC_PINF: u16 = 0xD000;
+    const NUMERIC_NINF: u16 = 0xF000;
 
-                "NULL" | "VOID" => {
-                    out.null()?;
-                }
+    if buf.len() < 8 {
+        return Err(Error::Sqlx("unexpected end of numeric value".into()));
+    }
+    let read = |i: usize| u16::from_be_bytes([buf[i * 2], buf[i * 2 + 1]]);
+    let ndigits = read(0) as usize;
+    let weight = read(1) as i16 as isize;
+    let sign = read(2);
+    let dscale = read(3) as usize;
+    if buf.len() < 8 + ndigits * 2 {
+        return Err(Error::Sqlx("unexpected end of numeric value".into()));
+    }
+    let digit = |i: isize| {
+        if i >= 0 && (i as usize) < ndigits {
+            read(4 + i as usize)
+        } else {
+            0
+        }
+    };
 
-                _ => {
-                    return Err(Error::DbType(type_name.into()));
-                }
-            }
+    let mut value = String::new();
+    match sign {
+        NUMERIC_NAN => return Ok("NaN".into()),
+        NUMERIC_PINF => return Ok("Infinity".into()),
+        NUMERIC_NINF => return Ok("-Infinity".into()),
+        NUMERIC_NEG => value.push('-'),
+        _ => {}
+    }
+
+    // digits are base 10000, with `weight` base-10000 digits before the decimal point
+    if weight < 0 {
+        value.push('0');
+    } else {
+        value.push_str(&digit(0).to_string());
+        for i in 1..=weight {
+            value.push_str(&format!("{:04}", digit(i)));
         }
     }
 
-    Ok(buf)
+    if dscale > 0 {
+        let mut fraction = String::with_capacity(dscale + 4);
+        let mut i = weight + 1;
+        while fraction.len() < dscale {
+            fraction.push_str(&format!("{:04}", digit(i)));
+            i += 1;
+        }
+        fraction.truncate(dscale);
+        value.push('.');
+        value.push_str(&fraction);
+    }
+
+    Ok(value)
 }
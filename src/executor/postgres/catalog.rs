@@ -0,0 +1,129 @@
+//! Postgres schema introspection
+//!
+//! Tables and columns are read from `information_schema`, which only shows
+//! what the user has privileges on. Indexes and foreign keys aren't part of
+//! it, so they are read from `pg_index` and `pg_constraint`.
+
+use sqlx::PgConnection;
+
+use crate::{
+    ext::{TableDescription, TableInfo},
+    result::Result,
+};
+
+use super::super::catalog::{self, ColumnRow, ForeignKeyRow, IndexRow};
+
+const SCHEMAS_SQL: &str = r#"
+select nspname::text from pg_namespace
+where nspname not in ('information_schema', 'pg_catalog', 'pg_toast')
+    and nspname not like 'pg\_temp\_%' and nspname not like 'pg\_toast\_temp\_%'
+    and has_schema_privilege(oid, 'usage')
+order by 1
+"#;
+
+const TABLES_SQL: &str = r#"
+select table_schema::text, table_name::text, table_type::text
+from information_schema.tables
+where case when $1::text is null
+    then table_schema not in ('information_schema', 'pg_catalog')
+    else table_schema = $1::text end
+order by 1, 2
+"#;
+
+const TABLE_SQL: &str = r#"
+select table_schema::text, table_name::text, table_type::text
+from information_schema.tables
+where table_schema = coalesce($1::text, current_schema()) and table_name = $2::text
+"#;
+
+const COLUMNS_SQL: &str = r#"
+select column_name::text, ordinal_position::int8,
+    case when data_type in ('ARRAY', 'USER-DEFINED') then udt_name else data_type end::text,
+    is_nullable = 'YES', column_default::text, character_maximum_length::int8,
+    coalesce(numeric_precision, datetime_precision)::int8, numeric_scale::int8
+from information_schema.columns
+where table_schema = $1::text and table_name = $2::text
+order by ordinal_position
+"#;
+
+// indexes on expressions only list the columns they index directly
+const INDEXES_SQL: &str = r#"
+select i.relname::text, x.indisunique, x.indisprimary, a.attname::text
+from pg_index x
+join pg_class i on i.oid = x.indexrelid
+join unnest(x.indkey::int2[]) with ordinality k(attnum, n) on true
+join pg_attribute a on a.attrelid = x.indrelid and a.attnum = k.attnum
+where x.indrelid = format('%I.%I', $1::text, $2::text)::regclass
+order by 1, k.n
+"#;
+
+const FOREIGN_KEYS_SQL: &str = r#"
+select c.conname::text, a.attname::text, fn.nspname::text, f.relname::text, fa.attname::text
+from pg_constraint c
+join unnest(c.conkey, c.confkey) with ordinality k(attnum, fattnum, n) on true
+join pg_attribute a on a.attrelid = c.conrelid and a.attnum = k.attnum
+join pg_attribute fa on fa.attrelid = c.confrelid and fa.attnum = k.fattnum
+join pg_class f on f.oid = c.confrelid
+join pg_namespace fn on fn.oid = f.relnamespace
+where c.contype = 'f' and c.conrelid = format('%I.%I', $1::text, $2::text)::regclass
+order by 1, k.n
+"#;
+
+pub(super) async fn list_schemas(conn: &mut PgConnection) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar(SCHEMAS_SQL).fetch_all(conn).await?)
+}
+
+pub(super) async fn list_tables(
+    conn: &mut PgConnection,
+    schema: Option<&str>,
+) -> Result<Vec<TableInfo>> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(TABLES_SQL)
+        .bind(schema)
+        .fetch_all(conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(schema, name, table_type)| TableInfo {
+            schema,
+            name,
+            kind: catalog::table_kind(&table_type),
+        })
+        .collect())
+}
+
+pub(super) async fn describe_table(
+    conn: &mut PgConnection,
+    schema: Option<&str>,
+    table: &str,
+) -> Result<Option<TableDescription>> {
+    let found: Option<(String, String, String)> = sqlx::query_as(TABLE_SQL)
+        .bind(schema)
+        .bind(table)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let found = match found {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let columns: Vec<ColumnRow> = sqlx::query_as(COLUMNS_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    let indexes: Vec<IndexRow> = sqlx::query_as(INDEXES_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    let foreign_keys: Vec<ForeignKeyRow> = sqlx::query_as(FOREIGN_KEYS_SQL)
+        .bind(&found.0)
+        .bind(&found.1)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(Some(catalog::describe_table(
+        found,
+        columns,
+        indexes,
+        foreign_keys,
+    )))
+}
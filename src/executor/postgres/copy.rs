@@ -0,0 +1,144 @@
+//! Bulk load and export with `COPY`
+//!
+//! Rows are loaded with `COPY ... FROM STDIN (FORMAT binary)`, with values
+//! encoded in the binary format of their column type, converted the same way
+//! statement parameters are converted to the parameter types of a prepared
+//! statement. Query results are exported with `COPY (query) TO STDOUT`.
+
+use futures::TryStreamExt;
+use sqlx::{
+    encode::IsNull,
+    postgres::{PgArgumentBuffer, PgCopyIn, PgTypeInfo},
+    Column, Encode, PgConnection, Postgres, Type as SqlType,
+};
+
+use tokio::sync::mpsc;
+
+use crate::{
+    ext::{BulkInsert, Export, ExportFormat},
+    result::{Error, Result},
+};
+
+use super::{
+    super::{bulk, param::Param},
+    bind_typed, PgBind,
+};
+
+/// signature, flags and header extension length of the binary copy format
+const COPY_HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+/// field count marking the end of the rows
+const COPY_TRAILER: i16 = -1;
+/// size of the chunks of data sent to the server
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Rows encoded in the binary copy format
+#[derive(Default)]
+struct CopyData {
+    buf: Vec<u8>,
+}
+
+impl<'q> PgBind<'q> for CopyData {
+    fn bind<T>(mut self, value: T) -> Self
+    where
+        T: 'q + Send + Encode<'q, Postgres> + SqlType<Postgres>,
+    {
+        // each field is its length, or -1 for null, followed by its value
+        let mut field = PgArgumentBuffer::default();
+        match value.encode_by_ref(&mut field) {
+            IsNull::Yes => self.buf.extend_from_slice(&(-1i32).to_be_bytes()),
+            IsNull::No => {
+                self.buf
+                    .extend_from_slice(&(field.len() as i32).to_be_bytes());
+                self.buf.extend_from_slice(&field);
+            }
+        }
+        self
+    }
+}
+
+/// Insert the rows with `COPY`, returning the number of rows inserted
+pub(super) async fn copy_in(conn: &mut PgConnection, insert: &BulkInsert) -> Result<u64> {
+    let (table, columns) = bulk::target(insert)?;
+    let rows = bulk::decode_rows(&insert.rows, insert.columns.len())?;
+
+    // binary values have to be of the exact column type
+    let select = format!("select {} from {}", columns, table);
+    let describe = sqlx::Executor::describe(&mut *conn, select.as_str()).await?;
+    let types: Vec<PgTypeInfo> = describe
+        .columns()
+        .iter()
+        .map(|column| column.type_info().clone())
+        .collect();
+
+    let copy = format!("copy {} ({}) from stdin (format binary)", table, columns);
+    let mut copy = conn.copy_in_raw(&copy).await?;
+    match send_rows(&mut copy, &rows, &types).await {
+        Ok(()) => Ok(copy.finish().await?),
+        Err(err) => {
+            copy.abort(err.to_string()).await?;
+            Err(err)
+        }
+    }
+}
+
+async fn send_rows(
+    copy: &mut PgCopyIn<&mut PgConnection>,
+    rows: &[Vec<&[u8]>],
+    types: &[PgTypeInfo],
+) -> Result<()> {
+    let mut data = CopyData::default();
+    data.buf.extend_from_slice(COPY_HEADER);
+    for row in rows {
+        data.buf
+            .extend_from_slice(&(row.len() as i16).to_be_bytes());
+        for (value, type_info) in row.iter().zip(types) {
+            data = bind_typed(data, Param::decode(value)?, type_info)?;
+        }
+        if data.buf.len() >= CHUNK_SIZE {
+            copy.send(std::mem::take(&mut data.buf)).await?;
+        }
+    }
+    data.buf.extend_from_slice(&COPY_TRAILER.to_be_bytes());
+    copy.send(data.buf).await?;
+    Ok(())
+}
+
+/// Export the result of a query with `COPY (query) TO STDOUT`
+pub(super) async fn copy_out(
+    conn: &mut PgConnection,
+    export: &Export,
+    chunk_size: usize,
+    chunks: &mpsc::Sender<Result<Vec<u8>>>,
+) -> Result<()> {
+    if export
+        .statement
+        .parameters
+        .as_ref()
+        .is_some_and(|params| !params.is_empty())
+    {
+        return Err(Error::Unsupported("exporting a query with parameters"));
+    }
+    let options = match export.format {
+        ExportFormat::Csv if export.header => "format csv, header true",
+        ExportFormat::Csv => "format csv",
+        ExportFormat::Binary => "format binary",
+    };
+    let query = export.statement.sql.trim_end().trim_end_matches(';');
+    // on a new line, in case the query ends with a comment
+    let copy = format!("copy ({}\n) to stdout ({})", query, options);
+
+    // the server sends a message per row, which are gathered into chunks
+    let mut data = conn.copy_out_raw(&copy).await?;
+    let mut chunk = Vec::with_capacity(chunk_size);
+    while let Some(bytes) = data.try_next().await? {
+        chunk.extend_from_slice(&bytes);
+        if chunk.len() >= chunk_size && chunks.send(Ok(std::mem::take(&mut chunk))).await.is_err() {
+            // the export was dropped
+            return Ok(());
+        }
+    }
+    if !chunk.is_empty() {
+        let _ = chunks.send(Ok(chunk)).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Postgres column metadata
+//!
+//! The statement description gives the name, type and nullability of each
+//! column. sqlx 0.6 doesn't expose the table OID and column number the server
+//! sends with it, so the source of a column and the type details stored with
+//! it in `pg_attribute` can't be looked up, and aren't reported.
+
+use sqlx::{Column as _, PgConnection, TypeInfo};
+
+use crate::{ext::ColumnMetadata, result::Result};
+
+pub(super) async fn describe_columns(
+    conn: &mut PgConnection,
+    sql: &str,
+) -> Result<Vec<ColumnMetadata>> {
+    let describe = sqlx::Executor::describe(&mut *conn, sql).await?;
+    let columns = describe
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| ColumnMetadata {
+            ordinal: column.ordinal() as u32,
+            name: column.name().into(),
+            db_type: column.type_info().name().into(),
+            nullable: describe.nullable(i),
+            ..Default::default()
+        })
+        .collect();
+    Ok(columns)
+}
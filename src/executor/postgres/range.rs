@@ -0,0 +1,402 @@
+//! Postgres range and multirange types
+//!
+//! Ranges are encoded as a CBOR map with the keys `empty`, `lower`, `upper`,
+//! `lower_inc` and `upper_inc`, mirroring the Postgres range functions of the
+//! same names. Unbounded sides are encoded as `null`. Multiranges are encoded
+//! as a CBOR array of ranges.
+
+use std::{ops::Bound, str::FromStr};
+
+use minicbor::{data::Type, Decoder, Encoder};
+use sqlx::{
+    encode::IsNull,
+    postgres::{
+        types::{Oid, PgRange},
+        PgArgumentBuffer, PgTypeInfo, PgTypeKind,
+    },
+    types::BigDecimal,
+    Encode, Postgres, Type as SqlType, TypeInfo,
+};
+use time::{
+    format_description::well_known::Rfc3339, macros::date, Date, Duration, OffsetDateTime,
+    PrimitiveDateTime, Time,
+};
+
+use crate::result::{Error, Result};
+
+use super::{
+    super::datetime::{DATE_FORMAT, TIMESTAMP_FORMAT},
+    decode_numeric, PgBind,
+};
+
+const RANGE_EMPTY: u8 = 0x01;
+const RANGE_LB_INC: u8 = 0x02;
+const RANGE_UB_INC: u8 = 0x04;
+const RANGE_LB_INF: u8 = 0x08;
+const RANGE_UB_INF: u8 = 0x10;
+
+/// Postgres epoch for `DATE` and `TIMESTAMP` values in binary format
+const PG_EPOCH: Date = date!(2000 - 01 - 01);
+
+/// Element type of a range
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum RangeElement {
+    Int4,
+    Int8,
+    Numeric,
+    Date,
+    Timestamp,
+    TimestampTz,
+}
+
+impl RangeElement {
+    fn from_element_name(name: &str) -> Option<Self> {
+        match name {
+            "INT" | "SERIAL" | "INT4" => Some(Self::Int4),
+            "BIGINT" | "BIGSERIAL" | "INT8" => Some(Self::Int8),
+            "NUMERIC" => Some(Self::Numeric),
+            "DATE" => Some(Self::Date),
+            "TIMESTAMP" => Some(Self::Timestamp),
+            "TIMESTAMPTZ" => Some(Self::TimestampTz),
+            _ => None,
+        }
+    }
+
+    /// Element type of a range type, built-in or user-defined
+    pub(super) fn of_range(type_info: &PgTypeInfo) -> Option<Self> {
+        match type_info.kind() {
+            PgTypeKind::Range(element) => Self::from_element_name(element.name()),
+            _ => None,
+        }
+    }
+
+    /// Element type of a built-in range type, by the name a type hint gives it
+    pub(super) fn of_range_name(type_name: &str) -> Option<Self> {
+        match type_name.to_ascii_lowercase().as_str() {
+            "int4range" => Some(Self::Int4),
+            "int8range" => Some(Self::Int8),
+            "numrange" => Some(Self::Numeric),
+            "daterange" => Some(Self::Date),
+            "tsrange" => Some(Self::Timestamp),
+            "tstzrange" => Some(Self::TimestampTz),
+            _ => None,
+        }
+    }
+
+    /// Element type of a built-in multirange type (Postgres 14+)
+    pub(super) fn of_multirange(type_name: &str) -> Option<Self> {
+        match type_name {
+            "int4multirange" => Some(Self::Int4),
+            "int8multirange" => Some(Self::Int8),
+            "nummultirange" => Some(Self::Numeric),
+            "datemultirange" => Some(Self::Date),
+            "tsmultirange" => Some(Self::Timestamp),
+            "tstzmultirange" => Some(Self::TimestampTz),
+            _ => None,
+        }
+    }
+}
+
+/// Encode a range value from its binary wire format
+pub(super) fn encode_range(
+    out: &mut Encoder<&mut Vec<u8>>,
+    element: RangeElement,
+    buf: &[u8],
+) -> Result<()> {
+    let mut buf = buf;
+    let flags = read_u8(&mut buf)?;
+    let empty = flags & RANGE_EMPTY != 0;
+
+    out.map(5)?;
+    out.str("empty")?.bool(empty)?;
+
+    out.str("lower")?;
+    if empty || flags & RANGE_LB_INF != 0 {
+        out.null()?;
+    } else {
+        let value = read_element(&mut buf)?;
+        encode_element(out, element, value)?;
+    }
+
+    out.str("upper")?;
+    if empty || flags & RANGE_UB_INF != 0 {
+        out.null()?;
+    } else {
+        let value = read_element(&mut buf)?;
+        encode_element(out, element, value)?;
+    }
+
+    out.str("lower_inc")?.bool(flags & RANGE_LB_INC != 0)?;
+    out.str("upper_inc")?.bool(flags & RANGE_UB_INC != 0)?;
+    Ok(())
+}
+
+/// Encode a multirange value from its binary wire format
+pub(super) fn encode_multirange(
+    out: &mut Encoder<&mut Vec<u8>>,
+    element: RangeElement,
+    buf: &[u8],
+) -> Result<()> {
+    let mut buf = buf;
+    let count = read_i32(&mut buf)?;
+    out.array(count.max(0) as u64)?;
+    for _ in 0..count {
+        let range = read_element(&mut buf)?;
+        encode_range(out, element, range)?;
+    }
+    Ok(())
+}
+
+fn encode_element(
+    out: &mut Encoder<&mut Vec<u8>>,
+    element: RangeElement,
+    buf: &[u8],
+) -> Result<()> {
+    let mut buf = buf;
+    match element {
+        RangeElement::Int4 => {
+            out.i32(read_i32(&mut buf)?)?;
+        }
+        RangeElement::Int8 => {
+            out.i64(read_i64(&mut buf)?)?;
+        }
+        RangeElement::Numeric => {
+            out.str(&decode_numeric(buf)?)?;
+        }
+        RangeElement::Date => match read_i32(&mut buf)? {
+            i32::MAX => {
+                out.str("infinity")?;
+            }
+            i32::MIN => {
+                out.str("-infinity")?;
+            }
+            days => {
+                let date = PG_EPOCH
+                    .checked_add(Duration::days(days as i64))
+                    .ok_or_else(out_of_range)?;
+                out.str(&date.format(DATE_FORMAT)?)?;
+            }
+        },
+        RangeElement::Timestamp | RangeElement::TimestampTz => match read_i64(&mut buf)? {
+            i64::MAX => {
+                out.str("infinity")?;
+            }
+            i64::MIN => {
+                out.str("-infinity")?;
+            }
+            micros => {
+                let timestamp = PrimitiveDateTime::new(PG_EPOCH, Time::MIDNIGHT)
+                    .checked_add(Duration::microseconds(micros))
+                    .ok_or_else(out_of_range)?;
+                if element == RangeElement::TimestampTz {
+                    out.str(&timestamp.assume_utc().format(&Rfc3339)?)?;
+                } else {
+                    out.str(&timestamp.format(TIMESTAMP_FORMAT)?)?;
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Error for a date beyond the years 9999 BC to 9999 AD, which can't be
+/// formatted
+fn out_of_range() -> Error {
+    Error::Sqlx("range bound is outside the years 9999 BC to 9999 AD".into())
+}
+
+fn read_u8(buf: &mut &[u8]) -> Result<u8> {
+    let (value, rest) = buf
+        .split_first()
+        .ok_or_else(|| Error::Sqlx("unexpected end of range value".into()))?;
+    *buf = rest;
+    Ok(*value)
+}
+
+fn read_array<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N]> {
+    if buf.len() < N {
+        return Err(Error::Sqlx("unexpected end of range value".into()));
+    }
+    let (value, rest) = buf.split_at(N);
+    *buf = rest;
+    Ok(value.try_into().unwrap())
+}
+
+fn read_i32(buf: &mut &[u8]) -> Result<i32> {
+    read_array(buf).map(i32::from_be_bytes)
+}
+
+fn read_i64(buf: &mut &[u8]) -> Result<i64> {
+    read_array(buf).map(i64::from_be_bytes)
+}
+
+/// Read a length-prefixed element
+fn read_element<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = read_i32(buf)?;
+    let len =
+        usize::try_from(len).map_err(|_| Error::Sqlx("unexpected null in range value".into()))?;
+    if buf.len() < len {
+        return Err(Error::Sqlx("unexpected end of range value".into()));
+    }
+    let (value, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(value)
+}
+
+/// A range bound value decoded from CBOR
+#[derive(Debug)]
+enum BoundValue {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// A range parameter decoded from a CBOR map
+#[derive(Debug)]
+pub(super) struct RangeParam {
+    empty: bool,
+    lower: Option<BoundValue>,
+    upper: Option<BoundValue>,
+    lower_inc: bool,
+    upper_inc: bool,
+}
+
+impl RangeParam {
+    /// Decode a range from a CBOR map. Omitted inclusivity flags default to
+    /// the canonical Postgres form `[lower,upper)`.
+    pub(super) fn decode(decoder: &mut Decoder) -> Result<Self> {
+        let mut range = RangeParam {
+            empty: false,
+            lower: None,
+            upper: None,
+            lower_inc: true,
+            upper_inc: false,
+        };
+        let mut remaining = decoder.map()?;
+        loop {
+            match remaining {
+                Some(0) => break,
+                Some(n) => remaining = Some(n - 1),
+                None if decoder.datatype()? == Type::Break => break,
+                None => {}
+            }
+            match decoder.str()? {
+                "empty" => range.empty = decoder.bool()?,
+                "lower" => range.lower = decode_bound(decoder)?,
+                "upper" => range.upper = decode_bound(decoder)?,
+                "lower_inc" => range.lower_inc = decoder.bool()?,
+                "upper_inc" => range.upper_inc = decoder.bool()?,
+                key => return Err(Error::CborDeValue(format!("unknown range key `{}`", key))),
+            }
+        }
+        Ok(range)
+    }
+
+    fn bounds<T>(
+        &self,
+        convert: impl Fn(&BoundValue) -> Result<T>,
+    ) -> Result<(Bound<T>, Bound<T>)> {
+        let bound = |value: &Option<BoundValue>, inclusive: bool| -> Result<Bound<T>> {
+            Ok(match value {
+                None => Bound::Unbounded,
+                Some(value) if inclusive => Bound::Included(convert(value)?),
+                Some(value) => Bound::Excluded(convert(value)?),
+            })
+        };
+        Ok((
+            bound(&self.lower, self.lower_inc)?,
+            bound(&self.upper, self.upper_inc)?,
+        ))
+    }
+
+    /// Bind the range as a range of the given element type, that of the
+    /// hinted range type or of a column
+    pub(super) fn bind_as<'q, B: PgBind<'q>>(self, query: B, element: RangeElement) -> Result<B> {
+        if self.empty {
+            // the empty range has no element values on the wire
+            return Ok(query.bind(UntypedRange(RANGE_EMPTY)));
+        }
+        let query = match element {
+            RangeElement::Int4 => query.bind(PgRange::from(self.bounds(|value| match value {
+                BoundValue::Int(value) => Ok(*value as i32),
+                _ => Err(Error::CborDeValue("expected integer range bound".into())),
+            })?)),
+            RangeElement::Int8 => query.bind(PgRange::from(self.bounds(|value| match value {
+                BoundValue::Int(value) => Ok(*value),
+                _ => Err(Error::CborDeValue("expected integer range bound".into())),
+            })?)),
+            RangeElement::Numeric => query.bind(PgRange::from(self.bounds(|value| {
+                match value {
+                    BoundValue::Int(value) => Ok(BigDecimal::from(*value)),
+                    BoundValue::Float(value) => BigDecimal::try_from(*value)
+                        .map_err(|_| Error::CborDeValue(format!("invalid numeric `{}`", value))),
+                    BoundValue::Text(text) => BigDecimal::from_str(text)
+                        .map_err(|_| Error::CborDeValue(format!("invalid numeric `{}`", text))),
+                }
+            })?)),
+            RangeElement::Date => {
+                query.bind(PgRange::from(self.bounds(|value| {
+                    Ok(Date::parse(bound_text(value)?, DATE_FORMAT)?)
+                })?))
+            }
+            RangeElement::Timestamp => query.bind(PgRange::from(self.bounds(|value| {
+                Ok(PrimitiveDateTime::parse(
+                    bound_text(value)?,
+                    TIMESTAMP_FORMAT,
+                )?)
+            })?)),
+            RangeElement::TimestampTz => {
+                query.bind(PgRange::from(self.bounds(|value| {
+                    Ok(OffsetDateTime::parse(bound_text(value)?, &Rfc3339)?)
+                })?))
+            }
+        };
+        Ok(query)
+    }
+}
+
+fn decode_bound(decoder: &mut Decoder) -> Result<Option<BoundValue>> {
+    let datatype = decoder.datatype()?;
+    let value = match datatype {
+        Type::Null | Type::Undefined => {
+            decoder.skip()?;
+            return Ok(None);
+        }
+        Type::U8
+        | Type::U16
+        | Type::U32
+        | Type::U64
+        | Type::I8
+        | Type::I16
+        | Type::I32
+        | Type::I64
+        | Type::Int => BoundValue::Int(decoder.i64()?),
+        Type::F16 | Type::F32 | Type::F64 => BoundValue::Float(decoder.f64()?),
+        Type::String => BoundValue::Text(decoder.str()?.to_string()),
+        _ => return Err(Error::CborDeType(datatype)),
+    };
+    Ok(Some(value))
+}
+
+fn bound_text(value: &BoundValue) -> Result<&str> {
+    match value {
+        BoundValue::Text(text) => Ok(text),
+        _ => Err(Error::CborDeValue("expected string range bound".into())),
+    }
+}
+
+/// The empty range, bound with an unspecified type
+struct UntypedRange(u8);
+
+impl SqlType<Postgres> for UntypedRange {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_oid(Oid(0))
+    }
+}
+
+impl Encode<'_, Postgres> for UntypedRange {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+        buf.push(self.0);
+        IsNull::No
+    }
+}
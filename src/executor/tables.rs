@@ -0,0 +1,289 @@
+//! Tables referenced by a statement
+//!
+//! Statements are scanned like placeholders are, skipping literals and
+//! comments, and split into words, quoted identifiers and punctuation. The
+//! names following `from`, `join`, `into`, `update` and the like are taken as
+//! tables. This is no parser: common table expressions and some aliases come
+//! out as tables too, so the names found are a superset of the tables, unless
+//! the statement reaches them through views, functions or triggers.
+
+use super::placeholder::{skipped_len, Dialect};
+
+/// words followed by a table name
+const INTRODUCERS: &[&str] = &[
+    "from", "join", "into", "update", "table", "using", "delete", "insert", "truncate", "merge",
+];
+
+/// words that may come between an introducer and the table name
+const FILLERS: &[&str] = &[
+    "from",
+    "into",
+    "table",
+    "only",
+    "if",
+    "not",
+    "exists",
+    "ignore",
+    "low_priority",
+    "quick",
+    "lateral",
+    "top",
+];
+
+/// words of statements, or functions, that change data
+const WRITES: &[&str] = &[
+    "insert", "update", "delete", "merge", "into", "call", "exec", "execute", "nextval", "setval",
+    "lock",
+];
+
+enum Token<'a> {
+    Word(&'a str),
+    /// quoted identifier, without its quotes
+    Quoted(String),
+    Punct(char),
+}
+
+impl Token<'_> {
+    /// The table name of a word or quoted identifier
+    fn name(&self) -> Option<String> {
+        match self {
+            Token::Word(word) => Some(word.to_lowercase()),
+            Token::Quoted(name) => Some(name.to_lowercase()),
+            Token::Punct(_) => None,
+        }
+    }
+}
+
+/// Where the scan is, relative to table names
+#[derive(Clone, Copy)]
+enum State {
+    Other,
+    /// expecting a table name, `in_from` when it's an item of a `from` list
+    Name {
+        in_from: bool,
+    },
+    /// after a table name of a `from` list, which may be followed by an
+    /// alias and a comma
+    AfterName {
+        alias: bool,
+    },
+}
+
+/// Whether a statement only reads: a `select`, or `with` query, with no
+/// word of a statement or function that changes data
+pub(crate) fn is_read_only(sql: &str, dialect: Dialect) -> bool {
+    let tokens = tokens(sql, dialect);
+    let first = tokens.iter().find_map(|token| match token {
+        Token::Word(word) => Some(*word),
+        _ => None,
+    });
+    first.is_some_and(|w| w.eq_ignore_ascii_case("select") || w.eq_ignore_ascii_case("with"))
+        && !tokens.iter().any(|token| match token {
+            Token::Word(word) => WRITES.iter().any(|w| w.eq_ignore_ascii_case(word)),
+            _ => false,
+        })
+}
+
+/// Names of the tables a statement refers to, as `table_name` normalizes
+/// them, without duplicates
+pub(crate) fn table_names(sql: &str, dialect: Dialect) -> Vec<String> {
+    let tokens = tokens(sql, dialect);
+    let mut names: Vec<String> = Vec::new();
+    let mut state = State::Other;
+    // states to return to at the end of each parenthesis
+    let mut outer = Vec::new();
+    let is_word = |word: &str, words: &[&str]| words.iter().any(|w| w.eq_ignore_ascii_case(word));
+    let mut i = 0;
+    while i < tokens.len() {
+        match (&tokens[i], state) {
+            (Token::Punct('('), _) => {
+                outer.push(match state {
+                    State::Name { in_from: true } => State::AfterName { alias: false },
+                    _ => State::Other,
+                });
+                state = State::Other;
+            }
+            (Token::Punct(')'), _) => state = outer.pop().unwrap_or(State::Other),
+            (Token::Word(word), State::Name { .. }) if is_word(word, FILLERS) => {
+                if word.eq_ignore_ascii_case("from") {
+                    state = State::Name { in_from: true };
+                }
+            }
+            (Token::Word(_) | Token::Quoted(_), State::Name { in_from }) => {
+                // the last part of a qualified name
+                let mut last = i;
+                while let (Some(Token::Punct('.')), Some(Token::Word(_) | Token::Quoted(_))) =
+                    (tokens.get(last + 1), tokens.get(last + 2))
+                {
+                    last += 2;
+                }
+                if let Some(name) = tokens[last].name() {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                i = last;
+                state = if in_from {
+                    State::AfterName { alias: false }
+                } else {
+                    State::Other
+                };
+            }
+            (Token::Punct(','), State::AfterName { .. }) => state = State::Name { in_from: true },
+            (Token::Word(word), _) if is_word(word, INTRODUCERS) => {
+                state = State::Name {
+                    in_from: word.eq_ignore_ascii_case("from"),
+                }
+            }
+            (Token::Word(word), State::AfterName { alias: false })
+                if word.eq_ignore_ascii_case("as") => {}
+            (Token::Word(_) | Token::Quoted(_), State::AfterName { alias: false }) => {
+                state = State::AfterName { alias: true }
+            }
+            _ => state = State::Other,
+        }
+        i += 1;
+    }
+    names
+}
+
+/// Normalized name of a table, as written in a statement or request: the
+/// last part of a qualified name, lowercased
+pub(crate) fn table_name(name: &str) -> String {
+    name.rsplit('.').next().unwrap_or(name).to_lowercase()
+}
+
+fn tokens(sql: &str, dialect: Dialect) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    while let Some(c) = sql[offset..].chars().next() {
+        let rest = &sql[offset..];
+        let skip = skipped_len(rest, dialect);
+        if skip > 0 {
+            if let Some(name) = quoted_identifier(&rest[..skip], dialect) {
+                tokens.push(Token::Quoted(name));
+            }
+            offset += skip;
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let word_len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(&rest[..word_len]));
+            offset += word_len;
+            continue;
+        }
+        if !c.is_whitespace() {
+            tokens.push(Token::Punct(c));
+        }
+        offset += c.len_utf8();
+    }
+    tokens
+}
+
+/// The name of a quoted identifier, or none if `quoted` is a literal or
+/// comment
+fn quoted_identifier(quoted: &str, dialect: Dialect) -> Option<String> {
+    let close = match (quoted.chars().next()?, dialect) {
+        ('"', Dialect::Postgres | Dialect::Mssql) => '"',
+        ('`', Dialect::MySql) => '`',
+        ('[', Dialect::Mssql) => ']',
+        _ => return None,
+    };
+    let inner = quoted[1..].strip_suffix(close)?;
+    let doubled = format!("{}{}", close, close);
+    Some(inner.replace(&doubled, &close.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qualified_and_quoted_names() {
+        assert_eq!(
+            table_names(
+                r#"select * from "Sales"."Orders" o join public.items i on i.order_id = o.id"#,
+                Dialect::Postgres
+            ),
+            ["orders", "items"]
+        );
+        assert_eq!(
+            table_names("select * from `shop`.`order``s`", Dialect::MySql),
+            ["order`s"]
+        );
+        assert_eq!(
+            table_names("select * from [dbo].[Line Items]", Dialect::Mssql),
+            ["line items"]
+        );
+        assert_eq!(table_name("Sales.Orders"), "orders");
+    }
+
+    #[test]
+    fn common_table_expressions() {
+        let sql = "with recent as (select * from orders where placed > now() - interval '1 day')
+            select * from recent join customers c on c.id = recent.customer_id";
+        // the name of the expression comes out as a table too
+        assert_eq!(
+            table_names(sql, Dialect::Postgres),
+            ["orders", "recent", "customers"]
+        );
+        assert!(is_read_only(sql, Dialect::Postgres));
+
+        let sql = "with gone as (delete from orders returning *) select count(*) from gone";
+        assert_eq!(table_names(sql, Dialect::Postgres), ["orders", "gone"]);
+        assert!(!is_read_only(sql, Dialect::Postgres));
+    }
+
+    #[test]
+    fn select_into() {
+        let sql = "select * into archive from orders where placed < '2020-01-01'";
+        assert_eq!(table_names(sql, Dialect::Mssql), ["archive", "orders"]);
+        assert!(!is_read_only(sql, Dialect::Mssql));
+    }
+
+    #[test]
+    fn delete_using() {
+        let sql = "delete from only orders using customers c where c.id = orders.customer_id";
+        assert_eq!(table_names(sql, Dialect::Postgres), ["orders", "customers"]);
+        assert!(!is_read_only(sql, Dialect::Postgres));
+    }
+
+    #[test]
+    fn from_lists() {
+        assert_eq!(
+            table_names(
+                "select * from a, b as x, c y, (select * from d) z where a.id = x.id",
+                Dialect::Postgres
+            ),
+            ["a", "b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn literals_and_comments_are_skipped() {
+        let sql = "select 'from fake' as note, \"update\" /* join ghost */ from real -- delete from other";
+        assert_eq!(table_names(sql, Dialect::Postgres), ["real"]);
+        assert!(is_read_only(sql, Dialect::Postgres));
+
+        let sql = "select * from real # insert into other";
+        assert_eq!(table_names(sql, Dialect::MySql), ["real"]);
+        assert!(is_read_only(sql, Dialect::MySql));
+    }
+
+    #[test]
+    fn writes_are_not_read_only() {
+        for sql in [
+            "insert into t values (1)",
+            "update t set a = 1",
+            "select nextval('s')",
+            "exec refresh_totals",
+            "with x as (select 1) insert into t select * from x",
+            "select * from t for update",
+        ] {
+            assert!(!is_read_only(sql, Dialect::Postgres), "{}", sql);
+        }
+        assert!(!is_read_only("", Dialect::Postgres));
+    }
+}
@@ -0,0 +1,223 @@
+//! Tagged CBOR parameter values
+//!
+//! Parameters carrying one of the standard CBOR tags are decoded to the value
+//! they describe, so they can be bound as the native database type:
+//!
+//! - 0: date/time string (RFC 3339)
+//! - 1: epoch-based date/time, in seconds
+//! - 2, 3: positive and negative bignums
+//! - 4: decimal fraction, `[exponent, mantissa]`
+//! - 37: UUID, as 16 bytes
+
+use std::str::FromStr;
+
+use minicbor::{
+    data::{Tag, Type},
+    Decoder,
+};
+use num_traits::ToPrimitive;
+use sqlx::types::BigDecimal;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::result::{Error, Result};
+
+/// CBOR tag for a UUID
+const TAG_UUID: u64 = 37;
+
+/// Largest decimal fraction exponent, and the largest bignum in bytes (about
+/// as many decimal digits). No database holds an exact number this large
+/// except Postgres `NUMERIC`, and larger values are costly to convert.
+const MAX_EXPONENT: i64 = 1000;
+const MAX_BIGNUM_BYTES: usize = 416;
+
+/// A value decoded from a tagged CBOR item
+#[derive(Debug)]
+pub(crate) enum TaggedValue {
+    DateTime(OffsetDateTime),
+    Uuid(Uuid),
+    Number(BigDecimal),
+}
+
+impl TaggedValue {
+    /// Decode the item following a tag
+    pub(crate) fn decode(tag: Tag, decoder: &mut Decoder) -> Result<Self> {
+        let value = match tag {
+            Tag::DateTime => {
+                TaggedValue::DateTime(OffsetDateTime::parse(decoder.str()?, &Rfc3339)?)
+            }
+            Tag::Timestamp => {
+                let nanos = match decoder.datatype()? {
+                    Type::F16 | Type::F32 | Type::F64 => (decoder.f64()? * 1e9) as i128,
+                    _ => i128::from(decoder.int()?) * 1_000_000_000,
+                };
+                TaggedValue::DateTime(
+                    OffsetDateTime::from_unix_timestamp_nanos(nanos)
+                        .map_err(|e| Error::CborDeValue(e.to_string()))?,
+                )
+            }
+            Tag::PosBignum | Tag::NegBignum => {
+                let digits = bignum_digits(tag, decoder.bytes()?)?;
+                TaggedValue::Number(parse_decimal(&digits)?)
+            }
+            Tag::Decimal => {
+                if decoder.array()? != Some(2) {
+                    return Err(Error::CborDeValue(
+                        "a decimal fraction must be an array of two items".into(),
+                    ));
+                }
+                let exponent = i64::try_from(decoder.int()?)
+                    .ok()
+                    .filter(|exponent| exponent.unsigned_abs() <= MAX_EXPONENT as u64)
+                    .ok_or_else(|| Error::CborDeValue("decimal exponent out of range".into()))?;
+                let mantissa = match decoder.datatype()? {
+                    Type::Tag => match decoder.tag()? {
+                        tag @ (Tag::PosBignum | Tag::NegBignum) => {
+                            bignum_digits(tag, decoder.bytes()?)?
+                        }
+                        tag => return Err(unsupported_tag(tag)),
+                    },
+                    _ => i128::from(decoder.int()?).to_string(),
+                };
+                TaggedValue::Number(parse_decimal(&format!("{}E{}", mantissa, exponent))?)
+            }
+            Tag::Unassigned(TAG_UUID) => TaggedValue::Uuid(
+                Uuid::from_slice(decoder.bytes()?)
+                    .map_err(|e| Error::CborDeValue(format!("invalid uuid: {}", e)))?,
+            ),
+            tag => return Err(unsupported_tag(tag)),
+        };
+        Ok(value)
+    }
+}
+
+/// Whether a decimal is an integer that fits in an `i64`
+pub(crate) fn as_i64(value: &BigDecimal) -> Option<i64> {
+    if value.is_integer() {
+        value.to_i64()
+    } else {
+        None
+    }
+}
+
+pub(crate) fn unsupported_tag(tag: Tag) -> Error {
+    Error::CborDeValue(format!("unsupported CBOR tag {:?}", tag))
+}
+
+/// Decimal digits of a bignum, whose bytes are a big-endian magnitude. A
+/// negative bignum `n` represents `-1 - n`.
+fn bignum_digits(tag: Tag, bytes: &[u8]) -> Result<String> {
+    if bytes.len() > MAX_BIGNUM_BYTES {
+        return Err(Error::CborDeValue("bignum out of range".into()));
+    }
+    let mut magnitude: Vec<u8> = bytes.to_vec();
+    if tag == Tag::NegBignum {
+        // add one to the magnitude
+        let mut carry = true;
+        for byte in magnitude.iter_mut().rev() {
+            let (sum, overflow) = byte.overflowing_add(carry as u8);
+            *byte = sum;
+            carry = overflow;
+            if !carry {
+                break;
+            }
+        }
+        if carry {
+            magnitude.insert(0, 1);
+        }
+    }
+
+    // repeated division by 10
+    let mut digits = Vec::new();
+    while magnitude.iter().any(|byte| *byte != 0) {
+        let mut remainder = 0u32;
+        for byte in magnitude.iter_mut() {
+            let value = (remainder << 8) | *byte as u32;
+            *byte = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    if tag == Tag::NegBignum {
+        digits.push(b'-');
+    }
+    digits.reverse();
+    Ok(String::from_utf8(digits).unwrap())
+}
+
+fn parse_decimal(value: &str) -> Result<BigDecimal> {
+    BigDecimal::from_str(value)
+        .map_err(|e| Error::CborDeValue(format!("invalid decimal `{}`: {}", value, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use minicbor::Encoder;
+
+    use super::*;
+
+    fn decode(encode: impl FnOnce(&mut Encoder<&mut Vec<u8>>)) -> Result<TaggedValue> {
+        let mut buf = Vec::new();
+        encode(&mut Encoder::new(&mut buf));
+        let mut decoder = Decoder::new(&buf);
+        let tag = decoder.tag()?;
+        TaggedValue::decode(tag, &mut decoder)
+    }
+
+    fn decimal(exponent: i64, mantissa: i64) -> Result<TaggedValue> {
+        decode(|e| {
+            e.tag(Tag::Decimal)
+                .unwrap()
+                .array(2)
+                .unwrap()
+                .i64(exponent)
+                .unwrap()
+                .i64(mantissa)
+                .unwrap();
+        })
+    }
+
+    fn number(value: Result<TaggedValue>) -> BigDecimal {
+        match value.unwrap() {
+            TaggedValue::Number(number) => number,
+            value => panic!("not a number: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn decimal_fractions() {
+        assert_eq!(number(decimal(-2, 27315)).to_string(), "273.15");
+        assert_eq!(as_i64(&number(decimal(3, 12))), Some(12_000));
+        assert_eq!(as_i64(&number(decimal(-1, 15))), None);
+        assert_eq!(as_i64(&number(decimal(1, i64::MAX))), None);
+    }
+
+    #[test]
+    fn huge_exponents_are_rejected() {
+        for exponent in [i64::MAX, i64::MIN, MAX_EXPONENT + 1, -MAX_EXPONENT - 1] {
+            assert!(matches!(decimal(exponent, 1), Err(Error::CborDeValue(_))));
+        }
+        assert!(decimal(MAX_EXPONENT, 1).is_ok());
+        assert!(decimal(-MAX_EXPONENT, 1).is_ok());
+    }
+
+    #[test]
+    fn bignums() {
+        let value = number(decode(|e| {
+            e.tag(Tag::NegBignum).unwrap().bytes(&[1, 0]).unwrap();
+        }));
+        assert_eq!(value.to_string(), "-257");
+        assert!(matches!(
+            decode(|e| {
+                e.tag(Tag::PosBignum)
+                    .unwrap()
+                    .bytes(&[0xff; MAX_BIGNUM_BYTES + 1])
+                    .unwrap();
+            }),
+            Err(Error::CborDeValue(_))
+        ));
+    }
+}
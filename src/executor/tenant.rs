@@ -0,0 +1,200 @@
+//! Routing of statements to a tenant
+//!
+//! A statement's `database` names the tenant whose data it works on. The
+//! connection is switched to the tenant before the statement runs, and pooled
+//! connections are left as they were, even when the statement is dropped
+//! before it's done:
+//!
+//! - Postgres runs the statement in a transaction, with the tenant's schema
+//!   first in its local `search_path`, so names not found in it still resolve
+//!   in the schemas shared by all tenants. The setting ends with the
+//!   transaction, which a dropped statement rolls back.
+//! - MySQL and SQL Server `USE` the tenant's database, in the same batch that
+//!   finds the database the connection is in, and switch back to it when the
+//!   statement is done. A connection that isn't switched back, because the
+//!   statement was dropped or switching failed, is detached from the pool and
+//!   closed. MySQL can't switch a connection back to no database, so its uri
+//!   must name one.
+
+use std::ops::{Deref, DerefMut};
+
+use sqlx::{
+    any::AnyKind, pool::PoolConnection, Any, AnyConnection, Connection, Executor, Row, Transaction,
+};
+use tracing::error;
+
+use crate::result::{Error, Result};
+
+/// Pooled connection checked out for a call, which goes back to the pool when
+/// it's dropped, unless it's still switched to a MySQL or SQL Server tenant
+pub(crate) struct Checkout {
+    conn: Option<PoolConnection<Any>>,
+    /// whether the connection may be in a tenant's database
+    away: bool,
+}
+
+/// Connection switched to the tenant of a statement
+pub(crate) enum Switched<'c> {
+    /// connection of no tenant
+    Connection(&'c mut AnyConnection),
+    /// Postgres transaction with the tenant's schema first in its search_path
+    Transaction(Box<Transaction<'c, Any>>),
+    /// MySQL or SQL Server connection in the tenant's database, with the
+    /// database to switch back to
+    Database {
+        conn: &'c mut AnyConnection,
+        home: String,
+        away: &'c mut bool,
+    },
+}
+
+/// Switch the connection to a tenant, if there is one
+pub(crate) async fn enter<'c>(
+    checkout: &'c mut Checkout,
+    tenant: Option<&str>,
+) -> Result<Switched<'c>> {
+    let Checkout { conn, away } = checkout;
+    let conn: &mut AnyConnection = conn.as_mut().expect("checked out connection");
+    let tenant = match tenant.filter(|tenant| !tenant.is_empty()) {
+        Some(tenant) => tenant,
+        None => return Ok(Switched::Connection(conn)),
+    };
+    if conn.kind() == AnyKind::Postgres {
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            r#"select set_config('search_path',
+            concat_ws(', ', quote_ident($1), nullif(current_setting('search_path'), '')), true)"#,
+        )
+        .bind(tenant)
+        .execute(&mut *tx)
+        .await?;
+        return Ok(Switched::Transaction(Box::new(tx)));
+    }
+
+    // a batch dropped midway may or may not have switched the connection
+    *away = true;
+    let home = match switch_database(conn, tenant).await {
+        Ok(home) => home,
+        Err(err) => {
+            // a database error is the `USE` failing, which leaves the
+            // connection where it was
+            *away = !matches!(err, sqlx::Error::Database(_));
+            return Err(err.into());
+        }
+    };
+    match home {
+        Some(home) => Ok(Switched::Database { conn, home, away }),
+        None => Err(Error::Tenant(
+            "the MySQL uri must name a database to switch back to".into(),
+        )),
+    }
+}
+
+/// Leave the tenant, keeping the statement's result. A Postgres transaction is
+/// committed if the statement succeeded, and rolled back if it failed; only a
+/// failed commit, which loses the statement's work, replaces the result. A
+/// MySQL or SQL Server connection is switched back to its database.
+pub(crate) async fn leave<T>(switched: Switched<'_>, result: Result<T>) -> Result<T> {
+    match switched {
+        Switched::Connection(_) => {}
+        Switched::Transaction(tx) => {
+            if result.is_ok() {
+                tx.commit().await?;
+            } else if let Err(err) = tx.rollback().await {
+                error!("rolling back the statement of a tenant: {}", err);
+            }
+        }
+        Switched::Database { conn, home, away } => match use_database(conn, &home).await {
+            Ok(()) => *away = false,
+            Err(err) => error!("switching a connection back to `{}`: {}", home, err),
+        },
+    }
+    result
+}
+
+impl Checkout {
+    pub(crate) fn new(conn: PoolConnection<Any>) -> Self {
+        Checkout {
+            conn: Some(conn),
+            away: false,
+        }
+    }
+
+    /// Take the connection out of the pool, so it's closed when it's dropped
+    pub(crate) fn detach(mut self) -> AnyConnection {
+        self.conn.take().expect("checked out connection").detach()
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if self.away {
+            if let Some(conn) = self.conn.take() {
+                drop(conn.detach());
+            }
+        }
+    }
+}
+
+impl Deref for Checkout {
+    type Target = AnyConnection;
+
+    fn deref(&self) -> &AnyConnection {
+        self.conn.as_ref().expect("checked out connection")
+    }
+}
+
+impl DerefMut for Checkout {
+    fn deref_mut(&mut self) -> &mut AnyConnection {
+        self.conn.as_mut().expect("checked out connection")
+    }
+}
+
+impl Deref for Switched<'_> {
+    type Target = AnyConnection;
+
+    fn deref(&self) -> &AnyConnection {
+        match self {
+            Switched::Connection(conn) | Switched::Database { conn, .. } => conn,
+            Switched::Transaction(tx) => tx,
+        }
+    }
+}
+
+impl DerefMut for Switched<'_> {
+    fn deref_mut(&mut self) -> &mut AnyConnection {
+        match self {
+            Switched::Connection(conn) | Switched::Database { conn, .. } => conn,
+            Switched::Transaction(tx) => tx,
+        }
+    }
+}
+
+/// Switch a MySQL or SQL Server connection to a database, returning the one it
+/// was in. `USE` can't be a prepared statement, so the name is quoted into the
+/// SQL.
+async fn switch_database(conn: &mut AnyConnection, database: &str) -> sqlx::Result<Option<String>> {
+    let current = match conn.kind() {
+        AnyKind::Mssql => "db_name()",
+        _ => "database()",
+    };
+    let sql = format!("select {}; {}", current, use_sql(conn.kind(), database));
+    let rows = conn.fetch_all(sql.as_str()).await?;
+    match rows.first() {
+        Some(row) => row.try_get(0),
+        None => Ok(None),
+    }
+}
+
+async fn use_database(conn: &mut AnyConnection, database: &str) -> sqlx::Result<()> {
+    let sql = use_sql(conn.kind(), database);
+    conn.execute(sql.as_str()).await?;
+    Ok(())
+}
+
+fn use_sql(kind: AnyKind, database: &str) -> String {
+    match kind {
+        AnyKind::Mssql => format!("use [{}]", database.replace(']', "]]")),
+        _ => format!("use `{}`", database.replace('`', "``")),
+    }
+}
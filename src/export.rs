@@ -0,0 +1,206 @@
+//! Chunked exports
+//!
+//! An export runs in a task of its own, holding a pooled connection, and sends
+//! its data through a bounded channel so it only runs a few chunks ahead of the
+//! actor. The actor fetches the first chunk with `SqlDbExt.Export` and the next
+//! ones with `SqlDbExt.ExportNext`, until a chunk is marked done. Exports the
+//! actor stops fetching are dropped after a while by a sweeper task, which ends
+//! their task and releases their connection.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{mpsc, Mutex};
+use wasmcloud_interface_sqldb::SqlDbError;
+
+use crate::{
+    executor::{Checkout, SqlDbExecutor},
+    ext::{Export, ExportChunk},
+    limit::Permit,
+    result::Result,
+};
+
+/// size of chunks, when the export doesn't set it
+const DEFAULT_CHUNK_SIZE: usize = 256 << 10;
+/// largest chunk size an export may set
+const MAX_CHUNK_SIZE: usize = 16 << 20;
+/// chunks an export may run ahead of the actor
+const CHUNKS_AHEAD: usize = 2;
+/// time after which an export the actor stopped fetching is dropped
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// interval at which idle exports are looked for
+const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+
+type Running = Mutex<HashMap<(String, String), RunningExport>>;
+
+/// Exports in progress, by actor and export id
+#[derive(Clone, Default)]
+pub(crate) struct Exports {
+    running: Arc<Running>,
+    next_id: Arc<AtomicU64>,
+    sweeping: Arc<AtomicBool>,
+}
+
+struct RunningExport {
+    chunks: mpsc::Receiver<Result<Vec<u8>>>,
+    last_fetch: Instant,
+}
+
+impl Exports {
+    /// Start an export on the connection, returning its first chunk. The
+    /// export holds its permit until it ends.
+    pub(crate) async fn start(
+        &self,
+        actor_id: &str,
+        mut conn: Checkout,
+        permit: Permit,
+        export: Export,
+    ) -> ExportChunk {
+        if !self.sweeping.swap(true, Ordering::Relaxed) {
+            tokio::spawn(sweep(Arc::downgrade(&self.running)));
+        }
+
+        let chunk_size = export
+            .chunk_size
+            .map_or(DEFAULT_CHUNK_SIZE, |size| size as usize)
+            .clamp(1, MAX_CHUNK_SIZE);
+        let (sender, receiver) = mpsc::channel(CHUNKS_AHEAD);
+        tokio::spawn(async move {
+            if let Err(err) = conn.export(&export, chunk_size, &sender).await {
+                let _ = sender.send(Err(err)).await;
+            }
+            // an export stopped midway leaves the connection in the middle of
+            // a response, so it isn't returned to the pool
+            if sender.is_closed() {
+                drop(conn.detach());
+            }
+            drop(permit);
+        });
+
+        let export_id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let running = RunningExport {
+            chunks: receiver,
+            last_fetch: Instant::now(),
+        };
+        self.fetch(actor_id, export_id, running).await
+    }
+
+    /// Fetch the next chunk of an export
+    pub(crate) async fn next(&self, actor_id: &str, export_id: &str) -> ExportChunk {
+        let key = (actor_id.to_string(), export_id.to_string());
+        let running = self.running.lock().await.remove(&key);
+        match running {
+            Some(running) => self.fetch(actor_id, export_id.to_string(), running).await,
+            None => ExportChunk {
+                export_id: export_id.to_string(),
+                error: Some(SqlDbError::new(
+                    "db",
+                    format!("unknown export `{}`, it may have been dropped", export_id),
+                )),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Wait for the next chunk, keeping the export for the following one
+    /// unless it is done. The export is out of the map while waiting, so a
+    /// concurrent fetch of the same export fails rather than waits.
+    async fn fetch(
+        &self,
+        actor_id: &str,
+        export_id: String,
+        mut running: RunningExport,
+    ) -> ExportChunk {
+        match running.chunks.recv().await {
+            Some(Ok(data)) => {
+                running.last_fetch = Instant::now();
+                self.running
+                    .lock()
+                    .await
+                    .insert((actor_id.to_string(), export_id.clone()), running);
+                ExportChunk {
+                    export_id,
+                    data,
+                    done: false,
+                    error: None,
+                }
+            }
+            Some(Err(err)) => ExportChunk {
+                export_id,
+                done: true,
+                error: Some(err.into()),
+                ..Default::default()
+            },
+            None => ExportChunk {
+                export_id,
+                done: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Drop the exports of an actor
+    pub(crate) async fn drop_actor(&self, actor_id: &str) {
+        self.running
+            .lock()
+            .await
+            .retain(|(actor, _), _| actor != actor_id);
+    }
+}
+
+/// Drop the exports the actors stopped fetching, until the exports are
+/// dropped themselves
+async fn sweep(running: Weak<Running>) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        match running.upgrade() {
+            Some(running) => drop_idle(&running).await,
+            None => break,
+        }
+    }
+}
+
+async fn drop_idle(running: &Running) {
+    running
+        .lock()
+        .await
+        .retain(|_, running| running.last_fetch.elapsed() < IDLE_TIMEOUT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn idle_exports_are_dropped() {
+        let exports = Exports::default();
+        let mut senders = Vec::new();
+        for (id, idle) in [("1", IDLE_TIMEOUT * 2), ("2", Duration::ZERO)] {
+            let (sender, chunks) = mpsc::channel(CHUNKS_AHEAD);
+            senders.push(sender);
+            let running = RunningExport {
+                chunks,
+                last_fetch: Instant::now().checked_sub(idle).unwrap(),
+            };
+            exports
+                .running
+                .lock()
+                .await
+                .insert(("actor".into(), id.into()), running);
+        }
+
+        drop_idle(&exports.running).await;
+        // dropping the export closes its channel, which ends its task
+        assert!(senders[0].is_closed());
+        assert!(!senders[1].is_closed());
+        let chunk = exports.next("actor", "1").await;
+        assert!(chunk.error.is_some());
+    }
+}
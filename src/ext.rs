@@ -0,0 +1,628 @@
+//! # SqlDbExt service
+//!
+//! Operations this provider supports beyond the `wasmcloud:sqldb` contract.
+//! They are served to linked actors on the same contract id, as methods of the
+//! `SqlDbExt` service (e.g. `SqlDbExt.Describe`). Arguments and results are
+//! serialized like the contract's own types, with camelCase field names.
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use wasmbus_rpc::{
+    common::{Context, Message, MessageDispatch, Transport},
+    error::{RpcError, RpcResult},
+};
+use wasmcloud_interface_sqldb::{Column, QueryResult, SqlDbError, Statement};
+
+/// Metadata about a result column, in addition to what the contract's
+/// `Column` reports. Fields the database doesn't report are omitted.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMetadata {
+    /// column ordinal
+    #[serde(default)]
+    pub ordinal: u32,
+    /// column name in the result
+    #[serde(default)]
+    pub name: String,
+    /// column data type as reported by the database
+    #[serde(default)]
+    pub db_type: String,
+    /// whether the column can be null
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nullable: Option<bool>,
+    /// schema of the table the column is read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_schema: Option<String>,
+    /// table the column is read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_table: Option<String>,
+    /// name of the column in the table it is read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_column: Option<String>,
+    /// numeric precision, or fractional second digits of time types
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u32>,
+    /// numeric scale
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+    /// maximum length of string and binary values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u32>,
+    /// whether the column is part of the primary key of its table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<bool>,
+}
+
+/// Result of describing a statement
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DescribeResult {
+    /// metadata of the columns the statement returns
+    #[serde(default)]
+    pub columns: Vec<ColumnMetadata>,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Format of the rows in query results
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowFormat {
+    #[default]
+    Cbor,
+    Msgpack,
+    Json,
+    /// an Arrow IPC stream of the schema and one record batch
+    Arrow,
+}
+
+/// Query to run, returning its rows in a chosen format
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryAs {
+    /// query to run
+    pub statement: Statement,
+    /// format of the rows
+    pub format: RowFormat,
+}
+
+/// Statement to execute, returning the keys generated for the rows it changes
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecuteReturning {
+    /// statement to execute
+    pub statement: Statement,
+    /// columns to return for each changed row. Default: all columns on
+    /// Postgres and SQL Server, the generated id on MySQL
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_columns: Vec<String>,
+}
+
+/// Result of executing a statement with `ExecuteReturning`
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReturningResult {
+    /// number of rows affected by the statement
+    #[serde(default)]
+    pub rows_affected: u64,
+    /// number of returned rows
+    #[serde(default)]
+    pub num_rows: u64,
+    /// description of the returned columns
+    #[serde(default)]
+    pub columns: Vec<Column>,
+    /// returned rows, encoded in CBOR as an array (rows) of arrays (fields per row)
+    #[serde(with = "serde_bytes", default)]
+    pub rows: Vec<u8>,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// One result set of a statement
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultSet {
+    /// number of rows in the result set
+    #[serde(default)]
+    pub num_rows: u64,
+    /// description of the columns
+    #[serde(default)]
+    pub columns: Vec<Column>,
+    /// rows, encoded in CBOR as an array (rows) of arrays (fields per row)
+    #[serde(with = "serde_bytes", default)]
+    pub rows: Vec<u8>,
+}
+
+/// Result of running a statement with `QueryMulti`
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiQueryResult {
+    /// result sets, in the order they were returned
+    #[serde(default)]
+    pub result_sets: Vec<ResultSet>,
+    /// rows affected by each statement that returned no rows, in order
+    #[serde(default)]
+    pub rows_affected: Vec<u64>,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Stored procedure or function to call
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcedureCall {
+    /// name of the procedure or function, optionally qualified by its schema
+    pub procedure: String,
+    /// arguments, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<ProcedureParam>,
+    /// call a function instead of a procedure
+    #[serde(default)]
+    pub function: bool,
+}
+
+/// Direction of a procedure parameter
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamMode {
+    #[default]
+    In,
+    Out,
+    InOut,
+}
+
+/// Argument of a procedure call
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcedureParam {
+    /// parameter name, to pass the argument by name (Postgres, SQL Server) and
+    /// to name the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// direction of the parameter. Default: in
+    #[serde(default)]
+    pub mode: ParamMode,
+    /// value of an in or inout parameter, encoded in CBOR like statement
+    /// parameters. Default: null
+    #[serde(with = "serde_bytes", default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Vec<u8>>,
+    /// SQL type of the parameter, required for out and inout parameters on
+    /// SQL Server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_type: Option<String>,
+}
+
+/// Result of calling a procedure or function
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallResult {
+    /// values of the out and inout parameters, as a single row with a column
+    /// per parameter
+    #[serde(default)]
+    pub outputs: ResultSet,
+    /// return value of a function, or return status of a SQL Server
+    /// procedure, encoded in CBOR
+    #[serde(with = "serde_bytes", default, skip_serializing_if = "Option::is_none")]
+    pub return_value: Option<Vec<u8>>,
+    /// result sets returned by the procedure, in order
+    #[serde(default)]
+    pub result_sets: Vec<ResultSet>,
+    /// rows affected by each statement that returned no rows, in order
+    #[serde(default)]
+    pub rows_affected: Vec<u64>,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Rows to insert into a table
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkInsert {
+    /// name of the table, optionally qualified by its schema
+    pub table: String,
+    /// columns the values of each row are inserted into
+    pub columns: Vec<String>,
+    /// rows, encoded in CBOR as an array (rows) of arrays (values per column),
+    /// each value encoded like a statement parameter
+    #[serde(with = "serde_bytes")]
+    pub rows: Vec<u8>,
+}
+
+/// Result of a bulk insert
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkInsertResult {
+    /// number of rows inserted
+    #[serde(default)]
+    pub rows_affected: u64,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Format of exported data
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Binary,
+}
+
+/// Query to export the result of
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Export {
+    /// query to export, without parameters
+    pub statement: Statement,
+    /// format of the data. Default: csv
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// whether CSV data starts with a header line of column names
+    #[serde(default)]
+    pub header: bool,
+    /// size of the chunks, in bytes. Default: 256 KiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<u32>,
+}
+
+/// Export to fetch the next chunk of
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportNext {
+    /// id of the export, from its first chunk
+    pub export_id: String,
+}
+
+/// Chunk of exported data
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportChunk {
+    /// id of the export, to fetch the next chunk with
+    #[serde(default)]
+    pub export_id: String,
+    /// exported data
+    #[serde(with = "serde_bytes", default)]
+    pub data: Vec<u8>,
+    /// whether this is the last chunk, which may be empty
+    #[serde(default)]
+    pub done: bool,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Tables to list
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableFilter {
+    /// schema to list the tables of. Default: all schemas but the system ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+}
+
+/// Table or view
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableInfo {
+    /// schema of the table
+    pub schema: String,
+    /// name of the table
+    pub name: String,
+    /// kind of the table: "table", "view", or the backend's name for another
+    /// kind, in lowercase
+    pub kind: String,
+}
+
+/// Result of listing schemas
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemasResult {
+    /// names of the schemas, sorted
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Result of listing tables
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TablesResult {
+    /// tables, sorted by schema and name
+    #[serde(default)]
+    pub tables: Vec<TableInfo>,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Table to describe
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRef {
+    /// schema of the table. Default: the connection's current schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// name of the table
+    pub table: String,
+}
+
+/// Column of a table
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableColumn {
+    /// position of the column, starting at 1
+    pub ordinal: u32,
+    /// name of the column
+    pub name: String,
+    /// type of the column, as the backend names it
+    pub data_type: String,
+    /// whether the column allows nulls
+    pub nullable: bool,
+    /// default value expression, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// maximum length of character and binary columns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u32>,
+    /// precision of numeric columns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u32>,
+    /// scale of numeric columns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+}
+
+/// Index of a table
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexInfo {
+    /// name of the index
+    pub name: String,
+    /// indexed columns, in index order. Expressions are left out.
+    pub columns: Vec<String>,
+    /// whether the index is unique
+    pub unique: bool,
+    /// whether the index is the primary key
+    pub primary: bool,
+}
+
+/// Foreign key of a table
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignKeyInfo {
+    /// name of the constraint
+    pub name: String,
+    /// referencing columns
+    pub columns: Vec<String>,
+    /// schema of the referenced table
+    pub referenced_schema: String,
+    /// referenced table
+    pub referenced_table: String,
+    /// referenced columns, matching `columns` by position
+    pub referenced_columns: Vec<String>,
+}
+
+/// Description of a table
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableDescription {
+    /// schema of the table
+    pub schema: String,
+    /// name of the table
+    pub name: String,
+    /// kind of the table, as in `TableInfo`
+    pub kind: String,
+    /// columns, in order
+    pub columns: Vec<TableColumn>,
+    /// columns of the primary key, in key order. Empty if there is none.
+    #[serde(default)]
+    pub primary_key: Vec<String>,
+    /// indexes, including the one of the primary key
+    #[serde(default)]
+    pub indexes: Vec<IndexInfo>,
+    /// foreign keys
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+/// Result of describing a table
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableResult {
+    /// description of the table, or none if it wasn't found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<TableDescription>,
+    /// optional error information.
+    /// If error is included in the result, other values should be ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SqlDbError>,
+}
+
+/// Notification received on a Postgres channel the link listens to
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// channel the notification was sent on
+    pub channel: String,
+    /// payload of the notification, which may be empty
+    pub payload: String,
+    /// process id of the server backend that sent the notification
+    pub process_id: u32,
+}
+
+/// Extended SQL database operations
+#[async_trait::async_trait]
+pub trait SqlDbExt {
+    /// Describe the columns a statement returns, without running it
+    async fn describe(&self, ctx: &Context, arg: &Statement) -> RpcResult<DescribeResult>;
+
+    /// Run a query, returning its rows in the given format instead of the
+    /// link's
+    async fn query_as(&self, ctx: &Context, arg: &QueryAs) -> RpcResult<QueryResult>;
+
+    /// Execute a statement, returning the keys generated for the rows it
+    /// inserts or changes
+    async fn execute_returning(
+        &self,
+        ctx: &Context,
+        arg: &ExecuteReturning,
+    ) -> RpcResult<ReturningResult>;
+
+    /// Run a statement that may return several result sets, such as a stored
+    /// procedure or a string of statements
+    async fn query_multi(&self, ctx: &Context, arg: &Statement) -> RpcResult<MultiQueryResult>;
+
+    /// Call a stored procedure or function, returning its output parameters,
+    /// return value and result sets
+    async fn call(&self, ctx: &Context, arg: &ProcedureCall) -> RpcResult<CallResult>;
+
+    /// Insert many rows into a table, all or none
+    async fn bulk_insert(&self, ctx: &Context, arg: &BulkInsert) -> RpcResult<BulkInsertResult>;
+
+    /// Start exporting the result of a query, returning the first chunk
+    async fn export(&self, ctx: &Context, arg: &Export) -> RpcResult<ExportChunk>;
+
+    /// Fetch the next chunk of an export
+    async fn export_next(&self, ctx: &Context, arg: &ExportNext) -> RpcResult<ExportChunk>;
+
+    /// List the schemas of the database
+    async fn list_schemas(&self, ctx: &Context) -> RpcResult<SchemasResult>;
+
+    /// List the tables and views of a schema, or of all schemas
+    async fn list_tables(&self, ctx: &Context, arg: &TableFilter) -> RpcResult<TablesResult>;
+
+    /// Describe the columns, indexes and foreign keys of a table
+    async fn describe_table(&self, ctx: &Context, arg: &TableRef) -> RpcResult<TableResult>;
+}
+
+/// SqlDbExtReceiver receives messages defined in the SqlDbExt service trait
+#[async_trait::async_trait]
+pub trait SqlDbExtReceiver: MessageDispatch + SqlDbExt {
+    async fn dispatch(&self, ctx: &Context, message: Message<'_>) -> RpcResult<Vec<u8>> {
+        match message.method {
+            "Describe" => {
+                let value: Statement = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'Statement': {}", e)))?;
+                let resp = SqlDbExt::describe(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "QueryAs" => {
+                let value: QueryAs = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'QueryAs': {}", e)))?;
+                let resp = SqlDbExt::query_as(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "ExecuteReturning" => {
+                let value: ExecuteReturning = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'ExecuteReturning': {}", e)))?;
+                let resp = SqlDbExt::execute_returning(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "QueryMulti" => {
+                let value: Statement = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'Statement': {}", e)))?;
+                let resp = SqlDbExt::query_multi(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "Call" => {
+                let value: ProcedureCall = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'ProcedureCall': {}", e)))?;
+                let resp = SqlDbExt::call(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "BulkInsert" => {
+                let value: BulkInsert = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'BulkInsert': {}", e)))?;
+                let resp = SqlDbExt::bulk_insert(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "Export" => {
+                let value: Export = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'Export': {}", e)))?;
+                let resp = SqlDbExt::export(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "ExportNext" => {
+                let value: ExportNext = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'ExportNext': {}", e)))?;
+                let resp = SqlDbExt::export_next(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "ListSchemas" => {
+                let resp = SqlDbExt::list_schemas(self, ctx).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "ListTables" => {
+                let value: TableFilter = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'TableFilter': {}", e)))?;
+                let resp = SqlDbExt::list_tables(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            "DescribeTable" => {
+                let value: TableRef = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'TableRef': {}", e)))?;
+                let resp = SqlDbExt::describe_table(self, ctx, &value).await?;
+                Ok(wasmbus_rpc::common::serialize(&resp)?)
+            }
+            _ => Err(RpcError::MethodNotHandled(format!(
+                "SqlDbExt::{}",
+                message.method
+            ))),
+        }
+    }
+}
+
+/// Handler actors implement to receive the notifications of the channels their
+/// link listens to
+#[async_trait::async_trait]
+pub trait SqlDbNotificationHandler {
+    /// Handle a notification
+    async fn handle_notification(&self, ctx: &Context, arg: &Notification) -> RpcResult<()>;
+}
+
+/// SqlDbNotificationHandlerSender sends messages to a SqlDbNotificationHandler
+/// service
+#[derive(Clone, Debug)]
+pub struct SqlDbNotificationHandlerSender<T: Transport> {
+    transport: T,
+}
+
+impl<T: Transport> SqlDbNotificationHandlerSender<T> {
+    /// Constructs a SqlDbNotificationHandlerSender with the specified transport
+    pub fn via(transport: T) -> Self {
+        Self { transport }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Transport + Sync + Send> SqlDbNotificationHandler for SqlDbNotificationHandlerSender<T> {
+    async fn handle_notification(&self, ctx: &Context, arg: &Notification) -> RpcResult<()> {
+        let buf = wasmbus_rpc::common::serialize(arg)?;
+        self.transport
+            .send(
+                ctx,
+                Message {
+                    method: "SqlDbNotificationHandler.HandleNotification",
+                    arg: Cow::Borrowed(&buf),
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+//! Limits on the statements of a link
+//!
+//! A link may limit how many statements it starts per second and how many run
+//! at once, so a single actor can't take all of a database shared with others.
+//! Statements over a limit aren't queued; they fail right away with a
+//! `throttled` error, and the actor decides whether to retry.
+
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Instant,
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    config::LimitOptions,
+    result::{Error, Result},
+};
+
+/// Limits of a link
+pub(crate) struct Limits {
+    rate: Option<Mutex<TokenBucket>>,
+    concurrency: Option<(Arc<Semaphore>, u32)>,
+}
+
+/// Admission of a statement, holding its place under the concurrency limit
+/// until dropped
+pub(crate) struct Permit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// Tokens refilled at `rate` per second, up to a second's worth
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Limits {
+    pub(crate) fn new(options: &LimitOptions) -> Self {
+        let rate = options
+            .queries_per_second
+            .filter(|rate| *rate > 0)
+            .map(|rate| {
+                Mutex::new(TokenBucket {
+                    rate: rate.into(),
+                    tokens: rate.into(),
+                    refilled: Instant::now(),
+                })
+            });
+        let concurrency = options
+            .max_concurrent
+            .filter(|max| *max > 0)
+            .map(|max| (Arc::new(Semaphore::new(max as usize)), max));
+        Limits { rate, concurrency }
+    }
+
+    /// Admit a statement, unless the link is at one of its limits
+    pub(crate) fn admit(&self) -> Result<Permit> {
+        // the concurrency permit is taken first, since it's given back if the
+        // rate limit is reached, and a token isn't
+        let permit = match &self.concurrency {
+            Some((semaphore, max)) => {
+                Some(semaphore.clone().try_acquire_owned().map_err(|_| {
+                    Error::Throttled(format!("{} statements are already running", max))
+                })?)
+            }
+            None => None,
+        };
+        if let Some(rate) = &self.rate {
+            let mut bucket = rate.lock().unwrap_or_else(PoisonError::into_inner);
+            if !bucket.take(Instant::now()) {
+                return Err(Error::Throttled(format!(
+                    "over the limit of {} statements per second",
+                    bucket.rate
+                )));
+            }
+        }
+        Ok(Permit { _permit: permit })
+    }
+}
+
+impl TokenBucket {
+    /// Take a token at `now`, if there is one
+    fn take(&mut self, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.refilled = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn bucket_refills_at_its_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket {
+            rate: 2.0,
+            tokens: 2.0,
+            refilled: start,
+        };
+        // a burst of a second's worth
+        assert!(bucket.take(start));
+        assert!(bucket.take(start));
+        assert!(!bucket.take(start));
+        // a token every half second
+        assert!(!bucket.take(start + Duration::from_millis(400)));
+        assert!(bucket.take(start + Duration::from_millis(600)));
+        assert!(!bucket.take(start + Duration::from_millis(600)));
+        // a long wait refills no more than a second's worth
+        let later = start + Duration::from_secs(10);
+        assert!(bucket.take(later));
+        assert!(bucket.take(later));
+        assert!(!bucket.take(later));
+    }
+
+    #[test]
+    fn concurrency_is_given_back_by_permits() {
+        let limits = Limits::new(&LimitOptions {
+            max_concurrent: Some(2),
+            ..Default::default()
+        });
+        let first = limits.admit().unwrap();
+        let _second = limits.admit().unwrap();
+        assert!(matches!(limits.admit(), Err(Error::Throttled(_))));
+        drop(first);
+        assert!(limits.admit().is_ok());
+    }
+}
@@ -2,19 +2,55 @@
 //!
 //! Implements the `wasmcloud:sqldb` capability.
 
+mod cache;
 mod config;
 mod executor;
+mod export;
+mod ext;
+mod limit;
+mod migrate;
+mod notify;
 mod result;
+mod session;
 
 use std::{collections::HashMap, convert::Infallible, sync::Arc};
 
-use sqlx::{any::AnyPool, pool::PoolConnection, Any};
-use tokio::sync::RwLock;
+use sqlx::any::AnyPool;
+use tokio::{sync::RwLock, task::JoinHandle};
 use tracing::{info, instrument};
 use wasmbus_rpc::provider::prelude::*;
 use wasmcloud_interface_sqldb::{ExecuteResult, QueryResult, SqlDb, SqlDbReceiver, Statement};
 
-use crate::executor::SqlDbExecutor;
+use crate::{
+    cache::QueryCache,
+    config::EncodingOptions,
+    executor::{format, tables, Checkout, SqlDbExecutor},
+    export::Exports,
+    ext::{
+        BulkInsert, BulkInsertResult, CallResult, DescribeResult, ExecuteReturning, Export,
+        ExportChunk, ExportNext, MultiQueryResult, ProcedureCall, QueryAs, ReturningResult,
+        RowFormat, SchemasResult, SqlDbExt, SqlDbExtReceiver, TableFilter, TableRef, TableResult,
+        TablesResult,
+    },
+    limit::{Limits, Permit},
+};
+
+/// Admit a statement under the limits of the actor's link, or return the
+/// throttled error in a `$result`, with any other `$field`s given
+macro_rules! admit {
+    ($provider:ident, $ctx:ident, $result:ident $({ $($field:ident: $value:expr),* })?) => {
+        match $provider.admit($ctx).await? {
+            Ok(permit) => permit,
+            Err(err) => {
+                return Ok($result {
+                    $($($field: $value,)*)?
+                    error: Some(err.into()),
+                    ..Default::default()
+                })
+            }
+        }
+    };
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     provider_main(
@@ -27,23 +63,111 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[derive(Default, Clone, Provider)]
-#[services(SqlDb)]
+#[services(SqlDb, SqlDbExt)]
 struct SqlDbProvider {
-    actors: Arc<RwLock<HashMap<String, AnyPool>>>,
+    actors: Arc<RwLock<HashMap<String, ActorLink>>>,
+    exports: Exports,
+}
+
+/// Connection pool and settings for a linked actor
+struct ActorLink {
+    pool: AnyPool,
+    encoding: EncodingOptions,
+    /// cache of query results, if the link caches them
+    cache: Option<Arc<QueryCache>>,
+    /// limits on the statements of the link
+    limits: Limits,
+    /// task forwarding Postgres notifications to the actor
+    listener: Option<JoinHandle<()>>,
+}
+
+impl ActorLink {
+    async fn close(self) {
+        if let Some(listener) = self.listener {
+            listener.abort();
+        }
+        self.pool.close().await;
+    }
 }
 
 impl SqlDbProvider {
-    async fn acquire_connection(&self, ctx: &Context) -> RpcResult<PoolConnection<Any>> {
+    async fn acquire_connection(&self, ctx: &Context) -> RpcResult<(Checkout, EncodingOptions)> {
         let actor_id = actor_id(ctx)?;
         let rd = self.actors.read().await;
 
-        let pool = rd
+        let link = rd
             .get(actor_id)
             .ok_or_else(|| RpcError::InvalidParameter(format!("actor not linked:{}", actor_id)))?;
 
-        pool.acquire()
+        let conn = link
+            .pool
+            .acquire()
             .await
-            .map_err(|err| RpcError::Other(err.to_string()))
+            .map_err(|err| RpcError::Other(err.to_string()))?;
+        Ok((Checkout::new(conn), link.encoding))
+    }
+
+    /// The cache of query results of the actor's link, if it caches them
+    async fn cache(&self, ctx: &Context) -> RpcResult<Option<Arc<QueryCache>>> {
+        self.with_link(ctx, |link| link.cache.clone()).await
+    }
+
+    /// Admit a statement under the limits of the actor's link, before it takes
+    /// a connection
+    async fn admit(&self, ctx: &Context) -> RpcResult<result::Result<Permit>> {
+        self.with_link(ctx, |link| link.limits.admit()).await
+    }
+
+    async fn with_link<T>(&self, ctx: &Context, f: impl FnOnce(&ActorLink) -> T) -> RpcResult<T> {
+        let actor_id = actor_id(ctx)?;
+        let rd = self.actors.read().await;
+        let link = rd
+            .get(actor_id)
+            .ok_or_else(|| RpcError::InvalidParameter(format!("actor not linked:{}", actor_id)))?;
+        Ok(f(link))
+    }
+
+    /// Run a query, returning its rows in `format`, or the link's row format.
+    /// Results are served from the link's cache when they can be.
+    async fn query_rows(
+        &self,
+        ctx: &Context,
+        stmt: &Statement,
+        format: Option<RowFormat>,
+    ) -> RpcResult<QueryResult> {
+        let cache = self.cache(ctx).await?;
+        if let Some(result) = cache.as_ref().and_then(|cache| cache.get(stmt, format)) {
+            return Ok(result);
+        }
+        let generation = cache.as_ref().map_or(0, |cache| cache.generation());
+
+        let _permit = admit!(self, ctx, QueryResult);
+        let (mut conn, encoding) = self.acquire_connection(ctx).await?;
+        let encoding = EncodingOptions {
+            rows: format.unwrap_or(encoding.rows),
+            ..encoding
+        };
+        let db = conn.kind();
+        let result = conn
+            .fetch_all(stmt, &encoding)
+            .await
+            .and_then(|mut result| {
+                format::transcode_rows(&mut result.rows, &result.columns, db, &encoding)?;
+                Ok(result)
+            });
+        if let Some(cache) = &cache {
+            match &result {
+                Ok(result) => cache.record(stmt, format, generation, result),
+                Err(_) => cache.invalidate(&stmt.sql),
+            }
+        }
+        match result {
+            Ok(result) => Ok(result),
+            Err(err) => Ok(QueryResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
     }
 }
 
@@ -54,24 +178,44 @@ impl ProviderHandler for SqlDbProvider {
     #[instrument(level = "debug", skip(self), fields(actor_id = %ld.actor_id))]
     async fn put_link(&self, ld: &LinkDefinition) -> RpcResult<bool> {
         let config = config::load_config(ld)?;
-        let pool = config::create_pool(config).await?;
+        let pool = config::create_pool(&config).await?;
+        if !config.migrations.is_empty() {
+            if let Err(err) = migrate::migrate(&pool, &config.migrations).await {
+                pool.close().await;
+                return Err(err);
+            }
+        }
+        let listener = (!config.listen.is_empty()).then(|| {
+            notify::spawn_listener(config.uri().to_string(), config.listen.clone(), ld.clone())
+        });
+        let cache = QueryCache::new(&config.cache, pool.any_kind()).map(Arc::new);
+        let link = ActorLink {
+            pool,
+            encoding: config.encoding,
+            cache,
+            limits: Limits::new(&config.limits),
+            listener,
+        };
         let mut update_map = self.actors.write().await;
-        update_map.insert(ld.actor_id.to_string(), pool);
+        if let Some(previous) = update_map.insert(ld.actor_id.to_string(), link) {
+            previous.close().await;
+        }
         Ok(true)
     }
 
     #[instrument(level = "debug", skip(self))]
     async fn delete_link(&self, actor_id: &str) {
         let mut aw = self.actors.write().await;
-        if let Some(pool) = aw.remove(actor_id) {
-            pool.close().await;
+        if let Some(link) = aw.remove(actor_id) {
+            link.close().await;
         }
+        self.exports.drop_actor(actor_id).await;
     }
 
     async fn shutdown(&self) -> Result<(), Infallible> {
         let mut aw = self.actors.write().await;
-        for (_, pool) in aw.drain() {
-            pool.close().await;
+        for (_, link) in aw.drain() {
+            link.close().await;
         }
         Ok(())
     }
@@ -87,8 +231,13 @@ fn actor_id(ctx: &Context) -> Result<&String, RpcError> {
 impl SqlDb for SqlDbProvider {
     #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, sql = stmt.sql))]
     async fn execute(&self, ctx: &Context, stmt: &Statement) -> RpcResult<ExecuteResult> {
-        let mut conn = self.acquire_connection(ctx).await?;
-        match conn.execute(stmt).await {
+        let _permit = admit!(self, ctx, ExecuteResult);
+        let (mut conn, _) = self.acquire_connection(ctx).await?;
+        let result = conn.execute(stmt).await;
+        if let Some(cache) = self.cache(ctx).await? {
+            cache.invalidate(&stmt.sql);
+        }
+        match result {
             Ok(result) => Ok(result),
             Err(err) => Ok(ExecuteResult {
                 error: Some(err.into()),
@@ -99,10 +248,186 @@ impl SqlDb for SqlDbProvider {
 
     #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, sql = stmt.sql))]
     async fn query(&self, ctx: &Context, stmt: &Statement) -> RpcResult<QueryResult> {
-        let mut conn = self.acquire_connection(ctx).await?;
-        match conn.fetch_all(stmt).await {
+        self.query_rows(ctx, stmt, None).await
+    }
+}
+
+#[async_trait]
+impl SqlDbExt for SqlDbProvider {
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, sql = stmt.sql))]
+    async fn describe(&self, ctx: &Context, stmt: &Statement) -> RpcResult<DescribeResult> {
+        let _permit = admit!(self, ctx, DescribeResult);
+        let (mut conn, _) = self.acquire_connection(ctx).await?;
+        match conn.describe(stmt).await {
+            Ok(columns) => Ok(DescribeResult {
+                columns,
+                error: None,
+            }),
+            Err(err) => Ok(DescribeResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, sql = arg.statement.sql))]
+    async fn query_as(&self, ctx: &Context, arg: &QueryAs) -> RpcResult<QueryResult> {
+        self.query_rows(ctx, &arg.statement, Some(arg.format)).await
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, sql = arg.statement.sql))]
+    async fn execute_returning(
+        &self,
+        ctx: &Context,
+        arg: &ExecuteReturning,
+    ) -> RpcResult<ReturningResult> {
+        let _permit = admit!(self, ctx, ReturningResult);
+        let (mut conn, encoding) = self.acquire_connection(ctx).await?;
+        let db = conn.kind();
+        let result = conn
+            .execute_returning(&arg.statement, &arg.key_columns, &encoding)
+            .await
+            .and_then(|mut result| {
+                format::transcode_rows(&mut result.rows, &result.columns, db, &encoding)?;
+                Ok(result)
+            });
+        if let Some(cache) = self.cache(ctx).await? {
+            cache.invalidate(&arg.statement.sql);
+        }
+        match result {
             Ok(result) => Ok(result),
-            Err(err) => Ok(QueryResult {
+            Err(err) => Ok(ReturningResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, procedure = arg.procedure))]
+    async fn call(&self, ctx: &Context, arg: &ProcedureCall) -> RpcResult<CallResult> {
+        let _permit = admit!(self, ctx, CallResult);
+        let (mut conn, encoding) = self.acquire_connection(ctx).await?;
+        let db = conn.kind();
+        let result = conn.call(arg, &encoding).await.and_then(|mut result| {
+            let outputs = &mut result.outputs;
+            format::transcode_rows(&mut outputs.rows, &outputs.columns, db, &encoding)?;
+            if let Some(return_value) = &mut result.return_value {
+                format::transcode(return_value, encoding.rows)?;
+            }
+            format::transcode_result_sets(&mut result.result_sets, db, &encoding)?;
+            Ok(result)
+        });
+        // a procedure may change any table
+        if let Some(cache) = self.cache(ctx).await? {
+            cache.clear();
+        }
+        match result {
+            Ok(result) => Ok(result),
+            Err(err) => Ok(CallResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, table = arg.table))]
+    async fn bulk_insert(&self, ctx: &Context, arg: &BulkInsert) -> RpcResult<BulkInsertResult> {
+        let _permit = admit!(self, ctx, BulkInsertResult);
+        let (mut conn, _) = self.acquire_connection(ctx).await?;
+        let result = conn.bulk_insert(arg).await;
+        if let Some(cache) = self.cache(ctx).await? {
+            cache.invalidate_tables(&[tables::table_name(&arg.table)]);
+        }
+        match result {
+            Ok(rows_affected) => Ok(BulkInsertResult {
+                rows_affected,
+                error: None,
+            }),
+            Err(err) => Ok(BulkInsertResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, sql = arg.statement.sql))]
+    async fn export(&self, ctx: &Context, arg: &Export) -> RpcResult<ExportChunk> {
+        let permit = admit!(self, ctx, ExportChunk { done: true });
+        let (conn, _) = self.acquire_connection(ctx).await?;
+        Ok(self
+            .exports
+            .start(actor_id(ctx)?, conn, permit, arg.clone())
+            .await)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, export_id = arg.export_id))]
+    async fn export_next(&self, ctx: &Context, arg: &ExportNext) -> RpcResult<ExportChunk> {
+        Ok(self.exports.next(actor_id(ctx)?, &arg.export_id).await)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, sql = stmt.sql))]
+    async fn query_multi(&self, ctx: &Context, stmt: &Statement) -> RpcResult<MultiQueryResult> {
+        let _permit = admit!(self, ctx, MultiQueryResult);
+        let (mut conn, encoding) = self.acquire_connection(ctx).await?;
+        let db = conn.kind();
+        let result = conn
+            .fetch_multi(stmt, &encoding)
+            .await
+            .and_then(|mut result| {
+                format::transcode_result_sets(&mut result.result_sets, db, &encoding)?;
+                Ok(result)
+            });
+        if let Some(cache) = self.cache(ctx).await? {
+            cache.invalidate(&stmt.sql);
+        }
+        match result {
+            Ok(result) => Ok(result),
+            Err(err) => Ok(MultiQueryResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor))]
+    async fn list_schemas(&self, ctx: &Context) -> RpcResult<SchemasResult> {
+        let _permit = admit!(self, ctx, SchemasResult);
+        let (mut conn, _) = self.acquire_connection(ctx).await?;
+        match conn.list_schemas().await {
+            Ok(schemas) => Ok(SchemasResult {
+                schemas,
+                error: None,
+            }),
+            Err(err) => Ok(SchemasResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, schema = ?arg.schema))]
+    async fn list_tables(&self, ctx: &Context, arg: &TableFilter) -> RpcResult<TablesResult> {
+        let _permit = admit!(self, ctx, TablesResult);
+        let (mut conn, _) = self.acquire_connection(ctx).await?;
+        match conn.list_tables(arg.schema.as_deref()).await {
+            Ok(tables) => Ok(TablesResult {
+                tables,
+                error: None,
+            }),
+            Err(err) => Ok(TablesResult {
+                error: Some(err.into()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(actor_id = ?ctx.actor, table = arg.table))]
+    async fn describe_table(&self, ctx: &Context, arg: &TableRef) -> RpcResult<TableResult> {
+        let _permit = admit!(self, ctx, TableResult);
+        let (mut conn, _) = self.acquire_connection(ctx).await?;
+        match conn.describe_table(arg.schema.as_deref(), &arg.table).await {
+            Ok(table) => Ok(TableResult { table, error: None }),
+            Err(err) => Ok(TableResult {
                 error: Some(err.into()),
                 ..Default::default()
             }),
@@ -0,0 +1,244 @@
+//! Schema migrations
+//!
+//! When a link is put, the migrations not yet recorded in the history table
+//! are applied in version order before the pool is handed to the actor. Each
+//! migration runs in a transaction with its history row, where the database
+//! supports transactional DDL, and a lock is held meanwhile so providers
+//! linking to the same database at once don't apply them twice.
+
+use sha2::{Digest, Sha256};
+use sqlx::{any::AnyKind, pool::PoolConnection, Any, AnyPool, Connection, Executor, Row};
+use tracing::{error, info};
+use wasmbus_rpc::error::RpcError;
+
+use crate::{
+    config::{MigrationScript, MigrationsConfig},
+    executor::ident,
+};
+
+/// history table, when the config doesn't name one
+const DEFAULT_TABLE: &str = "_sqldb_migrations";
+
+/// Apply the pending migrations
+pub(crate) async fn migrate(pool: &AnyPool, config: &MigrationsConfig) -> Result<(), RpcError> {
+    let mut migrations = config.scripts.clone();
+    if let Some(dir) = &config.dir {
+        migrations.extend(read_dir(dir)?);
+    }
+    migrations.sort_by_key(|migration| migration.version);
+    if let Some(pair) = migrations
+        .windows(2)
+        .find(|pair| pair[0].version == pair[1].version)
+    {
+        return Err(init_error(format!(
+            "duplicate migration version {}",
+            pair[0].version
+        )));
+    }
+
+    let table = ident::identifier(config.table.as_deref().unwrap_or(DEFAULT_TABLE))
+        .map_err(|e| init_error(e.to_string()))?;
+    let dialect = MigrationDialect::of(pool.any_kind());
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+
+    conn.execute(dialect.lock(table).as_str())
+        .await
+        .map_err(db_error)?;
+    let result = apply(&mut conn, dialect, table, &migrations).await;
+    if let Err(unlock_err) = conn.execute(dialect.unlock(table).as_str()).await {
+        // the lock belongs to the session, so the connection is closed rather
+        // than returned to the pool still holding it
+        drop(conn.detach());
+        if result.is_ok() {
+            return Err(db_error(unlock_err));
+        }
+        // a failed migration matters more than the lock
+        error!(%unlock_err, "releasing the migration lock");
+    }
+    result
+}
+
+async fn apply(
+    conn: &mut PoolConnection<Any>,
+    dialect: MigrationDialect,
+    table: &str,
+    migrations: &[MigrationScript],
+) -> Result<(), RpcError> {
+    conn.execute(dialect.create_table(table).as_str())
+        .await
+        .map_err(db_error)?;
+    let applied = conn
+        .fetch_all(format!("select version, checksum from {}", table).as_str())
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(|row| Ok((row.try_get::<i64, _>(0)?, row.try_get::<String, _>(1)?)))
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(db_error)?;
+
+    for migration in migrations {
+        let checksum = checksum(&migration.sql);
+        match applied
+            .iter()
+            .find(|(version, _)| *version == migration.version)
+        {
+            Some((_, applied)) if *applied == checksum => continue,
+            Some(_) => {
+                return Err(init_error(format!(
+                    "migration {} was changed after it was applied",
+                    migration.version
+                )))
+            }
+            None => {}
+        }
+
+        info!(version = migration.version, description = %migration.description, "applying migration");
+        let mut tx = conn.begin().await.map_err(db_error)?;
+        tx.execute(migration.sql.as_str())
+            .await
+            .map_err(|e| init_error(format!("migration {} failed: {}", migration.version, e)))?;
+        let record = dialect.record(table);
+        sqlx::query(&record)
+            .bind(migration.version)
+            .bind(&migration.description)
+            .bind(&checksum)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Read the `<version>_<description>.sql` files of a directory
+fn read_dir(dir: &str) -> Result<Vec<MigrationScript>, RpcError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| init_error(format!("reading migrations in {}: {}", dir, e)))?;
+    let mut migrations = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| init_error(format!("reading migrations in {}: {}", dir, e)))?
+            .path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.ends_with(".sql") => name.trim_end_matches(".sql"),
+            _ => continue,
+        };
+        let (version, description) = name.split_once('_').unwrap_or((name, ""));
+        let version = version.parse().map_err(|_| {
+            init_error(format!(
+                "migration file {} isn't named <version>_<description>.sql",
+                path.display()
+            ))
+        })?;
+        let sql = std::fs::read_to_string(&path)
+            .map_err(|e| init_error(format!("reading {}: {}", path.display(), e)))?;
+        migrations.push(MigrationScript {
+            version,
+            description: description.replace('_', " "),
+            sql,
+        });
+    }
+    Ok(migrations)
+}
+
+/// Hex SHA-256 of the statements of a migration, to tell if an applied
+/// migration was changed
+fn checksum(sql: &str) -> String {
+    Sha256::digest(sql.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Statements managing the lock and history table in each database
+#[derive(Clone, Copy)]
+enum MigrationDialect {
+    Postgres,
+    MySql,
+    Mssql,
+}
+
+impl MigrationDialect {
+    fn of(kind: AnyKind) -> Self {
+        match kind {
+            AnyKind::Postgres => MigrationDialect::Postgres,
+            AnyKind::MySql => MigrationDialect::MySql,
+            AnyKind::Mssql => MigrationDialect::Mssql,
+        }
+    }
+
+    fn lock(self, table: &str) -> String {
+        match self {
+            MigrationDialect::Postgres => format!("select pg_advisory_lock({})", lock_key(table)),
+            MigrationDialect::MySql => format!("select get_lock('{}', -1)", table),
+            MigrationDialect::Mssql => format!(
+                "exec sp_getapplock @Resource = '{}', @LockMode = 'Exclusive', @LockOwner = 'Session'",
+                table
+            ),
+        }
+    }
+
+    fn unlock(self, table: &str) -> String {
+        match self {
+            MigrationDialect::Postgres => {
+                format!("select pg_advisory_unlock({})", lock_key(table))
+            }
+            MigrationDialect::MySql => format!("select release_lock('{}')", table),
+            MigrationDialect::Mssql => format!(
+                "exec sp_releaseapplock @Resource = '{}', @LockOwner = 'Session'",
+                table
+            ),
+        }
+    }
+
+    /// Statement recording an applied migration, binding its version,
+    /// description and checksum
+    fn record(self, table: &str) -> String {
+        let values = match self {
+            MigrationDialect::Postgres => "$1, $2, $3",
+            MigrationDialect::MySql => "?, ?, ?",
+            MigrationDialect::Mssql => "@p1, @p2, @p3",
+        };
+        format!(
+            "insert into {} (version, description, checksum) values ({})",
+            table, values
+        )
+    }
+
+    fn create_table(self, table: &str) -> String {
+        match self {
+            MigrationDialect::Postgres => format!(
+                "create table if not exists {} (version bigint primary key, \
+                 description text not null, checksum text not null, \
+                 installed_on timestamptz not null default now())",
+                table
+            ),
+            MigrationDialect::MySql => format!(
+                "create table if not exists {} (version bigint primary key, \
+                 description text not null, checksum varchar(64) not null, \
+                 installed_on timestamp not null default current_timestamp)",
+                table
+            ),
+            MigrationDialect::Mssql => format!(
+                "if object_id('{0}', 'U') is null create table {0} (version bigint primary key, \
+                 description nvarchar(max) not null, checksum varchar(64) not null, \
+                 installed_on datetime2 not null default sysutcdatetime())",
+                table
+            ),
+        }
+    }
+}
+
+/// Postgres advisory lock key for the history table
+fn lock_key(table: &str) -> i64 {
+    let digest = Sha256::digest(table.as_bytes());
+    i64::from_be_bytes(digest[..8].try_into().unwrap_or_default())
+}
+
+fn init_error(message: String) -> RpcError {
+    RpcError::ProviderInit(message)
+}
+
+fn db_error(err: sqlx::Error) -> RpcError {
+    RpcError::ProviderInit(format!("applying migrations: {}", err))
+}
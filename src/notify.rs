@@ -0,0 +1,79 @@
+//! Postgres notifications
+//!
+//! A link can listen to Postgres channels. The provider holds a dedicated
+//! connection for the link, outside of its pool, and forwards each
+//! notification to the actor with `SqlDbNotificationHandler.HandleNotification`.
+
+use std::time::Duration;
+
+use sqlx::postgres::PgListener;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+use wasmbus_rpc::{common::Context, core::LinkDefinition, provider::ProviderTransport};
+
+use crate::ext::{Notification, SqlDbNotificationHandler, SqlDbNotificationHandlerSender};
+
+/// time to wait before reconnecting after the listener fails
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Start forwarding the notifications of `channels` to the linked actor. The
+/// task runs until it is aborted, reconnecting when the connection is lost.
+pub(crate) fn spawn_listener(
+    uri: String,
+    channels: Vec<String>,
+    ld: LinkDefinition,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let mut listener = match connect(&uri, &channels).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    error!(actor_id = %ld.actor_id, %err, "listening to notifications");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+            // the listener reconnects by itself, an error means it couldn't
+            loop {
+                match listener.recv().await {
+                    Ok(notification) => {
+                        forward(
+                            &ld,
+                            Notification {
+                                channel: notification.channel().to_string(),
+                                payload: notification.payload().to_string(),
+                                process_id: notification.process_id(),
+                            },
+                        )
+                        .await
+                    }
+                    Err(err) => {
+                        error!(actor_id = %ld.actor_id, %err, "receiving notifications");
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+async fn connect(uri: &str, channels: &[String]) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect(uri).await?;
+    listener
+        .listen_all(channels.iter().map(String::as_str))
+        .await?;
+    Ok(listener)
+}
+
+async fn forward(ld: &LinkDefinition, notification: Notification) {
+    debug!(actor_id = %ld.actor_id, channel = %notification.channel, "forwarding notification");
+    let transport = ProviderTransport::new(ld, None);
+    let handler = SqlDbNotificationHandlerSender::via(transport);
+    if let Err(err) = handler
+        .handle_notification(&Context::default(), &notification)
+        .await
+    {
+        warn!(actor_id = %ld.actor_id, %err, "actor didn't handle notification");
+    }
+}
@@ -14,11 +14,14 @@ pub enum Error {
     CborDeType(minicbor::data::Type),
 
     #[error("CBOR int value out of range: `{0}`")]
-    CborDeIntOutOfRange(minicbor::data::Int),
+    CborDeIntOutOfRange(i128),
 
     #[error("CBOR u64 value out of range: `{0}`")]
     CborDeU64OutOfRange(u64),
 
+    #[error("invalid CBOR value: {0}")]
+    CborDeValue(String),
+
     #[error(transparent)]
     CborSer(#[from] minicbor::encode::Error<Infallible>),
 
@@ -31,30 +34,75 @@ pub enum Error {
     #[error("unsupported database type: `{0}`")]
     DbType(String),
 
+    #[error("unsupported database type: `{0}`, cast it to {1} in the query")]
+    DbTypeCast(String, &'static str),
+
+    #[error("invalid procedure call: {0}")]
+    InvalidCall(String),
+
+    #[error("invalid name: `{0}`")]
+    InvalidIdentifier(String),
+
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error(transparent)]
+    MsgPack(#[from] rmp_serde::encode::Error),
+
+    #[error("invalid tenant: {0}")]
+    Tenant(String),
+
+    #[error("throttled: {0}")]
+    Throttled(String),
+
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
 
     #[error(transparent)]
     Sqlx(#[from] sqlx::error::BoxDynError),
 
+    #[error("unsupported type hint: `{0}`")]
+    TypeHint(String),
+
+    #[error("type hint `{0}` is not supported by {1}")]
+    TypeHintNotSupported(&'static str, &'static str),
+
+    #[error("{0} is not supported")]
+    Unsupported(&'static str),
+
     #[error(transparent)]
     TimeFormat(#[from] time::error::Format),
+
+    #[error(transparent)]
+    TimeParse(#[from] time::error::Parse),
 }
 
 impl From<Error> for SqlDbError {
     fn from(err: Error) -> SqlDbError {
         match err {
-            Error::ConfigDatabaseNotSupported => SqlDbError::new("config", err.to_string()),
+            Error::ConfigDatabaseNotSupported | Error::Tenant(_) => {
+                SqlDbError::new("config", err.to_string())
+            }
+            Error::Throttled(_) => SqlDbError::new("throttled", err.to_string()),
             Error::CborDe(_)
             | Error::CborDeType(_)
             | Error::CborDeIntOutOfRange(_)
-            | Error::CborDeU64OutOfRange(_) => SqlDbError::new("decoding", err.to_string()),
-            Error::CborSer(_) | Error::SerdeJson(_) | Error::TimeFormat(_) => {
+            | Error::CborDeU64OutOfRange(_)
+            | Error::CborDeValue(_)
+            | Error::InvalidCall(_)
+            | Error::InvalidIdentifier(_)
+            | Error::InvalidRequest(_)
+            | Error::TimeParse(_)
+            | Error::TypeHint(_) => SqlDbError::new("decoding", err.to_string()),
+            Error::CborSer(_) | Error::MsgPack(_) | Error::SerdeJson(_) | Error::TimeFormat(_) => {
                 SqlDbError::new("encoding", err.to_string())
             }
-            Error::Db(_) | Error::DbType(_) | Error::Sqlx(_) => {
-                SqlDbError::new("db", err.to_string())
-            }
+            Error::Db(_)
+            | Error::DbType(_)
+            | Error::DbTypeCast(..)
+            | Error::Sqlx(_)
+            | Error::TypeHintNotSupported(..)
+            | Error::Unsupported(_) => SqlDbError::new("db", err.to_string()),
         }
     }
 }
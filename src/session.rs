@@ -0,0 +1,31 @@
+//! Session setup of new connections
+//!
+//! Each connection a link's pool opens has the link's session variables set,
+//! then its init SQL run, before it's used. Variables are set with bound
+//! values: `set_config` in Postgres, `SET SESSION` in MySQL, and the session
+//! context in SQL Server, which has no session variables of its own.
+
+use sqlx::{any::AnyKind, AnyConnection, Executor};
+
+use crate::config::SessionOptions;
+
+/// Set up the session of a new connection
+pub(crate) async fn init(conn: &mut AnyConnection, session: &SessionOptions) -> sqlx::Result<()> {
+    for (name, value) in &session.variables {
+        let sql = match conn.kind() {
+            AnyKind::Postgres => "select set_config($1, $2, false)".to_string(),
+            // names are checked when the config is loaded
+            AnyKind::MySql => format!("set session {} = ?", name),
+            AnyKind::Mssql => "exec sp_set_session_context @p1, @p2".to_string(),
+        };
+        let query = match conn.kind() {
+            AnyKind::MySql => sqlx::query(&sql),
+            _ => sqlx::query(&sql).bind(name),
+        };
+        query.bind(value).execute(&mut *conn).await?;
+    }
+    if let Some(sql) = &session.init_sql {
+        conn.execute(sql.as_str()).await?;
+    }
+    Ok(())
+}
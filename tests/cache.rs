@@ -0,0 +1,68 @@
+//! Tests of a link that caches query results
+
+#[macro_use]
+mod common;
+
+use wasmbus_rpc::provider::prelude::*;
+use wasmcloud_interface_sqldb::*;
+use wasmcloud_test_util::{provider_test::test_provider, testing::TestOptions};
+
+#[tokio::test]
+async fn run_all() {
+    run_all!("tests/cache_test_config.toml", cache_test);
+}
+
+/// test that query results are cached, and dropped by a write to their table
+async fn cache_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+
+    let resp = client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: "create table if not exists test_cached (id int4 primary key)".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: "delete from test_cached".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    let query = Statement {
+        sql: "select count(*), clock_timestamp()::text from test_cached".to_string(),
+        ..Default::default()
+    };
+
+    // the second query is answered from the cache, with the same timestamp
+    let first = client.query(&ctx, &query).await?;
+    assert!(first.error.is_none(), "{:?}", first.error);
+    let second = client.query(&ctx, &query).await?;
+    assert_eq!(first.rows, second.rows);
+
+    let resp = client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: "insert into test_cached values (1)".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    let third = client.query(&ctx, &query).await?;
+    assert!(third.error.is_none(), "{:?}", third.error);
+    assert_ne!(first.rows, third.rows);
+    let rows: Vec<(i64, String)> =
+        minicbor::decode(&third.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(rows[0].0, 1);
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Harness of the tests of a link with its own config. Each test binary starts
+//! the provider with the config it names, so that link's settings don't apply
+//! to the other tests.
+
+/// Starts the provider with the config at `$config`, runs the listed tests,
+/// and asserts that all of them passed
+macro_rules! run_all {
+    ( $config:expr, $($test:ident),+ $(,)? ) => {{
+        use wasmbus_rpc::error::RpcError;
+        use wasmcloud_test_util::{
+            cli::print_test_results,
+            provider_test::test_provider,
+            run_selected_spawn,
+            testing::{TestOptions, TestResult},
+        };
+
+        std::env::set_var("PROVIDER_TEST_CONFIG", $config);
+        let provider = test_provider().await;
+        // allow time for it to start up and finish linking before we send rpc
+        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+        let opts = TestOptions::default();
+        let res = run_selected_spawn!(opts, $($test),+);
+        print_test_results(&res);
+
+        let passed = res.iter().filter(|tr| tr.passed).count();
+        let total = res.len();
+        assert_eq!(passed, total, "{} passed out of {}", passed, total);
+
+        let _ = provider.shutdown().await;
+    }};
+}
@@ -0,0 +1,56 @@
+//! Tests of a link that sets `encoding.json` to "cbor"
+
+#[macro_use]
+mod common;
+
+use std::collections::BTreeMap;
+
+use wasmbus_rpc::provider::prelude::*;
+use wasmcloud_interface_sqldb::*;
+use wasmcloud_test_util::{
+    provider_test::{test_provider, Provider},
+    testing::TestOptions,
+};
+
+#[tokio::test]
+async fn run_all() {
+    run_all!("tests/json_test_config.toml", json_test);
+}
+
+/// test CBOR maps as jsonb parameters, and jsonb results transcoded to CBOR
+async fn json_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    json_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn json_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select $1::jsonb -> 'tags', $1::jsonb ->> 'name'".to_string(),
+                parameters: Some(vec![minicbor::to_vec(BTreeMap::from([
+                    ("name", vec!["cone"]),
+                    ("tags", vec!["cold", "sweet"]),
+                ]))
+                .unwrap()]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 1, "select should have returned 1 row");
+
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(2));
+    assert_eq!(d.array()?, Some(2));
+    assert_eq!(d.str()?, "cold");
+    assert_eq!(d.str()?, "sweet");
+    assert_eq!(d.str()?, r#"["cone"]"#);
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Tests of a link with limits
+
+#[macro_use]
+mod common;
+
+use wasmbus_rpc::{
+    common::{deserialize, serialize, Transport},
+    provider::prelude::*,
+};
+use wasmcloud_interface_sqldb::*;
+use wasmcloud_test_util::{provider_test::test_provider, testing::TestOptions};
+
+#[tokio::test]
+async fn run_all() {
+    run_all!("tests/limit_test_config.toml", limit_test);
+}
+
+/// response to SqlDbExt.Export and SqlDbExt.ExportNext
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportChunk {
+    export_id: String,
+    done: bool,
+    error: Option<SqlDbError>,
+}
+
+/// test that a statement over the link's concurrency limit is throttled, while
+/// an unfinished export holds the link's only place
+async fn limit_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov.clone());
+    let ctx = Context::default();
+    let stmt = Statement {
+        sql: "select 1".to_string(),
+        ..Default::default()
+    };
+
+    let arg = serde_json::json!({
+        "statement": { "sql": "select n from generate_series(1, 10000) n" },
+        "chunkSize": 4096,
+    });
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.Export",
+                arg: serialize(&arg)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let mut chunk: ExportChunk = deserialize(&resp)?;
+    assert!(chunk.error.is_none(), "{:?}", chunk.error);
+    assert!(!chunk.done, "export should take several chunks");
+
+    let resp = client.query(&ctx, &stmt).await?;
+    let error = resp.error.expect("query should be throttled");
+    assert_eq!(error.code, "throttled", "{:?}", error);
+
+    while !chunk.done {
+        let arg = serde_json::json!({ "exportId": chunk.export_id });
+        let resp = prov
+            .send(
+                &ctx,
+                Message {
+                    method: "SqlDbExt.ExportNext",
+                    arg: serialize(&arg)?.into(),
+                },
+                None,
+            )
+            .await?;
+        chunk = deserialize(&resp)?;
+        assert!(chunk.error.is_none(), "{:?}", chunk.error);
+    }
+
+    // the export's place is given back when it ends
+    let resp = client.query(&ctx, &stmt).await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! Tests of a link with migrations
+
+#[macro_use]
+mod common;
+
+use wasmbus_rpc::provider::prelude::*;
+use wasmcloud_interface_sqldb::*;
+use wasmcloud_test_util::{provider_test::test_provider, testing::TestOptions};
+
+#[tokio::test]
+async fn run_all() {
+    run_all!("tests/migrate_test_config.toml", migration_test);
+}
+
+/// test that the migrations in the link config were applied
+async fn migration_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+
+    let resp = client
+        .query(
+            &ctx,
+            &Statement {
+                sql: r#"select m.version, m.description, count(t.id)
+                from _sqldb_test_migrations m, test_migrated t
+                group by m.version, m.description"#
+                    .to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    let rows: Vec<(i64, String, i64)> =
+        minicbor::decode(&resp.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(rows, vec![(1, "test's migrations".to_string(), 1)]);
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! Tests of a link to SQL Server, whose driver reads few types itself. They
+//! need a SQL Server at the uri of the config, so they're run with
+//! `cargo test --test mssql -- --ignored`.
+
+#[macro_use]
+mod common;
+
+use wasmbus_rpc::{
+    common::{deserialize, serialize, Transport},
+    provider::prelude::*,
+};
+use wasmcloud_interface_sqldb::*;
+use wasmcloud_test_util::{
+    provider_test::{test_provider, Provider},
+    testing::TestOptions,
+};
+
+#[tokio::test]
+#[ignore = "needs a SQL Server"]
+async fn run_all() {
+    run_all!("tests/mssql_test_config.toml", multi_test, call_test);
+}
+
+/// response to SqlDbExt.QueryMulti
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MultiQueryResult {
+    result_sets: Vec<ResultSet>,
+    rows_affected: Vec<u64>,
+    error: Option<SqlDbError>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultSet {
+    num_rows: u64,
+    columns: Vec<Column>,
+    #[serde(with = "serde_bytes")]
+    rows: Vec<u8>,
+}
+
+async fn query_multi(prov: &Provider, sql: &str) -> RpcResult<MultiQueryResult> {
+    let stmt = Statement {
+        sql: sql.to_string(),
+        ..Default::default()
+    };
+    let resp = prov
+        .send(
+            &Context::default(),
+            Message {
+                method: "SqlDbExt.QueryMulti",
+                arg: serialize(&stmt)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: MultiQueryResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    Ok(result)
+}
+
+fn decode<'b, T: minicbor::Decode<'b, ()>>(rows: &'b [u8]) -> RpcResult<T> {
+    minicbor::decode(rows).map_err(|e| RpcError::Deser(e.to_string()))
+}
+
+/// test that QueryMulti reads date and time columns, of a single query that's
+/// converted and of a script that isn't
+async fn multi_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let result = query_multi(
+        &prov,
+        "select cast('2023-01-02T03:04:05' as datetime) as at, cast('2023-01-02' as date) as day",
+    )
+    .await?;
+    assert_eq!(result.result_sets.len(), 1);
+    assert!(result.rows_affected.is_empty());
+    let first = &result.result_sets[0];
+    assert_eq!(first.num_rows, 1);
+    assert_eq!(first.columns[0].db_type, "DATETIME");
+    assert_eq!(first.columns[1].db_type, "DATE");
+    let rows: Vec<(String, String)> = decode(&first.rows)?;
+    assert_eq!(
+        rows,
+        vec![("2023-01-02T03:04:05".to_string(), "2023-01-02".to_string())]
+    );
+
+    let result = query_multi(
+        &prov,
+        "select 1 as a; select cast('2023-01-02T03:04:05' as datetime) as at",
+    )
+    .await?;
+    assert_eq!(result.result_sets.len(), 2);
+    let rows: Vec<(String,)> = decode(&result.result_sets[1].rows)?;
+    assert_eq!(rows, vec![("2023-01-02T03:04:05".to_string(),)]);
+
+    Ok(())
+}
+
+/// argument of SqlDbExt.Call
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcedureCall {
+    procedure: String,
+    parameters: Vec<ProcedureParam>,
+    function: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcedureParam {
+    name: Option<String>,
+    mode: &'static str,
+    #[serde(with = "serde_bytes")]
+    value: Vec<u8>,
+    db_type: Option<String>,
+}
+
+/// response to SqlDbExt.Call
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallResult {
+    outputs: ResultSet,
+    result_sets: Vec<ResultSet>,
+    error: Option<SqlDbError>,
+}
+
+/// test calling a procedure with date and time output parameters and result
+/// set
+async fn call_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov.clone());
+    let ctx = Context::default();
+
+    client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: r#"create or alter procedure test_times
+                    @at datetime output, @total money output, @took time output
+                as begin
+                    select @at = '2023-01-02T03:04:05', @total = 12.5, @took = '01:02:03';
+                    select cast('2023-01-02T03:04:05' as datetime) as at;
+                end"#
+                    .to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let output = |name: &str, db_type: &str| ProcedureParam {
+        name: Some(name.to_string()),
+        mode: "out",
+        value: minicbor::to_vec(()).unwrap(),
+        db_type: Some(db_type.to_string()),
+    };
+    let call = ProcedureCall {
+        procedure: "test_times".to_string(),
+        parameters: vec![
+            output("at", "datetime"),
+            output("total", "money"),
+            output("took", "time"),
+        ],
+        function: false,
+    };
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.Call",
+                arg: serialize(&call)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: CallResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+
+    assert_eq!(result.outputs.columns[2].db_type, "TIME");
+    let outputs: Vec<(String, String, String)> = decode(&result.outputs.rows)?;
+    assert_eq!(
+        outputs,
+        vec![(
+            "2023-01-02T03:04:05".to_string(),
+            "12.5000".to_string(),
+            "01:02:03".to_string()
+        )]
+    );
+    assert_eq!(result.result_sets.len(), 1);
+    let rows: Vec<(String,)> = decode(&result.result_sets[0].rows)?;
+    assert_eq!(rows, vec![("2023-01-02T03:04:05".to_string(),)]);
+
+    client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: "drop procedure test_times".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(())
+}
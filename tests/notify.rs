@@ -0,0 +1,80 @@
+//! Tests of a link that listens to a Postgres channel. The test stands in for
+//! the actor, answering the provider's invocations on the lattice.
+
+#[macro_use]
+mod common;
+
+use futures::StreamExt;
+use wasmbus_rpc::{
+    common::{deserialize, serialize},
+    core::{Invocation, InvocationResponse},
+    provider::prelude::*,
+};
+use wasmcloud_interface_sqldb::*;
+use wasmcloud_test_util::{provider_test::test_provider, testing::TestOptions};
+
+#[tokio::test]
+async fn run_all() {
+    run_all!("tests/notify_test_config.toml", notify_test);
+}
+
+/// argument of SqlDbNotificationHandler.HandleNotification
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+    channel: String,
+    payload: String,
+}
+
+/// test that a NOTIFY on a channel the link listens to reaches the actor
+async fn notify_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let ctx = Context::default();
+
+    // the provider sends to actors on `wasmbus.rpc.{prefix}.{actor}`, with the
+    // test harness's lattice prefix
+    let nats = async_nats::connect("127.0.0.1:4222")
+        .await
+        .map_err(|e| RpcError::Other(e.to_string()))?;
+    let mut invocations = nats
+        .subscribe("wasmbus.rpc.*.*".into())
+        .await
+        .map_err(|e| RpcError::Other(e.to_string()))?;
+
+    let client = SqlDbSender::via(prov);
+    for (channel, payload) in [("test_other", "ignored"), ("test_notify", "hello")] {
+        let resp = client
+            .execute(
+                &ctx,
+                &Statement {
+                    sql: format!("notify {}, '{}'", channel, payload),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+    }
+
+    let message = tokio::time::timeout(std::time::Duration::from_secs(10), invocations.next())
+        .await
+        .map_err(|_| RpcError::Timeout("no notification".into()))?
+        .expect("subscription ended");
+    let invocation: Invocation = deserialize(&message.payload)?;
+    assert_eq!(
+        invocation.operation,
+        "SqlDbNotificationHandler.HandleNotification"
+    );
+    let notification: Notification = deserialize(&invocation.msg)?;
+    // only channels the link listens to are forwarded
+    assert_eq!(notification.channel, "test_notify");
+    assert_eq!(notification.payload, "hello");
+
+    if let Some(reply) = message.reply {
+        let mut response = InvocationResponse::default();
+        response.invocation_id = invocation.id;
+        nats.publish(reply, serialize(&response)?.into())
+            .await
+            .map_err(|e| RpcError::Other(e.to_string()))?;
+    }
+    Ok(())
+}
@@ -1,4 +1,16 @@
-use wasmbus_rpc::{minicbor::Decode, provider::prelude::*};
+use std::collections::BTreeMap;
+
+use arrow_array::{
+    cast::AsArray,
+    types::{Date32Type, Float64Type, Int32Type, TimestampMicrosecondType},
+};
+use arrow_ipc::reader::StreamReader;
+use arrow_schema::{DataType, TimeUnit};
+use wasmbus_rpc::{
+    common::{deserialize, serialize, Transport},
+    minicbor::Decode,
+    provider::prelude::*,
+};
 use wasmcloud_interface_sqldb::*;
 use wasmcloud_test_util::{
     check,
@@ -17,7 +29,31 @@ async fn run_all() {
     tokio::time::sleep(std::time::Duration::from_secs(3)).await;
 
     let opts = TestOptions::default();
-    let res = run_selected_spawn!(opts, health_check, query, flavor_test);
+    let res = run_selected_spawn!(
+        opts,
+        health_check,
+        query,
+        flavor_test,
+        range_test,
+        custom_type_test,
+        tag_test,
+        chunked_test,
+        int_test,
+        hint_test,
+        named_test,
+        empty_test,
+        describe_test,
+        returning_test,
+        multi_test,
+        call_test,
+        bulk_test,
+        export_test,
+        catalog_test,
+        row_format_test,
+        arrow_test,
+        session_test,
+        tenant_test
+    );
     print_test_results(&res);
 
     let passed = res.iter().filter(|tr| tr.passed).count();
@@ -170,7 +206,7 @@ async fn flavor_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result
     assert_eq!(resp.num_rows, 2, "select should have returned 2 rows");
     let rows: Vec<FlavorResult> = minicbor::decode(&resp.rows)?;
     assert_eq!(rows.len(), 2);
-    assert_eq!(&rows.get(0).unwrap().flavor, "Chocolate",);
+    assert_eq!(&rows.first().unwrap().flavor, "Chocolate",);
     assert_eq!(&rows.get(1).unwrap().flavor, "Mint Chocolate Chip",);
 
     let resp = client
@@ -243,3 +279,1280 @@ async fn flavor_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result
 
     Ok(())
 }
+
+/// test range types as parameters and in results
+async fn range_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    range_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn range_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    let bounds = minicbor::to_vec(BTreeMap::from([("lower", 1), ("upper", 10)])).unwrap();
+    // tag 27: ["int4range", {"lower": 1, "upper": 10}], a range hinted with its type
+    let mut range = vec![0xd8, 0x1b, 0x82];
+    range.extend(minicbor::to_vec("int4range").unwrap());
+    range.extend(&bounds);
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: r#"select $1 = int4range(1, 10),
+                    tstzrange('2023-01-01 00:00:00+00', '2023-01-02 00:00:00+00', '[]'),
+                    'empty'::daterange"#
+                    .to_string(),
+                parameters: Some(vec![range]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 1, "select should have returned 1 row");
+
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(3));
+    assert!(d.bool()?, "range parameter should equal int4range(1, 10)");
+
+    assert_eq!(d.map()?, Some(5));
+    assert_eq!(d.str()?, "empty");
+    assert!(!d.bool()?);
+    assert_eq!(d.str()?, "lower");
+    assert_eq!(d.str()?, "2023-01-01T00:00:00Z");
+    assert_eq!(d.str()?, "upper");
+    assert_eq!(d.str()?, "2023-01-02T00:00:00Z");
+    assert_eq!(d.str()?, "lower_inc");
+    assert!(d.bool()?);
+    assert_eq!(d.str()?, "upper_inc");
+    assert!(d.bool()?);
+
+    assert_eq!(d.map()?, Some(5));
+    assert_eq!(d.str()?, "empty");
+    assert!(d.bool()?);
+
+    // bounds past the year 9999 are an error, not a panic of the provider
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select daterange('9999-12-30', '10000-01-05')".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.error.map(|e| e.code).as_deref(), Some("db"));
+
+    // without the hint, a map with the keys of a range is still JSON
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select $1::jsonb ->> 'upper'".to_string(),
+                parameters: Some(vec![bounds]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.str()?, "10");
+
+    Ok(())
+}
+
+/// test enum, domain and composite types as parameters and in results
+async fn custom_type_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    custom_type_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn custom_type_queries(
+    ctx: &Context,
+    client: &SqlDbSender<Provider>,
+) -> Result<(), SqlDbError> {
+    for sql in [
+        "drop table if exists test_moods",
+        "drop type if exists test_pair",
+        "drop domain if exists test_posint",
+        "drop type if exists test_mood",
+        "create type test_mood as enum ('sad', 'ok', 'happy')",
+        "create domain test_posint as int4 check (value > 0)",
+        "create type test_pair as (label text, score test_posint)",
+        "create table test_moods (id test_posint not null, mood test_mood not null)",
+    ] {
+        client
+            .execute(
+                ctx,
+                &Statement {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+
+    // tag 27: ["test_mood", "happy"], an enum label hinted with its type
+    let mut happy = vec![0xd8, 0x1b, 0x82];
+    happy.extend(minicbor::to_vec("test_mood").unwrap());
+    happy.extend(minicbor::to_vec("happy").unwrap());
+
+    let resp = client
+        .execute(
+            ctx,
+            &Statement {
+                sql: "insert into test_moods (id, mood) values ($1, $2)".to_string(),
+                parameters: Some(vec![minicbor::to_vec(1).unwrap(), happy]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.rows_affected, 1, "1 rows inserted");
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: r#"select id, mood, row(mood::text, id)::test_pair from test_moods
+                    where mood = $1::test_mood"#
+                    .to_string(),
+                parameters: Some(vec![minicbor::to_vec("happy").unwrap()]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 1, "select should have returned 1 row");
+
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(3));
+    assert_eq!(d.i32()?, 1);
+    assert_eq!(d.str()?, "happy");
+    assert_eq!(d.map()?, Some(2));
+    assert_eq!(d.str()?, "label");
+    assert_eq!(d.str()?, "happy");
+    assert_eq!(d.str()?, "score");
+    assert_eq!(d.i32()?, 1);
+
+    for sql in [
+        "drop table test_moods",
+        "drop type test_pair",
+        "drop domain test_posint",
+        "drop type test_mood",
+    ] {
+        client
+            .execute(
+                ctx,
+                &Statement {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// test CBOR tagged parameters
+async fn tag_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    tag_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn tag_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    // tag 0: RFC3339 date/time string
+    let mut datetime = vec![0xc0];
+    datetime.extend(minicbor::to_vec("2023-01-01T00:00:00Z").unwrap());
+    // tag 37: UUID bytes
+    let mut uuid = vec![0xd8, 0x25, 0x50];
+    uuid.extend([
+        0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f, 0xe0,
+        0xc8,
+    ]);
+    // tag 4: decimal fraction [-2, 12345]
+    let decimal = vec![0xc4, 0x82, 0x21, 0x19, 0x30, 0x39];
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: r#"select $1::timestamptz = '2023-01-01 00:00:00+00'::timestamptz,
+                    $2::uuid::text, $3::numeric::text"#
+                    .to_string(),
+                parameters: Some(vec![datetime, uuid, decimal]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 1, "select should have returned 1 row");
+
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(3));
+    assert!(d.bool()?, "tagged date/time should bind as timestamptz");
+    assert_eq!(d.str()?, "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    assert_eq!(d.str()?, "123.45");
+
+    Ok(())
+}
+
+/// test indefinite-length (chunked) string and byte string parameters
+async fn chunked_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    chunked_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn chunked_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    // "hello" "world" as two chunks of an indefinite-length string
+    let text = vec![
+        0x7f, 0x65, b'h', b'e', b'l', b'l', b'o', 0x65, b'w', b'o', b'r', b'l', b'd', 0xff,
+    ];
+    // [1, 2] [3] as two chunks of an indefinite-length byte string
+    let bytes = vec![0x5f, 0x42, 0x01, 0x02, 0x41, 0x03, 0xff];
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select $1::text, $2::bytea".to_string(),
+                parameters: Some(vec![text, bytes]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 1, "select should have returned 1 row");
+
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(2));
+    assert_eq!(d.str()?, "helloworld");
+    assert_eq!(d.bytes()?, &[1, 2, 3]);
+
+    Ok(())
+}
+
+/// test the types integer parameters are narrowed to
+async fn int_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    int_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn int_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    let values: Vec<i64> = vec![
+        -32768,
+        32767,
+        32768,
+        -32769,
+        2147483647,
+        2147483648,
+        -2147483649,
+        i64::MAX,
+    ];
+    let mut parameters: Vec<Vec<u8>> = values
+        .iter()
+        .map(|value| minicbor::to_vec(value).unwrap())
+        .collect();
+    // 1 encoded as a 32 bit unsigned integer
+    parameters.push(vec![0x1a, 0x00, 0x00, 0x00, 0x01]);
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select $1, $2, $3, $4, $5, $6, $7, $8, $9".to_string(),
+                parameters: Some(parameters),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    let types: Vec<&str> = resp
+        .columns
+        .iter()
+        .map(|column| column.db_type.as_str())
+        .collect();
+    assert_eq!(
+        types,
+        ["INT2", "INT2", "INT4", "INT4", "INT4", "INT8", "INT8", "INT8", "INT2"]
+    );
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(9));
+    for value in values {
+        assert_eq!(d.i64()?, value);
+    }
+    assert_eq!(d.i64()?, 1);
+
+    // beyond i64 as a u64, and below i64
+    for (value, message) in [
+        (
+            vec![0x1b, 0x80, 0, 0, 0, 0, 0, 0, 0],
+            "u64 value out of range",
+        ),
+        (
+            vec![0x3b, 0x80, 0, 0, 0, 0, 0, 0, 0],
+            "int value out of range",
+        ),
+    ] {
+        let resp = client
+            .query(
+                ctx,
+                &Statement {
+                    sql: "select $1".to_string(),
+                    parameters: Some(vec![value]),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let error = resp
+            .error
+            .expect("out of range integers should be rejected");
+        assert_eq!(error.code, "decoding");
+        assert!(error.message.contains(message), "{}", error.message);
+    }
+
+    Ok(())
+}
+
+/// test parameter type hints
+async fn hint_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    hint_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn hint_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    // tag 27: ["int8", 1]
+    let mut int8 = vec![0xd8, 0x1b, 0x82];
+    int8.extend(minicbor::to_vec("int8").unwrap());
+    int8.push(0x01);
+    // tag 27: ["text", 42]
+    let mut text = vec![0xd8, 0x1b, 0x82];
+    text.extend(minicbor::to_vec("text").unwrap());
+    text.extend(minicbor::to_vec(42u8).unwrap());
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select pg_typeof($1)::text, $2".to_string(),
+                parameters: Some(vec![int8, text]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 1, "select should have returned 1 row");
+
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(2));
+    assert_eq!(d.str()?, "bigint");
+    assert_eq!(d.str()?, "42");
+
+    // a hint that isn't a type is rejected by the server
+    let mut unknown = vec![0xd8, 0x1b, 0x82];
+    unknown.extend(minicbor::to_vec("int3").unwrap());
+    unknown.push(0x01);
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select $1".to_string(),
+                parameters: Some(vec![unknown]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.error.map(|e| e.code).as_deref(), Some("db"));
+
+    Ok(())
+}
+
+/// test named parameters
+async fn named_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    named_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn named_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    // tag 28272: {"name": "hello", "n": 7}
+    let mut params = vec![0xd9, 0x6e, 0x70, 0xa2];
+    params.extend(minicbor::to_vec("name").unwrap());
+    params.extend(minicbor::to_vec("hello").unwrap());
+    params.extend(minicbor::to_vec("n").unwrap());
+    params.extend(minicbor::to_vec(7u8).unwrap());
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select :name::text, ':name', :n::int4 + :n::int4".to_string(),
+                parameters: Some(vec![params.clone()]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 1, "select should have returned 1 row");
+
+    let mut d = minicbor::Decoder::new(&resp.rows);
+    assert_eq!(d.array()?, Some(1));
+    assert_eq!(d.array()?, Some(3));
+    assert_eq!(d.str()?, "hello");
+    assert_eq!(d.str()?, ":name");
+    assert_eq!(d.i32()?, 14);
+
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select :name::text, $1::text".to_string(),
+                parameters: Some(vec![params]),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(
+        resp.error.map(|e| e.code).as_deref(),
+        Some("decoding"),
+        "named and positional placeholders can't be mixed"
+    );
+
+    Ok(())
+}
+
+/// test columns are described for an empty result set
+async fn empty_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+    empty_queries(&ctx, &client).await?;
+    Ok(())
+}
+
+async fn empty_queries(ctx: &Context, client: &SqlDbSender<Provider>) -> Result<(), SqlDbError> {
+    let resp = client
+        .query(
+            ctx,
+            &Statement {
+                sql: "select 1::int4 as one, 'a'::text as two where false".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(resp.num_rows, 0, "select should have returned no rows");
+    assert_eq!(resp.columns.len(), 2);
+    assert_eq!(resp.columns[0].name, "one");
+    assert_eq!(resp.columns[0].db_type, "INT4");
+    assert_eq!(resp.columns[1].name, "two");
+    assert_eq!(resp.columns[1].db_type, "TEXT");
+
+    Ok(())
+}
+
+/// test extended column metadata from SqlDbExt.Describe
+async fn describe_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let ctx = Context::default();
+
+    let stmt = Statement {
+        sql: "select typname, typlen, 1 as one from pg_catalog.pg_type".to_string(),
+        ..Default::default()
+    };
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.Describe",
+                arg: serialize(&stmt)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: serde_json::Value = deserialize(&resp)?;
+    let columns = result["columns"].as_array().unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns[0]["name"], "typname");
+    assert_eq!(columns[0]["nullable"], false);
+    // sqlx doesn't expose the source of a Postgres column
+    assert!(columns[0].get("sourceTable").is_none());
+    assert_eq!(columns[1]["dbType"], "INT2");
+    assert_eq!(columns[2]["name"], "one");
+
+    Ok(())
+}
+
+/// response to SqlDbExt.ExecuteReturning
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReturningResult {
+    rows_affected: u64,
+    num_rows: u64,
+    columns: Vec<Column>,
+    #[serde(with = "serde_bytes")]
+    rows: Vec<u8>,
+    error: Option<SqlDbError>,
+}
+
+/// test that ExecuteReturning returns generated keys
+async fn returning_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov.clone());
+    let ctx = Context::default();
+
+    for sql in [
+        "drop table if exists test_returning",
+        "create table test_returning (id serial primary key, name text not null)",
+    ] {
+        client
+            .execute(
+                &ctx,
+                &Statement {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+
+    let arg = serde_json::json!({
+        "statement": {
+            "sql": "insert into test_returning (name) values ('a'), ('b');",
+        },
+        "keyColumns": ["id"],
+    });
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.ExecuteReturning",
+                arg: serialize(&arg)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: ReturningResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    assert_eq!(result.rows_affected, 2);
+    assert_eq!(result.num_rows, 2);
+    assert_eq!(result.columns[0].name, "id");
+    let ids: Vec<(i32,)> =
+        minicbor::decode(&result.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(ids, vec![(1,), (2,)]);
+
+    client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: "drop table test_returning".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+/// response to SqlDbExt.QueryMulti
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MultiQueryResult {
+    result_sets: Vec<ResultSet>,
+    rows_affected: Vec<u64>,
+    error: Option<SqlDbError>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultSet {
+    num_rows: u64,
+    columns: Vec<Column>,
+    #[serde(with = "serde_bytes")]
+    rows: Vec<u8>,
+}
+
+/// test that QueryMulti returns each result set with its own columns
+async fn multi_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let ctx = Context::default();
+
+    let stmt = Statement {
+        sql: "select 1 as a; select 'x' as b, 2 as c; select 1 where false".to_string(),
+        ..Default::default()
+    };
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.QueryMulti",
+                arg: serialize(&stmt)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: MultiQueryResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    assert_eq!(result.result_sets.len(), 3);
+    assert!(result.rows_affected.is_empty());
+
+    let first = &result.result_sets[0];
+    assert_eq!(first.num_rows, 1);
+    assert_eq!(first.columns.len(), 1);
+    assert_eq!(first.columns[0].name, "a");
+    let second = &result.result_sets[1];
+    assert_eq!(second.columns.len(), 2);
+    let rows: Vec<(String, i32)> =
+        minicbor::decode(&second.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(rows, vec![("x".to_string(), 2)]);
+    // a query without rows is an empty result set with its columns
+    let third = &result.result_sets[2];
+    assert_eq!(third.num_rows, 0);
+    assert_eq!(third.columns.len(), 1);
+    assert_eq!(third.rows, vec![0x80]);
+
+    Ok(())
+}
+
+/// argument of SqlDbExt.Call
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcedureCall {
+    procedure: String,
+    parameters: Vec<ProcedureParam>,
+    function: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcedureParam {
+    name: Option<String>,
+    mode: &'static str,
+    #[serde(with = "serde_bytes")]
+    value: Vec<u8>,
+    db_type: Option<String>,
+}
+
+/// response to SqlDbExt.Call
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallResult {
+    outputs: ResultSet,
+    #[serde(with = "serde_bytes", default)]
+    return_value: Option<Vec<u8>>,
+    error: Option<SqlDbError>,
+}
+
+async fn send_call(prov: &Provider, call: &ProcedureCall) -> RpcResult<CallResult> {
+    let resp = prov
+        .send(
+            &Context::default(),
+            Message {
+                method: "SqlDbExt.Call",
+                arg: serialize(call)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: CallResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    Ok(result)
+}
+
+/// test calling a procedure with an inout parameter, and a function
+async fn call_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov.clone());
+    let ctx = Context::default();
+
+    client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: r#"create or replace procedure test_add(a int4, inout total int4)
+                language plpgsql as $$ begin total := total + a; end $$"#
+                    .to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let encode = |value: i32| minicbor::to_vec(value).unwrap();
+    let result = send_call(
+        &prov,
+        &ProcedureCall {
+            procedure: "test_add".to_string(),
+            parameters: vec![
+                ProcedureParam {
+                    name: None,
+                    mode: "in",
+                    value: encode(2),
+                    db_type: None,
+                },
+                ProcedureParam {
+                    name: Some("total".to_string()),
+                    mode: "inout",
+                    value: encode(40),
+                    db_type: None,
+                },
+            ],
+            function: false,
+        },
+    )
+    .await?;
+    assert_eq!(result.outputs.columns[0].name, "total");
+    let outputs: Vec<(i32,)> =
+        minicbor::decode(&result.outputs.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(outputs, vec![(42,)]);
+
+    let result = send_call(
+        &prov,
+        &ProcedureCall {
+            procedure: "abs".to_string(),
+            parameters: vec![ProcedureParam {
+                name: None,
+                mode: "in",
+                value: encode(-3),
+                db_type: Some("int4".to_string()),
+            }],
+            function: true,
+        },
+    )
+    .await?;
+    assert_eq!(result.return_value, Some(encode(3)));
+
+    client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: "drop procedure test_add".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+/// argument of SqlDbExt.BulkInsert
+#[derive(serde::Serialize)]
+struct BulkInsert {
+    table: String,
+    columns: Vec<String>,
+    #[serde(with = "serde_bytes")]
+    rows: Vec<u8>,
+}
+
+/// response to SqlDbExt.BulkInsert
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BulkInsertResult {
+    rows_affected: u64,
+    error: Option<SqlDbError>,
+}
+
+/// test that BulkInsert copies rows into a table
+async fn bulk_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov.clone());
+    let ctx = Context::default();
+
+    for sql in [
+        "drop table if exists test_bulk",
+        "create table test_bulk (id int8 not null, name text, price numeric(10, 2))",
+    ] {
+        client
+            .execute(
+                &ctx,
+                &Statement {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+
+    let rows: Vec<(u32, Option<String>, f64)> = (1..=1000)
+        .map(|i| {
+            (
+                i,
+                (i % 2 == 0).then(|| format!("item {}", i)),
+                i as f64 / 4.0,
+            )
+        })
+        .collect();
+    let arg = BulkInsert {
+        table: "test_bulk".to_string(),
+        columns: vec!["id".to_string(), "name".to_string(), "price".to_string()],
+        rows: minicbor::to_vec(&rows).map_err(|e| RpcError::Ser(e.to_string()))?,
+    };
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.BulkInsert",
+                arg: serialize(&arg)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: BulkInsertResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    assert_eq!(result.rows_affected, 1000);
+
+    let resp = client
+        .query(
+            &ctx,
+            &Statement {
+                sql: "select count(name), sum(price)::text from test_bulk".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    let totals: Vec<(i64, String)> =
+        minicbor::decode(&resp.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(totals, vec![(500, "125125.00".to_string())]);
+
+    client
+        .execute(
+            &ctx,
+            &Statement {
+                sql: "drop table test_bulk".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+/// response to SqlDbExt.Export and SqlDbExt.ExportNext
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportChunk {
+    export_id: String,
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+    done: bool,
+    error: Option<SqlDbError>,
+}
+
+/// test that Export returns CSV data in chunks
+async fn export_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let ctx = Context::default();
+
+    let arg = serde_json::json!({
+        "statement": { "sql": "select n, 'row ' || n as name from generate_series(1, 1000) n" },
+        "header": true,
+        "chunkSize": 4096,
+    });
+    let mut message = Message {
+        method: "SqlDbExt.Export",
+        arg: serialize(&arg)?.into(),
+    };
+    let mut data = Vec::new();
+    let mut chunks = 0;
+    loop {
+        let resp = prov.send(&ctx, message, None).await?;
+        let chunk: ExportChunk = deserialize(&resp)?;
+        assert!(chunk.error.is_none(), "{:?}", chunk.error);
+        data.extend_from_slice(&chunk.data);
+        if chunk.done {
+            break;
+        }
+        chunks += 1;
+        message = Message {
+            method: "SqlDbExt.ExportNext",
+            arg: serialize(&serde_json::json!({ "exportId": chunk.export_id }))?.into(),
+        };
+    }
+    assert!(chunks > 1, "should be sent in several chunks");
+
+    let csv = String::from_utf8(data).map_err(|e| RpcError::Deser(e.to_string()))?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 1001);
+    assert_eq!(lines[0], "n,name");
+    assert_eq!(lines[1000], "1000,row 1000");
+    Ok(())
+}
+
+/// test listing tables and describing one with its indexes and foreign keys
+async fn catalog_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov.clone());
+    let ctx = Context::default();
+
+    for sql in [
+        "drop table if exists test_catalog_line",
+        "drop table if exists test_catalog_order",
+        "create table test_catalog_order (id int4 primary key, placed date not null)",
+        r#"create table test_catalog_line (
+            order_id int4 not null references test_catalog_order (id),
+            line int2 not null,
+            note varchar(40),
+            amount numeric(10, 2) default 0,
+            primary key (order_id, line))"#,
+        "create index test_catalog_line_note on test_catalog_line (note)",
+    ] {
+        let resp = client
+            .execute(
+                &ctx,
+                &Statement {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+    }
+
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.ListSchemas",
+                arg: serialize(&())?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: serde_json::Value = deserialize(&resp)?;
+    let schemas = result["schemas"].as_array().unwrap();
+    assert!(schemas.contains(&"public".into()));
+    assert!(!schemas.contains(&"pg_catalog".into()));
+
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.ListTables",
+                arg: serialize(&serde_json::json!({ "schema": "public" }))?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: serde_json::Value = deserialize(&resp)?;
+    let tables = result["tables"].as_array().unwrap();
+    assert!(tables.contains(&serde_json::json!({
+        "schema": "public",
+        "name": "test_catalog_line",
+        "kind": "table",
+    })));
+
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.DescribeTable",
+                arg: serialize(&serde_json::json!({ "table": "test_catalog_line" }))?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: serde_json::Value = deserialize(&resp)?;
+    let table = &result["table"];
+    assert_eq!(table["schema"], "public");
+    let columns = table["columns"].as_array().unwrap();
+    assert_eq!(columns.len(), 4);
+    assert_eq!(columns[1]["name"], "line");
+    assert_eq!(columns[1]["dataType"], "smallint");
+    assert_eq!(columns[1]["nullable"], false);
+    assert_eq!(columns[2]["maxLength"], 40);
+    assert_eq!(columns[2]["nullable"], true);
+    assert_eq!(columns[3]["precision"], 10);
+    assert_eq!(columns[3]["scale"], 2);
+    assert_eq!(columns[3]["default"], "0");
+    assert_eq!(table["primaryKey"], serde_json::json!(["order_id", "line"]));
+    let indexes = table["indexes"].as_array().unwrap();
+    assert_eq!(indexes.len(), 2);
+    assert!(indexes.contains(&serde_json::json!({
+        "name": "test_catalog_line_note",
+        "columns": ["note"],
+        "unique": false,
+        "primary": false,
+    })));
+    let foreign_keys = table["foreignKeys"].as_array().unwrap();
+    assert_eq!(foreign_keys.len(), 1);
+    assert_eq!(foreign_keys[0]["columns"], serde_json::json!(["order_id"]));
+    assert_eq!(foreign_keys[0]["referencedTable"], "test_catalog_order");
+    assert_eq!(
+        foreign_keys[0]["referencedColumns"],
+        serde_json::json!(["id"])
+    );
+
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.DescribeTable",
+                arg: serialize(&serde_json::json!({ "table": "test_catalog_missing" }))?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: serde_json::Value = deserialize(&resp)?;
+    assert!(result.get("table").is_none());
+    assert!(result.get("error").is_none());
+    Ok(())
+}
+
+/// test returning rows as JSON and MessagePack
+async fn row_format_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let ctx = Context::default();
+
+    let statement = Statement {
+        sql: r#"select 1::int4 as n, 'one' as name, '\x0102'::bytea as data,
+            null::int4 as missing, 'NaN'::float8 as nan"#
+            .to_string(),
+        ..Default::default()
+    };
+    let query_as = |format: &str| -> RpcResult<Message<'static>> {
+        let arg = serde_json::json!({ "statement": statement, "format": format });
+        Ok(Message {
+            method: "SqlDbExt.QueryAs",
+            arg: serialize(&arg)?.into(),
+        })
+    };
+
+    let resp = prov.send(&ctx, query_as("json")?, None).await?;
+    let result: QueryResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    assert_eq!(result.num_rows, 1);
+    let rows: serde_json::Value =
+        serde_json::from_slice(&result.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(rows, serde_json::json!([[1, "one", "AQI=", null, "NaN"]]));
+
+    let resp = prov.send(&ctx, query_as("msgpack")?, None).await?;
+    let result: QueryResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    let rows: Vec<(i32, String, serde_bytes::ByteBuf, Option<i32>, f64)> =
+        rmp_serde::from_slice(&result.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(rows.len(), 1);
+    assert_eq!((rows[0].0, rows[0].1.as_str()), (1, "one"));
+    assert_eq!(rows[0].2.as_slice(), &[1, 2]);
+    assert_eq!(rows[0].3, None);
+    assert!(rows[0].4.is_nan());
+    Ok(())
+}
+
+/// test returning rows as an Arrow IPC stream
+async fn arrow_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let ctx = Context::default();
+
+    let arg = serde_json::json!({
+        "statement": {
+            "sql": r#"select n::int4 as arrow_n, nullif('row ' || n, 'row 2') as arrow_name,
+                n % 2 = 0 as arrow_even, n * 0.5::float8 as arrow_half,
+                date '2000-01-01' + n as arrow_date,
+                timestamptz '2000-01-01 00:00:00Z' + n * interval '1 second' as arrow_at
+                from generate_series(1, 3) n"#
+        },
+        "format": "arrow",
+    });
+    let resp = prov
+        .send(
+            &ctx,
+            Message {
+                method: "SqlDbExt.QueryAs",
+                arg: serialize(&arg)?.into(),
+            },
+            None,
+        )
+        .await?;
+    let result: QueryResult = deserialize(&resp)?;
+    assert!(result.error.is_none(), "{:?}", result.error);
+    assert_eq!(result.num_rows, 3);
+
+    // the stream is read back with the Arrow implementation
+    let reader = StreamReader::try_new(result.rows.as_slice(), None)
+        .map_err(|e| RpcError::Deser(e.to_string()))?;
+    let schema = reader.schema();
+    let types: Vec<(&str, &DataType)> = schema
+        .fields()
+        .iter()
+        .map(|field| (field.name().as_str(), field.data_type()))
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("arrow_n", &DataType::Int32),
+            ("arrow_name", &DataType::Utf8),
+            ("arrow_even", &DataType::Boolean),
+            ("arrow_half", &DataType::Float64),
+            ("arrow_date", &DataType::Date32),
+            (
+                "arrow_at",
+                &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+            ),
+        ]
+    );
+    let batches = reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 3);
+    assert_eq!(
+        batch.column(0).as_primitive::<Int32Type>().values(),
+        &[1, 2, 3]
+    );
+    let names = batch.column(1).as_string::<i32>();
+    assert_eq!(
+        names.iter().collect::<Vec<_>>(),
+        vec![Some("row 1"), None, Some("row 3")]
+    );
+    let even = batch.column(2).as_boolean();
+    assert_eq!(
+        even.iter().collect::<Vec<_>>(),
+        vec![Some(false), Some(true), Some(false)]
+    );
+    assert_eq!(
+        batch.column(3).as_primitive::<Float64Type>().values(),
+        &[0.5, 1.0, 1.5]
+    );
+    // days and microseconds since the epoch
+    assert_eq!(
+        batch.column(4).as_primitive::<Date32Type>().values(),
+        &[10958, 10959, 10960]
+    );
+    assert_eq!(
+        batch
+            .column(5)
+            .as_primitive::<TimestampMicrosecondType>()
+            .values(),
+        &[
+            946_684_801_000_000,
+            946_684_802_000_000,
+            946_684_803_000_000
+        ]
+    );
+    Ok(())
+}
+
+/// test that the session setup of the link config was applied
+async fn session_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+
+    let resp = client
+        .query(
+            &ctx,
+            &Statement {
+                sql: r#"select current_setting('TimeZone'), application_name::text
+                from pg_stat_activity where pid = pg_backend_pid()"#
+                    .to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    let rows: Vec<(String, String)> =
+        minicbor::decode(&resp.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert_eq!(rows[0].0, "UTC");
+    assert!(rows[0].1.starts_with("sqldb-sqlx "), "{}", rows[0].1);
+    assert!(rows[0].1.len() > "sqldb-sqlx ".len(), "{}", rows[0].1);
+    Ok(())
+}
+
+/// test that statements naming a tenant run in its schema
+async fn tenant_test(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+    let client = SqlDbSender::via(prov);
+    let ctx = Context::default();
+
+    for sql in [
+        "create schema if not exists tenant_a",
+        "create schema if not exists tenant_b",
+        "create table if not exists tenant_a.tenant_items (name text)",
+        "create table if not exists tenant_b.tenant_items (name text)",
+        "delete from tenant_a.tenant_items",
+        "delete from tenant_b.tenant_items",
+    ] {
+        let resp = client
+            .execute(
+                &ctx,
+                &Statement {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert!(resp.error.is_none(), "{}: {:?}", sql, resp.error);
+    }
+    for tenant in ["tenant_a", "tenant_b"] {
+        let resp = client
+            .execute(
+                &ctx,
+                &Statement {
+                    database: Some(tenant.to_string()),
+                    sql: format!("insert into tenant_items values ('{}')", tenant),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+    }
+
+    let query = |database: Option<&str>, sql: &str| Statement {
+        database: database.map(String::from),
+        sql: sql.to_string(),
+        ..Default::default()
+    };
+    for tenant in ["tenant_a", "tenant_b"] {
+        let resp = client
+            .query(&ctx, &query(Some(tenant), "select name from tenant_items"))
+            .await?;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        let rows: Vec<(String,)> =
+            minicbor::decode(&resp.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+        assert_eq!(rows, vec![(tenant.to_string(),)]);
+    }
+
+    // a failed statement keeps its own error
+    let resp = client
+        .query(
+            &ctx,
+            &query(Some("tenant_a"), "select * from tenant_missing"),
+        )
+        .await?;
+    let error = resp.error.expect("statement should fail");
+    assert!(error.message.contains("tenant_missing"), "{:?}", error);
+
+    // connections are switched back once the statement is done
+    let resp = client
+        .query(&ctx, &query(None, "select current_setting('search_path')"))
+        .await?;
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    let rows: Vec<(String,)> =
+        minicbor::decode(&resp.rows).map_err(|e| RpcError::Deser(e.to_string()))?;
+    assert!(!rows[0].0.contains("tenant_"), "{}", rows[0].0);
+    Ok(())
+}